@@ -6,152 +6,198 @@ pub use pulldown_cmark::HeadingLevel;
 pub use pulldown_cmark::LinkType;
 pub use pulldown_cmark::MetadataBlockKind;
 
-#[derive(Debug, Clone)]
-pub struct Document {
-    pub blocks: Vec<Block>,
-}
-
-#[derive(Debug, Clone)]
-pub enum Block {
-    Metadata(MetadataBlock),
-    Heading(Heading),
-    Paragraph(Paragraph),
-    BlockQuote(BlockQuote),
-    CodeBlock(CodeBlock),
-    HtmlBlock(HtmlBlock),
-    FootnoteDefinition(FootnoteDefinition),
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Document<'a> {
+    pub blocks: Vec<Block<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub enum Block<'a> {
+    Metadata(MetadataBlock<'a>),
+    Heading(Heading<'a>),
+    Paragraph(Paragraph<'a>),
+    BlockQuote(BlockQuote<'a>),
+    CodeBlock(CodeBlock<'a>),
+    HtmlBlock(HtmlBlock<'a>),
+    FootnoteDefinition(FootnoteDefinition<'a>),
     Rule,
-    List(List),
-    DefinitionList(DefinitionList),
-    Table(Table),
+    List(List<'a>),
+    DefinitionList(DefinitionList<'a>),
+    Table(Table<'a>),
 }
 
-#[derive(Debug, Clone)]
-pub struct MetadataBlock {
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct MetadataBlock<'a> {
     pub kind: MetadataBlockKind,
-    pub content: CowStr<'static>,
+    pub content: CowStr<'a>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Heading {
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Heading<'a> {
     pub level: HeadingLevel,
-    pub id: Option<CowStr<'static>>,
-    pub classes: Vec<CowStr<'static>>,
-    pub attrs: Vec<(CowStr<'static>, Option<CowStr<'static>>)>,
-    pub body: Vec<Inline>,
-}
-
-#[derive(Debug, Clone)]
-pub struct Paragraph {
-    pub body: Vec<Inline>,
-}
-
-#[derive(Debug, Clone)]
-pub enum Inline {
-    Text(CowStr<'static>),
-    Image(Image),
-    Link(Link),
-    Html(CowStr<'static>),
+    pub id: Option<CowStr<'a>>,
+    pub classes: Vec<CowStr<'a>>,
+    pub attrs: Vec<(CowStr<'a>, Option<CowStr<'a>>)>,
+    pub body: Vec<Inline<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Paragraph<'a> {
+    pub body: Vec<Inline<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub enum Inline<'a> {
+    Text(CowStr<'a>),
+    Image(Image<'a>),
+    Link(Link<'a>),
+    Html(CowStr<'a>),
     SoftBreak,
     HardBreak,
-    InlineMath(CowStr<'static>),
-    DisplayMath(CowStr<'static>),
-    Code(CowStr<'static>),
-    FootnoteReference(CowStr<'static>),
-    TasklistMarker(bool),
-    Emphasis(Vec<Inline>),
-    Strong(Vec<Inline>),
-    Strikethrough(Vec<Inline>),
-    InlineBlock(Block),
-}
-
-#[derive(Debug, Clone)]
-pub struct Image {
+    InlineMath(CowStr<'a>),
+    DisplayMath(CowStr<'a>),
+    Code(CowStr<'a>),
+    FootnoteReference(CowStr<'a>),
+    /// A task list marker such as `[x]`, holding the character between the brackets
+    ///
+    /// `' '` and `'x'` are the unticked and ticked states which GFM knows
+    /// about, but any other character is kept as-is.
+    TasklistMarker(char),
+    Emphasis(Vec<Inline<'a>>),
+    Strong(Vec<Inline<'a>>),
+    Strikethrough(Vec<Inline<'a>>),
+    InlineBlock(Block<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Image<'a> {
     pub link_type: LinkType,
-    pub dest_url: CowStr<'static>,
-    pub title: CowStr<'static>,
-    pub id: CowStr<'static>,
-    pub body: Vec<Inline>,
+    pub dest_url: CowStr<'a>,
+    pub title: CowStr<'a>,
+    pub id: CowStr<'a>,
+    pub body: Vec<Inline<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Link {
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Link<'a> {
     pub link_type: LinkType,
-    pub dest_url: CowStr<'static>,
-    pub title: CowStr<'static>,
-    pub id: CowStr<'static>,
-    pub body: Vec<Inline>,
+    pub dest_url: CowStr<'a>,
+    pub title: CowStr<'a>,
+    pub id: CowStr<'a>,
+    pub body: Vec<Inline<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct BlockQuote {
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct BlockQuote<'a> {
     pub kind: Option<BlockQuoteKind>,
-    pub body: Vec<Block>,
+    pub body: Vec<Block<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct CodeBlock {
-    pub kind: CodeBlockKind<'static>,
-    pub body: Vec<Inline>,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct CodeBlock<'a> {
+    pub kind: CodeBlockKind<'a>,
+    pub body: Vec<Inline<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct HtmlBlock {
-    pub body: Vec<CowStr<'static>>,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct HtmlBlock<'a> {
+    pub body: Vec<CowStr<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct FootnoteDefinition {
-    pub label: CowStr<'static>,
-    pub body: Vec<Block>,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct FootnoteDefinition<'a> {
+    pub label: CowStr<'a>,
+    pub body: Vec<Block<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct List {
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct List<'a> {
     pub start: Option<u64>,
-    pub items: Vec<ListItem>,
+    pub items: Vec<ListItem<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub enum ListItem {
-    Inline(Vec<Inline>),
-    Block(Vec<Block>),
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub enum ListItem<'a> {
+    Inline(Vec<Inline<'a>>),
+    Block(Vec<Block<'a>>),
 }
 
-#[derive(Debug, Clone)]
-pub struct DefinitionList {
-    pub items: Vec<DefinitionItem>,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct DefinitionList<'a> {
+    pub items: Vec<DefinitionItem<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct DefinitionItem {
-    pub title: Vec<Inline>,
-    pub definitions: Vec<DefinitionDefinition>,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct DefinitionItem<'a> {
+    pub title: Vec<Inline<'a>>,
+    pub definitions: Vec<DefinitionDefinition<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct DefinitionDefinition {
-    pub body: Vec<Inline>,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct DefinitionDefinition<'a> {
+    pub body: Vec<Inline<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Table {
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Table<'a> {
     pub alignments: Vec<Alignment>,
-    pub header: TableHead,
-    pub rows: Vec<TableRow>,
+    pub header: TableHead<'a>,
+    pub rows: Vec<TableRow<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct TableHead {
-    pub cells: Vec<TableCell>,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct TableHead<'a> {
+    pub cells: Vec<TableCell<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct TableRow {
-    pub cells: Vec<TableCell>,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct TableRow<'a> {
+    pub cells: Vec<TableCell<'a>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct TableCell {
-    pub body: Vec<Inline>,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct TableCell<'a> {
+    pub body: Vec<Inline<'a>>,
 }
@@ -0,0 +1,484 @@
+//! Fluent construction of documents
+//!
+//! Spelling out nodes by hand gets verbose quickly, so this module offers
+//! builders for the common blocks, and helper functions for inline content.
+//! Anything which takes inline content accepts an [`IntoInlines`], so plain
+//! strings, single [`Inline`]s and arrays of them can all be used.
+//!
+//! ```
+//! use pulldown_cmark_ast::{builder::*, Document, HeadingLevel};
+//!
+//! let doc = Document::builder()
+//!     .heading(HeadingBuilder::new(HeadingLevel::H1, "Actions").class("todo"))
+//!     .list(ListBuilder::new().task(false, [text("Read "), link("https://example.com", "this")]))
+//!     .paragraph([text("Some "), strong("bold"), text(" text")])
+//!     .build();
+//! ```
+
+use crate::ast::*;
+
+/// Things which can be turned into a run of inline content
+pub trait IntoInlines<'a> {
+    fn into_inlines(self) -> Vec<Inline<'a>>;
+}
+
+impl<'a> IntoInlines<'a> for Inline<'a> {
+    fn into_inlines(self) -> Vec<Inline<'a>> {
+        vec![self]
+    }
+}
+
+impl<'a> IntoInlines<'a> for &'a str {
+    fn into_inlines(self) -> Vec<Inline<'a>> {
+        vec![self.into()]
+    }
+}
+
+impl<'a> IntoInlines<'a> for String {
+    fn into_inlines(self) -> Vec<Inline<'a>> {
+        vec![self.into()]
+    }
+}
+
+impl<'a> IntoInlines<'a> for CowStr<'a> {
+    fn into_inlines(self) -> Vec<Inline<'a>> {
+        vec![Inline::Text(self)]
+    }
+}
+
+impl<'a> IntoInlines<'a> for Vec<Inline<'a>> {
+    fn into_inlines(self) -> Vec<Inline<'a>> {
+        self
+    }
+}
+
+impl<'a, const N: usize> IntoInlines<'a> for [Inline<'a>; N] {
+    fn into_inlines(self) -> Vec<Inline<'a>> {
+        self.into()
+    }
+}
+
+impl<'a> From<&'a str> for Inline<'a> {
+    fn from(value: &'a str) -> Self {
+        Inline::Text(CowStr::Borrowed(value))
+    }
+}
+
+impl<'a> From<String> for Inline<'a> {
+    fn from(value: String) -> Self {
+        Inline::Text(value.into())
+    }
+}
+
+pub fn text<'a>(text: impl Into<CowStr<'a>>) -> Inline<'a> {
+    Inline::Text(text.into())
+}
+
+pub fn code<'a>(code: impl Into<CowStr<'a>>) -> Inline<'a> {
+    Inline::Code(code.into())
+}
+
+pub fn emphasis<'a>(body: impl IntoInlines<'a>) -> Inline<'a> {
+    Inline::Emphasis(body.into_inlines())
+}
+
+pub fn strong<'a>(body: impl IntoInlines<'a>) -> Inline<'a> {
+    Inline::Strong(body.into_inlines())
+}
+
+pub fn strikethrough<'a>(body: impl IntoInlines<'a>) -> Inline<'a> {
+    Inline::Strikethrough(body.into_inlines())
+}
+
+pub fn soft_break() -> Inline<'static> {
+    Inline::SoftBreak
+}
+
+pub fn hard_break() -> Inline<'static> {
+    Inline::HardBreak
+}
+
+/// An inline link, such as `[body](dest_url)`
+pub fn link<'a>(dest_url: impl Into<CowStr<'a>>, body: impl IntoInlines<'a>) -> Inline<'a> {
+    Inline::Link(Link {
+        link_type: LinkType::Inline,
+        dest_url: dest_url.into(),
+        title: "".into(),
+        id: "".into(),
+        body: body.into_inlines(),
+    })
+}
+
+/// An inline image, such as `![alt](dest_url)`
+pub fn image<'a>(dest_url: impl Into<CowStr<'a>>, alt: impl IntoInlines<'a>) -> Inline<'a> {
+    Inline::Image(Image {
+        link_type: LinkType::Inline,
+        dest_url: dest_url.into(),
+        title: "".into(),
+        id: "".into(),
+        body: alt.into_inlines(),
+    })
+}
+
+/// Builds a [`Document`] one block at a time
+#[derive(Debug, Default)]
+pub struct DocumentBuilder<'a> {
+    blocks: Vec<Block<'a>>,
+}
+
+impl<'a> Document<'a> {
+    pub fn builder() -> DocumentBuilder<'a> {
+        DocumentBuilder::default()
+    }
+}
+
+impl<'a> DocumentBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn block(mut self, block: impl Into<Block<'a>>) -> Self {
+        self.blocks.push(block.into());
+        self
+    }
+
+    pub fn heading(self, heading: impl Into<Heading<'a>>) -> Self {
+        self.block(heading.into())
+    }
+
+    pub fn paragraph(self, body: impl IntoInlines<'a>) -> Self {
+        self.block(Paragraph {
+            body: body.into_inlines(),
+        })
+    }
+
+    pub fn list(self, list: impl Into<List<'a>>) -> Self {
+        self.block(list.into())
+    }
+
+    pub fn table(self, table: impl Into<Table<'a>>) -> Self {
+        self.block(table.into())
+    }
+
+    pub fn rule(self) -> Self {
+        self.block(Block::Rule)
+    }
+
+    /// A fenced code block, with an empty `info` string for no language
+    pub fn code_block(self, info: impl Into<CowStr<'a>>, code: impl Into<CowStr<'a>>) -> Self {
+        self.block(CodeBlock {
+            kind: CodeBlockKind::Fenced(info.into()),
+            body: vec![text(code)],
+        })
+    }
+
+    pub fn block_quote(self, body: DocumentBuilder<'a>) -> Self {
+        self.block(BlockQuote {
+            kind: None,
+            body: body.blocks,
+        })
+    }
+
+    pub fn build(self) -> Document<'a> {
+        Document {
+            blocks: self.blocks,
+        }
+    }
+}
+
+impl<'a> From<DocumentBuilder<'a>> for Document<'a> {
+    fn from(builder: DocumentBuilder<'a>) -> Self {
+        builder.build()
+    }
+}
+
+/// Builds a [`Heading`], with any id, classes and attributes
+#[derive(Debug)]
+pub struct HeadingBuilder<'a> {
+    heading: Heading<'a>,
+}
+
+impl<'a> HeadingBuilder<'a> {
+    pub fn new(level: HeadingLevel, body: impl IntoInlines<'a>) -> Self {
+        Self {
+            heading: Heading {
+                level,
+                id: None,
+                classes: Vec::new(),
+                attrs: Vec::new(),
+                body: body.into_inlines(),
+            },
+        }
+    }
+
+    pub fn id(mut self, id: impl Into<CowStr<'a>>) -> Self {
+        self.heading.id = Some(id.into());
+        self
+    }
+
+    pub fn class(mut self, class: impl Into<CowStr<'a>>) -> Self {
+        self.heading.classes.push(class.into());
+        self
+    }
+
+    pub fn attr(
+        mut self,
+        key: impl Into<CowStr<'a>>,
+        value: Option<impl Into<CowStr<'a>>>,
+    ) -> Self {
+        self.heading.attrs.push((key.into(), value.map(Into::into)));
+        self
+    }
+
+    pub fn build(self) -> Heading<'a> {
+        self.heading
+    }
+}
+
+impl<'a> From<HeadingBuilder<'a>> for Heading<'a> {
+    fn from(builder: HeadingBuilder<'a>) -> Self {
+        builder.build()
+    }
+}
+
+/// Builds a [`List`], of plain items and tasks
+#[derive(Debug)]
+pub struct ListBuilder<'a> {
+    list: List<'a>,
+}
+
+impl<'a> ListBuilder<'a> {
+    /// An unordered list
+    pub fn new() -> Self {
+        Self {
+            list: List {
+                start: None,
+                items: Vec::new(),
+            },
+        }
+    }
+
+    /// An ordered list, numbered from `start`
+    pub fn ordered(start: u64) -> Self {
+        Self {
+            list: List {
+                start: Some(start),
+                items: Vec::new(),
+            },
+        }
+    }
+
+    pub fn item(mut self, body: impl IntoInlines<'a>) -> Self {
+        self.list.items.push(ListItem::Inline(body.into_inlines()));
+        self
+    }
+
+    pub fn task(self, done: bool, body: impl IntoInlines<'a>) -> Self {
+        self.task_marked(if done { 'x' } else { ' ' }, body)
+    }
+
+    /// A task with some other marker, such as `[.]`
+    pub fn task_marked(mut self, marker: char, body: impl IntoInlines<'a>) -> Self {
+        let mut inlines = vec![Inline::TasklistMarker(marker)];
+        inlines.extend(body.into_inlines());
+        self.list.items.push(ListItem::Inline(inlines));
+        self
+    }
+
+    /// An item holding blocks, such as several paragraphs or a nested list
+    pub fn item_blocks(mut self, body: DocumentBuilder<'a>) -> Self {
+        self.list.items.push(ListItem::Block(body.blocks));
+        self
+    }
+
+    pub fn build(self) -> List<'a> {
+        self.list
+    }
+}
+
+impl Default for ListBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> From<ListBuilder<'a>> for List<'a> {
+    fn from(builder: ListBuilder<'a>) -> Self {
+        builder.build()
+    }
+}
+
+/// Builds a [`Table`] from its header and then its rows
+#[derive(Debug)]
+pub struct TableBuilder<'a> {
+    table: Table<'a>,
+}
+
+fn cells<'a, T: IntoInlines<'a>>(cells: impl IntoIterator<Item = T>) -> Vec<TableCell<'a>> {
+    cells
+        .into_iter()
+        .map(|c| TableCell {
+            body: c.into_inlines(),
+        })
+        .collect()
+}
+
+impl<'a> TableBuilder<'a> {
+    /// A table with the given header, and no column alignments
+    pub fn new<T: IntoInlines<'a>>(header: impl IntoIterator<Item = T>) -> Self {
+        let cells = cells(header);
+        Self {
+            table: Table {
+                alignments: vec![Alignment::None; cells.len()],
+                header: TableHead { cells },
+                rows: Vec::new(),
+            },
+        }
+    }
+
+    /// Align the columns in order
+    ///
+    /// Columns left over are unaligned, and alignments left over are ignored,
+    /// since a table whose alignments don't match its columns won't parse.
+    pub fn align(mut self, alignments: impl IntoIterator<Item = Alignment>) -> Self {
+        let columns = self.table.columns();
+        self.table.alignments = vec![Alignment::None; columns];
+        for (column, alignment) in alignments.into_iter().take(columns).enumerate() {
+            self.table.set_alignment(column, alignment);
+        }
+        self
+    }
+
+    pub fn row<T: IntoInlines<'a>>(mut self, row: impl IntoIterator<Item = T>) -> Self {
+        self.table.rows.push(TableRow { cells: cells(row) });
+        self
+    }
+
+    pub fn build(self) -> Table<'a> {
+        self.table
+    }
+}
+
+impl<'a> From<TableBuilder<'a>> for Table<'a> {
+    fn from(builder: TableBuilder<'a>) -> Self {
+        builder.build()
+    }
+}
+
+macro_rules! block_from {
+    ($($ty:ident),*) => {
+        $(
+            impl<'a> From<$ty<'a>> for Block<'a> {
+                fn from(value: $ty<'a>) -> Self {
+                    Block::$ty(value)
+                }
+            }
+        )*
+    };
+}
+
+block_from!(Heading, Paragraph, BlockQuote, CodeBlock, List, Table);
+
+impl<'a> From<HeadingBuilder<'a>> for Block<'a> {
+    fn from(builder: HeadingBuilder<'a>) -> Self {
+        Block::Heading(builder.build())
+    }
+}
+
+impl<'a> From<ListBuilder<'a>> for Block<'a> {
+    fn from(builder: ListBuilder<'a>) -> Self {
+        Block::List(builder.build())
+    }
+}
+
+impl<'a> From<TableBuilder<'a>> for Block<'a> {
+    fn from(builder: TableBuilder<'a>) -> Self {
+        Block::Table(builder.build())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use insta::assert_snapshot;
+
+    use crate::RenderOptions;
+
+    use super::*;
+
+    #[test]
+    fn builds() {
+        let doc = Document::builder()
+            .heading(
+                HeadingBuilder::new(HeadingLevel::H1, "Actions")
+                    .id("actions")
+                    .class("todo"),
+            )
+            .list(
+                ListBuilder::new()
+                    .task(false, [text("Read "), link("https://example.com", "this")])
+                    .task(true, emphasis("Done"))
+                    .item("Not a task"),
+            )
+            .heading(HeadingBuilder::new(
+                HeadingLevel::H2,
+                [text("Some "), code("code")],
+            ))
+            .paragraph([text("Some "), strong("bold"), text(" text")])
+            .code_block("rust", "fn main() {}\n")
+            .table(
+                TableBuilder::new(["Who", "What"])
+                    .align([Alignment::Left, Alignment::Right])
+                    .row(["me", "this"])
+                    .row([strong("you"), text(format!("{} things", 2))]),
+            )
+            .list(ListBuilder::ordered(3).item("three").item("four"))
+            .build();
+
+        assert_snapshot!(doc.render(RenderOptions::default()).unwrap(), @r###"
+        # Actions { #actions .todo }
+
+        * [ ] Read [this](https://example.com)
+        * [x] *Done*
+        * Not a task
+
+        ## Some `code`
+
+        Some **bold** text
+
+        ```rust
+        fn main() {}
+        ```
+
+        |Who|What|
+        |:--|---:|
+        |me|this|
+        |**you**|2 things|
+
+        3. three
+        3. four
+        "###);
+    }
+
+    #[test]
+    fn table_alignments() {
+        let reparsed = |builder: TableBuilder<'static>| {
+            let doc = Document::builder().table(builder).build();
+            let text = doc.render(RenderOptions::default()).unwrap();
+            match Document::parse(&text, crate::ParseOptions::all())
+                .blocks
+                .as_slice()
+            {
+                [Block::Table(table)] => table.alignments.clone(),
+                blocks => panic!("Expected a table, got {blocks:?}"),
+            }
+        };
+        assert_eq!(
+            reparsed(TableBuilder::new(["A", "B", "C"]).align([Alignment::Left])),
+            [Alignment::Left, Alignment::None, Alignment::None]
+        );
+        assert_eq!(
+            reparsed(TableBuilder::new(["A"]).align([Alignment::Right, Alignment::Left])),
+            [Alignment::Right]
+        );
+    }
+}
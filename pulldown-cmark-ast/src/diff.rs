@@ -0,0 +1,328 @@
+//! Structural differences between documents
+//!
+//! Rather than comparing lines of text, [`Document::diff`] compares blocks.
+//! Each block is considered along with the titles of the headings it sits
+//! beneath, so that a paragraph which moves to a different section is seen
+//! as removed from one and added to the other.  Where a removed block and an
+//! added block are of the same kind and in the same section they are paired
+//! up as a change, and changed lists are further compared item by item.
+
+use pulldown_cmark::HeadingLevel;
+
+use crate::ast::*;
+
+/// The titles of the headings which a node sits beneath, outermost first
+pub type HeadingPath = Vec<String>;
+
+/// A node which took part in a [`Change`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffNode<'a> {
+    Block(&'a Block<'a>),
+    ListItem(&'a ListItem<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<'a> {
+    Added {
+        context: HeadingPath,
+        node: DiffNode<'a>,
+    },
+    Removed {
+        context: HeadingPath,
+        node: DiffNode<'a>,
+    },
+    Changed {
+        context: HeadingPath,
+        old: DiffNode<'a>,
+        new: DiffNode<'a>,
+    },
+}
+
+impl Change<'_> {
+    /// Where the change happened, in the new document unless it was a removal
+    pub fn context(&self) -> &[String] {
+        match self {
+            Change::Added { context, .. }
+            | Change::Removed { context, .. }
+            | Change::Changed { context, .. } => context,
+        }
+    }
+}
+
+/// The differences between two documents, in document order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diff<'a> {
+    pub changes: Vec<Change<'a>>,
+}
+
+impl<'a> Diff<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Change<'a>> {
+        self.changes.iter()
+    }
+}
+
+impl Document<'_> {
+    /// Compare this document with a newer version of it
+    pub fn diff<'d>(&'d self, new: &'d Document<'_>) -> Diff<'d> {
+        let old = flatten(&self.blocks);
+        let new = flatten(&new.blocks);
+        let mut diff = Diff::default();
+        align(
+            &old,
+            &new,
+            |a, b| a == b,
+            |a, b| a.context == b.context && same_kind(a.block, b.block),
+            &mut |step| match step {
+                Step::Removed(entry) => diff.changes.push(Change::Removed {
+                    context: entry.context.clone(),
+                    node: DiffNode::Block(entry.block),
+                }),
+                Step::Added(entry) => diff.changes.push(Change::Added {
+                    context: entry.context.clone(),
+                    node: DiffNode::Block(entry.block),
+                }),
+                Step::Paired(old, new) => diff_blocks(old, new, &mut diff),
+            },
+        );
+        diff
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Entry<'a> {
+    context: HeadingPath,
+    block: &'a Block<'a>,
+}
+
+/// Pair each block up with the titles of the headings above it
+fn flatten<'a>(blocks: &'a [Block<'a>]) -> Vec<Entry<'a>> {
+    let mut open: Vec<(HeadingLevel, String)> = Vec::new();
+    blocks
+        .iter()
+        .map(|block| {
+            if let Block::Heading(heading) = block {
+                while open.last().is_some_and(|(l, _)| *l >= heading.level) {
+                    open.pop();
+                }
+            }
+            let context = open.iter().map(|(_, t)| t.clone()).collect();
+            if let Block::Heading(heading) = block {
+                open.push((heading.level, heading.title()));
+            }
+            Entry { context, block }
+        })
+        .collect()
+}
+
+fn same_kind(a: &Block<'_>, b: &Block<'_>) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn diff_blocks<'a>(old: &Entry<'a>, new: &Entry<'a>, diff: &mut Diff<'a>) {
+    let (Block::List(old_list), Block::List(new_list)) = (old.block, new.block) else {
+        diff.changes.push(Change::Changed {
+            context: new.context.clone(),
+            old: DiffNode::Block(old.block),
+            new: DiffNode::Block(new.block),
+        });
+        return;
+    };
+    let before = diff.changes.len();
+    align(
+        &old_list.items,
+        &new_list.items,
+        |a, b| a == b,
+        |a, b| without_marker(a) == without_marker(b),
+        &mut |step| {
+            diff.changes.push(match step {
+                Step::Removed(item) => Change::Removed {
+                    context: old.context.clone(),
+                    node: DiffNode::ListItem(item),
+                },
+                Step::Added(item) => Change::Added {
+                    context: new.context.clone(),
+                    node: DiffNode::ListItem(item),
+                },
+                Step::Paired(old, item) => Change::Changed {
+                    context: new.context.clone(),
+                    old: DiffNode::ListItem(old),
+                    new: DiffNode::ListItem(item),
+                },
+            })
+        },
+    );
+    if diff.changes.len() == before {
+        // The items all match, so something about the list itself changed
+        diff.changes.push(Change::Changed {
+            context: new.context.clone(),
+            old: DiffNode::Block(old.block),
+            new: DiffNode::Block(new.block),
+        });
+    }
+}
+
+/// The content of a list item, ignoring whether it is a task or how ticked it is
+fn without_marker<'i>(item: &'i ListItem<'i>) -> &'i [Inline<'i>] {
+    let body = match item {
+        ListItem::Inline(body) => body,
+        ListItem::Block(blocks) => match blocks.first() {
+            Some(Block::Paragraph(p)) => &p.body,
+            _ => return &[],
+        },
+    };
+    match body.split_first() {
+        Some((Inline::TasklistMarker(_), rest)) => rest,
+        _ => body,
+    }
+}
+
+enum Step<'t, T> {
+    Removed(&'t T),
+    Added(&'t T),
+    Paired(&'t T, &'t T),
+}
+
+/// Walk two sequences in step, reporting how to get from `old` to `new`
+///
+/// Elements which are `equal` are matched up by longest common subsequence
+/// and not reported.  Between those, removed elements are paired with the
+/// next `similar` added element where possible.
+fn align<'t, T>(
+    old: &'t [T],
+    new: &'t [T],
+    equal: impl Fn(&T, &T) -> bool,
+    similar: impl Fn(&T, &T) -> bool,
+    report: &mut dyn FnMut(Step<'t, T>),
+) {
+    // lengths[i][j] is the length of the LCS of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if equal(&old[i], &new[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut gap = |old: &'t [T], new: &'t [T]| {
+        let mut next = 0;
+        for o in old {
+            match new[next..].iter().position(|n| similar(o, n)) {
+                Some(found) => {
+                    new[next..next + found]
+                        .iter()
+                        .for_each(|n| report(Step::Added(n)));
+                    report(Step::Paired(o, &new[next + found]));
+                    next += found + 1;
+                }
+                None => report(Step::Removed(o)),
+            }
+        }
+        new[next..].iter().for_each(|n| report(Step::Added(n)));
+    };
+
+    let (mut i, mut j) = (0, 0);
+    let (mut gap_i, mut gap_j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if equal(&old[i], &new[j]) {
+            gap(&old[gap_i..i], &new[gap_j..j]);
+            i += 1;
+            j += 1;
+            (gap_i, gap_j) = (i, j);
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    gap(&old[gap_i..], &new[gap_j..]);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ParseOptions;
+
+    use super::*;
+
+    const BEFORE: &str = r###"
+# Actions
+
+- [ ] Write the diff
+- [ ] Test the diff
+- [ ] Forget about this
+
+# Notes
+
+Some notes
+
+More notes
+"###;
+
+    const AFTER: &str = r###"
+# Actions
+
+- [x] Write the diff
+- [ ] Test the diff
+- [ ] Something new
+
+# Notes
+
+# Ideas
+
+A new idea
+"###;
+
+    fn describe(change: &Change<'_>) -> String {
+        fn node(node: &DiffNode<'_>) -> &'static str {
+            match node {
+                DiffNode::Block(Block::Paragraph(_)) => "paragraph",
+                DiffNode::Block(Block::Heading(_)) => "heading",
+                DiffNode::Block(_) => "block",
+                DiffNode::ListItem(_) => "item",
+            }
+        }
+        let (what, nodes) = match change {
+            Change::Added { node: n, .. } => ("added", node(n).to_string()),
+            Change::Removed { node: n, .. } => ("removed", node(n).to_string()),
+            Change::Changed { old, new, .. } => {
+                ("changed", format!("{} -> {}", node(old), node(new)))
+            }
+        };
+        format!("{}: {what} {nodes}", change.context().join(" > "))
+    }
+
+    #[test]
+    fn differences() {
+        let before = Document::parse(BEFORE, ParseOptions::all());
+        let after = Document::parse(AFTER, ParseOptions::all());
+        let diff = before.diff(&after);
+        let described: Vec<_> = diff.iter().map(describe).collect();
+        assert_eq!(
+            described,
+            [
+                "Actions: changed item -> item",
+                "Actions: removed item",
+                "Actions: added item",
+                "Notes: removed paragraph",
+                "Notes: removed paragraph",
+                ": added heading",
+                "Ideas: added paragraph",
+            ]
+        );
+        assert!(before.diff(&before).is_empty());
+    }
+
+    #[test]
+    fn moved_between_sections() {
+        let before = Document::parse("# A\n\nText\n\n# B\n", ParseOptions::all());
+        let after = Document::parse("# A\n\n# B\n\nText\n", ParseOptions::all());
+        let described: Vec<_> = before.diff(&after).iter().map(describe).collect();
+        assert_eq!(described, ["A: removed paragraph", "B: added paragraph"]);
+    }
+}
@@ -4,16 +4,16 @@ use pulldown_cmark::CowStr;
 
 use crate::ast::*;
 
-pub trait MarkdownFold {
-    fn fold_document(&mut self, document: Document) -> Document {
+pub trait MarkdownFold<'a> {
+    fn fold_document(&mut self, document: Document<'a>) -> Document<'a> {
         fold_document(self, document)
     }
 
-    fn fold_block(&mut self, block: Block) -> Block {
+    fn fold_block(&mut self, block: Block<'a>) -> Block<'a> {
         fold_block(self, block)
     }
 
-    fn fold_inline(&mut self, inline: Inline) -> Inline {
+    fn fold_inline(&mut self, inline: Inline<'a>) -> Inline<'a> {
         fold_inline(self, inline)
     }
 
@@ -21,140 +21,154 @@ pub trait MarkdownFold {
     fn fold_soft_break(&mut self) {}
     fn fold_hard_break(&mut self) {}
 
-    fn fold_emphasis(&mut self, inlines: Vec<Inline>) -> Vec<Inline> {
+    /// Fold a run of inlines, such as the body of a paragraph or of a link
+    fn fold_inlines(&mut self, inlines: Vec<Inline<'a>>) -> Vec<Inline<'a>> {
         fold_inlines(self, inlines)
     }
 
-    fn fold_strong(&mut self, inlines: Vec<Inline>) -> Vec<Inline> {
-        fold_inlines(self, inlines)
+    fn fold_emphasis(&mut self, inlines: Vec<Inline<'a>>) -> Vec<Inline<'a>> {
+        self.fold_inlines(inlines)
     }
 
-    fn fold_strikethrough(&mut self, inlines: Vec<Inline>) -> Vec<Inline> {
-        fold_inlines(self, inlines)
+    fn fold_strong(&mut self, inlines: Vec<Inline<'a>>) -> Vec<Inline<'a>> {
+        self.fold_inlines(inlines)
+    }
+
+    fn fold_strikethrough(&mut self, inlines: Vec<Inline<'a>>) -> Vec<Inline<'a>> {
+        self.fold_inlines(inlines)
     }
 
-    fn fold_text(&mut self, text: CowStr<'static>) -> CowStr<'static> {
+    fn fold_text(&mut self, text: CowStr<'a>) -> CowStr<'a> {
         text
     }
 
-    fn fold_html(&mut self, html: CowStr<'static>) -> CowStr<'static> {
+    fn fold_html(&mut self, html: CowStr<'a>) -> CowStr<'a> {
         html
     }
 
-    fn fold_inline_math(&mut self, inline_math: CowStr<'static>) -> CowStr<'static> {
+    fn fold_inline_math(&mut self, inline_math: CowStr<'a>) -> CowStr<'a> {
         inline_math
     }
 
-    fn fold_display_math(&mut self, display_math: CowStr<'static>) -> CowStr<'static> {
+    fn fold_display_math(&mut self, display_math: CowStr<'a>) -> CowStr<'a> {
         display_math
     }
 
-    fn fold_code(&mut self, code: CowStr<'static>) -> CowStr<'static> {
+    fn fold_code(&mut self, code: CowStr<'a>) -> CowStr<'a> {
         code
     }
 
-    fn fold_tasklist_marker(&mut self, ticked: bool) -> bool {
-        ticked
+    fn fold_tasklist_marker(&mut self, marker: char) -> char {
+        marker
     }
 
-    fn fold_footnote_reference(&mut self, footnote_reference: CowStr<'static>) -> CowStr<'static> {
+    fn fold_footnote_reference(&mut self, footnote_reference: CowStr<'a>) -> CowStr<'a> {
         footnote_reference
     }
 
-    fn fold_metadata_block(&mut self, metadata_block: MetadataBlock) -> MetadataBlock {
+    fn fold_metadata_block(&mut self, metadata_block: MetadataBlock<'a>) -> MetadataBlock<'a> {
         metadata_block
     }
 
-    fn fold_heading(&mut self, heading: Heading) -> Heading {
+    fn fold_heading(&mut self, heading: Heading<'a>) -> Heading<'a> {
         fold_heading(self, heading)
     }
 
-    fn fold_paragraph(&mut self, paragraph: Paragraph) -> Paragraph {
+    fn fold_paragraph(&mut self, paragraph: Paragraph<'a>) -> Paragraph<'a> {
         fold_paragraph(self, paragraph)
     }
 
-    fn fold_block_quote(&mut self, block_quote: BlockQuote) -> BlockQuote {
+    fn fold_block_quote(&mut self, block_quote: BlockQuote<'a>) -> BlockQuote<'a> {
         fold_block_quote(self, block_quote)
     }
 
-    fn fold_code_block(&mut self, code_block: CodeBlock) -> CodeBlock {
+    fn fold_code_block(&mut self, code_block: CodeBlock<'a>) -> CodeBlock<'a> {
         fold_code_block(self, code_block)
     }
 
-    fn fold_html_block(&mut self, html_block: HtmlBlock) -> HtmlBlock {
+    fn fold_html_block(&mut self, html_block: HtmlBlock<'a>) -> HtmlBlock<'a> {
         html_block
     }
 
     fn fold_footnote_definition(
         &mut self,
-        footnote_definition: FootnoteDefinition,
-    ) -> FootnoteDefinition {
+        footnote_definition: FootnoteDefinition<'a>,
+    ) -> FootnoteDefinition<'a> {
         fold_footnote_definition(self, footnote_definition)
     }
 
-    fn fold_list(&mut self, list: List) -> List {
+    fn fold_list(&mut self, list: List<'a>) -> List<'a> {
         fold_list(self, list)
     }
 
-    fn fold_list_item(&mut self, list_item: ListItem) -> ListItem {
+    fn fold_list_item(&mut self, list_item: ListItem<'a>) -> ListItem<'a> {
         fold_list_item(self, list_item)
     }
 
-    fn fold_definition_list(&mut self, definition_list: DefinitionList) -> DefinitionList {
+    fn fold_definition_list(&mut self, definition_list: DefinitionList<'a>) -> DefinitionList<'a> {
         fold_definition_list(self, definition_list)
     }
 
-    fn fold_definition_item(&mut self, definition_item: DefinitionItem) -> DefinitionItem {
+    fn fold_definition_item(&mut self, definition_item: DefinitionItem<'a>) -> DefinitionItem<'a> {
         fold_definition_item(self, definition_item)
     }
 
     fn fold_definition_definition(
         &mut self,
-        definition_definition: DefinitionDefinition,
-    ) -> DefinitionDefinition {
+        definition_definition: DefinitionDefinition<'a>,
+    ) -> DefinitionDefinition<'a> {
         fold_definition_definition(self, definition_definition)
     }
 
-    fn fold_table(&mut self, table: Table) -> Table {
+    fn fold_table(&mut self, table: Table<'a>) -> Table<'a> {
         fold_table(self, table)
     }
 
-    fn fold_table_head(&mut self, table_head: TableHead) -> TableHead {
+    fn fold_table_head(&mut self, table_head: TableHead<'a>) -> TableHead<'a> {
         fold_table_head(self, table_head)
     }
 
-    fn fold_table_row(&mut self, table_row: TableRow) -> TableRow {
+    fn fold_table_row(&mut self, table_row: TableRow<'a>) -> TableRow<'a> {
         fold_table_row(self, table_row)
     }
 
-    fn fold_table_cell(&mut self, table_cell: TableCell) -> TableCell {
+    fn fold_table_cell(&mut self, table_cell: TableCell<'a>) -> TableCell<'a> {
         fold_table_cell(self, table_cell)
     }
 
-    fn fold_image(&mut self, image: Image) -> Image {
+    fn fold_image(&mut self, image: Image<'a>) -> Image<'a> {
         fold_image(self, image)
     }
 
-    fn fold_link(&mut self, link: Link) -> Link {
+    fn fold_link(&mut self, link: Link<'a>) -> Link<'a> {
         fold_link(self, link)
     }
 }
 
-pub fn fold_inlines<F: MarkdownFold + ?Sized>(folder: &mut F, inlines: Vec<Inline>) -> Vec<Inline> {
+pub fn fold_inlines<'a, F: MarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    inlines: Vec<Inline<'a>>,
+) -> Vec<Inline<'a>> {
     inlines.into_iter().map(|i| folder.fold_inline(i)).collect()
 }
 
-pub fn fold_blocks<F: MarkdownFold + ?Sized>(folder: &mut F, blocks: Vec<Block>) -> Vec<Block> {
+pub fn fold_blocks<'a, F: MarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    blocks: Vec<Block<'a>>,
+) -> Vec<Block<'a>> {
     blocks.into_iter().map(|b| folder.fold_block(b)).collect()
 }
 
-pub fn fold_document<F: MarkdownFold + ?Sized>(folder: &mut F, document: Document) -> Document {
+pub fn fold_document<'a, F: MarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    document: Document<'a>,
+) -> Document<'a> {
     Document {
         blocks: fold_blocks(folder, document.blocks),
     }
 }
 
-pub fn fold_block<F: MarkdownFold + ?Sized>(folder: &mut F, block: Block) -> Block {
+pub fn fold_block<'a, F: MarkdownFold<'a> + ?Sized>(folder: &mut F, block: Block<'a>) -> Block<'a> {
     match block {
         Block::Metadata(metadata_block) => {
             Block::Metadata(folder.fold_metadata_block(metadata_block))
@@ -179,7 +193,10 @@ pub fn fold_block<F: MarkdownFold + ?Sized>(folder: &mut F, block: Block) -> Blo
     }
 }
 
-pub fn fold_inline<F: MarkdownFold + ?Sized>(folder: &mut F, inline: Inline) -> Inline {
+pub fn fold_inline<'a, F: MarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    inline: Inline<'a>,
+) -> Inline<'a> {
     match inline {
         Inline::SoftBreak => {
             folder.fold_soft_break();
@@ -189,8 +206,8 @@ pub fn fold_inline<F: MarkdownFold + ?Sized>(folder: &mut F, inline: Inline) ->
             folder.fold_hard_break();
             Inline::HardBreak
         }
-        Inline::TasklistMarker(ticked) => {
-            Inline::TasklistMarker(folder.fold_tasklist_marker(ticked))
+        Inline::TasklistMarker(marker) => {
+            Inline::TasklistMarker(folder.fold_tasklist_marker(marker))
         }
         Inline::Image(image) => Inline::Image(folder.fold_image(image)),
         Inline::Link(link) => Inline::Link(folder.fold_link(link)),
@@ -213,73 +230,79 @@ pub fn fold_inline<F: MarkdownFold + ?Sized>(folder: &mut F, inline: Inline) ->
     }
 }
 
-pub fn fold_heading<F: MarkdownFold + ?Sized>(folder: &mut F, heading: Heading) -> Heading {
+pub fn fold_heading<'a, F: MarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    heading: Heading<'a>,
+) -> Heading<'a> {
     Heading {
         level: heading.level,
         id: heading.id,
         classes: heading.classes,
         attrs: heading.attrs,
-        body: fold_inlines(folder, heading.body),
+        body: folder.fold_inlines(heading.body),
     }
 }
 
-pub fn fold_paragraph<F: MarkdownFold + ?Sized>(folder: &mut F, paragraph: Paragraph) -> Paragraph {
+pub fn fold_paragraph<'a, F: MarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    paragraph: Paragraph<'a>,
+) -> Paragraph<'a> {
     Paragraph {
-        body: fold_inlines(folder, paragraph.body),
+        body: folder.fold_inlines(paragraph.body),
     }
 }
 
-pub fn fold_link<F: MarkdownFold + ?Sized>(folder: &mut F, link: Link) -> Link {
+pub fn fold_link<'a, F: MarkdownFold<'a> + ?Sized>(folder: &mut F, link: Link<'a>) -> Link<'a> {
     Link {
         link_type: link.link_type,
         dest_url: link.dest_url,
         title: link.title,
         id: link.id,
-        body: fold_inlines(folder, link.body),
+        body: folder.fold_inlines(link.body),
     }
 }
 
-pub fn fold_image<F: MarkdownFold + ?Sized>(folder: &mut F, link: Image) -> Image {
+pub fn fold_image<'a, F: MarkdownFold<'a> + ?Sized>(folder: &mut F, link: Image<'a>) -> Image<'a> {
     Image {
         link_type: link.link_type,
         dest_url: link.dest_url,
         title: link.title,
         id: link.id,
-        body: fold_inlines(folder, link.body),
+        body: folder.fold_inlines(link.body),
     }
 }
 
-pub fn fold_block_quote<F: MarkdownFold + ?Sized>(
+pub fn fold_block_quote<'a, F: MarkdownFold<'a> + ?Sized>(
     folder: &mut F,
-    block_quote: BlockQuote,
-) -> BlockQuote {
+    block_quote: BlockQuote<'a>,
+) -> BlockQuote<'a> {
     BlockQuote {
         kind: block_quote.kind,
         body: fold_blocks(folder, block_quote.body),
     }
 }
 
-pub fn fold_code_block<F: MarkdownFold + ?Sized>(
+pub fn fold_code_block<'a, F: MarkdownFold<'a> + ?Sized>(
     folder: &mut F,
-    code_block: CodeBlock,
-) -> CodeBlock {
+    code_block: CodeBlock<'a>,
+) -> CodeBlock<'a> {
     CodeBlock {
         kind: code_block.kind,
-        body: fold_inlines(folder, code_block.body),
+        body: folder.fold_inlines(code_block.body),
     }
 }
 
-pub fn fold_footnote_definition<F: MarkdownFold + ?Sized>(
+pub fn fold_footnote_definition<'a, F: MarkdownFold<'a> + ?Sized>(
     folder: &mut F,
-    footnote_definition: FootnoteDefinition,
-) -> FootnoteDefinition {
+    footnote_definition: FootnoteDefinition<'a>,
+) -> FootnoteDefinition<'a> {
     FootnoteDefinition {
         label: footnote_definition.label,
         body: fold_blocks(folder, footnote_definition.body),
     }
 }
 
-pub fn fold_list<F: MarkdownFold + ?Sized>(folder: &mut F, list: List) -> List {
+pub fn fold_list<'a, F: MarkdownFold<'a> + ?Sized>(folder: &mut F, list: List<'a>) -> List<'a> {
     List {
         start: list.start,
         items: list
@@ -290,17 +313,20 @@ pub fn fold_list<F: MarkdownFold + ?Sized>(folder: &mut F, list: List) -> List {
     }
 }
 
-pub fn fold_list_item<F: MarkdownFold + ?Sized>(folder: &mut F, list_item: ListItem) -> ListItem {
+pub fn fold_list_item<'a, F: MarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    list_item: ListItem<'a>,
+) -> ListItem<'a> {
     match list_item {
-        ListItem::Inline(vec) => ListItem::Inline(fold_inlines(folder, vec)),
+        ListItem::Inline(vec) => ListItem::Inline(folder.fold_inlines(vec)),
         ListItem::Block(vec) => ListItem::Block(fold_blocks(folder, vec)),
     }
 }
 
-pub fn fold_definition_list<F: MarkdownFold + ?Sized>(
+pub fn fold_definition_list<'a, F: MarkdownFold<'a> + ?Sized>(
     folder: &mut F,
-    definition_list: DefinitionList,
-) -> DefinitionList {
+    definition_list: DefinitionList<'a>,
+) -> DefinitionList<'a> {
     DefinitionList {
         items: definition_list
             .items
@@ -310,12 +336,12 @@ pub fn fold_definition_list<F: MarkdownFold + ?Sized>(
     }
 }
 
-pub fn fold_definition_item<F: MarkdownFold + ?Sized>(
+pub fn fold_definition_item<'a, F: MarkdownFold<'a> + ?Sized>(
     folder: &mut F,
-    definition_item: DefinitionItem,
-) -> DefinitionItem {
+    definition_item: DefinitionItem<'a>,
+) -> DefinitionItem<'a> {
     DefinitionItem {
-        title: fold_inlines(folder, definition_item.title),
+        title: folder.fold_inlines(definition_item.title),
         definitions: definition_item
             .definitions
             .into_iter()
@@ -324,16 +350,16 @@ pub fn fold_definition_item<F: MarkdownFold + ?Sized>(
     }
 }
 
-pub fn fold_definition_definition<F: MarkdownFold + ?Sized>(
+pub fn fold_definition_definition<'a, F: MarkdownFold<'a> + ?Sized>(
     folder: &mut F,
-    definition_definition: DefinitionDefinition,
-) -> DefinitionDefinition {
+    definition_definition: DefinitionDefinition<'a>,
+) -> DefinitionDefinition<'a> {
     DefinitionDefinition {
-        body: fold_inlines(folder, definition_definition.body),
+        body: folder.fold_inlines(definition_definition.body),
     }
 }
 
-pub fn fold_table<F: MarkdownFold + ?Sized>(folder: &mut F, table: Table) -> Table {
+pub fn fold_table<'a, F: MarkdownFold<'a> + ?Sized>(folder: &mut F, table: Table<'a>) -> Table<'a> {
     Table {
         alignments: table.alignments,
         header: folder.fold_table_head(table.header),
@@ -345,10 +371,10 @@ pub fn fold_table<F: MarkdownFold + ?Sized>(folder: &mut F, table: Table) -> Tab
     }
 }
 
-pub fn fold_table_head<F: MarkdownFold + ?Sized>(
+pub fn fold_table_head<'a, F: MarkdownFold<'a> + ?Sized>(
     folder: &mut F,
-    table_head: TableHead,
-) -> TableHead {
+    table_head: TableHead<'a>,
+) -> TableHead<'a> {
     TableHead {
         cells: table_head
             .cells
@@ -358,7 +384,10 @@ pub fn fold_table_head<F: MarkdownFold + ?Sized>(
     }
 }
 
-pub fn fold_table_row<F: MarkdownFold + ?Sized>(folder: &mut F, table_row: TableRow) -> TableRow {
+pub fn fold_table_row<'a, F: MarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    table_row: TableRow<'a>,
+) -> TableRow<'a> {
     TableRow {
         cells: table_row
             .cells
@@ -368,11 +397,11 @@ pub fn fold_table_row<F: MarkdownFold + ?Sized>(folder: &mut F, table_row: Table
     }
 }
 
-pub fn fold_table_cell<F: MarkdownFold + ?Sized>(
+pub fn fold_table_cell<'a, F: MarkdownFold<'a> + ?Sized>(
     folder: &mut F,
-    table_cell: TableCell,
-) -> TableCell {
+    table_cell: TableCell<'a>,
+) -> TableCell<'a> {
     TableCell {
-        body: fold_inlines(folder, table_cell.body),
+        body: folder.fold_inlines(table_cell.body),
     }
 }
@@ -1,29 +1,17 @@
 //! Generators for turning the AST back into markdown text
 
 use pulldown_cmark::{Event, Tag, TagEnd};
-use pulldown_cmark_to_cmark::Options;
 
 use crate::ast::*;
 
-impl Document {
-    pub fn render(&self, mut options: Options) -> String {
-        let mut events = Vec::new();
-        self.push_events(&mut events);
-        options.code_block_token_count =
-            pulldown_cmark_to_cmark::calculate_code_block_token_count(&events)
-                .unwrap_or(pulldown_cmark_to_cmark::DEFAULT_CODE_BLOCK_TOKEN_COUNT);
-        let mut ret = String::new();
-        pulldown_cmark_to_cmark::cmark_with_options(events.into_iter(), &mut ret, options).unwrap();
-        ret
-    }
-
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> Document<'a> {
+    pub(crate) fn push_events(&self, events: &mut Vec<Event<'a>>) {
         self.blocks.iter().for_each(|b| b.push_events(events))
     }
 }
 
-impl Block {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> Block<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         match self {
             Block::Metadata(metadata_block) => metadata_block.push_events(events),
             Block::Heading(heading) => heading.push_events(events),
@@ -42,8 +30,8 @@ impl Block {
     }
 }
 
-impl Inline {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> Inline<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         match self {
             Inline::Image(image) => image.push_events(events),
             Inline::Link(link) => link.push_events(events),
@@ -70,22 +58,37 @@ impl Inline {
             }
             Inline::SoftBreak => events.push(Event::SoftBreak),
             Inline::HardBreak => events.push(Event::HardBreak),
-            Inline::TasklistMarker(b) => events.push(Event::TaskListMarker(*b)),
+            Inline::TasklistMarker(' ') => events.push(Event::TaskListMarker(false)),
+            Inline::TasklistMarker('x') => events.push(Event::TaskListMarker(true)),
+            // Written out raw, since as text the brackets would be escaped
+            Inline::TasklistMarker(c) => events.push(Event::InlineHtml(format!("[{c}]").into())),
             Inline::InlineBlock(b) => b.push_events(events),
         }
     }
 }
 
-impl MetadataBlock {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+/// Push inlines which may start with a task marker, separating a custom
+/// marker from whatever follows it
+fn push_item_body<'a>(body: &[Inline<'a>], events: &mut Vec<Event<'a>>) {
+    for (n, inline) in body.iter().enumerate() {
+        inline.push_events(events);
+        let custom = matches!(inline, Inline::TasklistMarker(c) if !matches!(c, ' ' | 'x'));
+        if n == 0 && custom && body.len() > 1 {
+            events.push(Event::InlineHtml(" ".into()));
+        }
+    }
+}
+
+impl<'a> MetadataBlock<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::MetadataBlock(self.kind)));
         events.push(Event::Text(self.content.clone()));
         events.push(Event::End(TagEnd::MetadataBlock(self.kind)));
     }
 }
 
-impl Heading {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> Heading<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::Heading {
             level: self.level,
             id: self.id.clone(),
@@ -97,32 +100,32 @@ impl Heading {
     }
 }
 
-impl Paragraph {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> Paragraph<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::Paragraph));
-        self.body.iter().for_each(|i| i.push_events(events));
+        push_item_body(&self.body, events);
         events.push(Event::End(TagEnd::Paragraph));
     }
 }
 
-impl BlockQuote {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> BlockQuote<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::BlockQuote(self.kind)));
         self.body.iter().for_each(|b| b.push_events(events));
         events.push(Event::End(TagEnd::BlockQuote(self.kind)));
     }
 }
 
-impl CodeBlock {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> CodeBlock<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::CodeBlock(self.kind.clone())));
         self.body.iter().for_each(|i| i.push_events(events));
         events.push(Event::End(TagEnd::CodeBlock));
     }
 }
 
-impl HtmlBlock {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> HtmlBlock<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::HtmlBlock));
         self.body
             .iter()
@@ -131,43 +134,43 @@ impl HtmlBlock {
     }
 }
 
-impl FootnoteDefinition {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> FootnoteDefinition<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::FootnoteDefinition(self.label.clone())));
         self.body.iter().for_each(|b| b.push_events(events));
         events.push(Event::End(TagEnd::FootnoteDefinition));
     }
 }
 
-impl List {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> List<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::List(self.start)));
         self.items.iter().for_each(|li| li.push_events(events));
         events.push(Event::End(Tag::List(self.start).into()));
     }
 }
 
-impl ListItem {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> ListItem<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::Item));
         match self {
-            ListItem::Inline(vec) => vec.iter().for_each(|i| i.push_events(events)),
+            ListItem::Inline(vec) => push_item_body(vec, events),
             ListItem::Block(vec) => vec.iter().for_each(|b| b.push_events(events)),
         }
         events.push(Event::End(TagEnd::Item));
     }
 }
 
-impl DefinitionList {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> DefinitionList<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::DefinitionList));
         self.items.iter().for_each(|i| i.push_events(events));
         events.push(Event::End(TagEnd::DefinitionList));
     }
 }
 
-impl DefinitionItem {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> DefinitionItem<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::DefinitionListTitle));
         self.title.iter().for_each(|i| i.push_events(events));
         events.push(Event::End(TagEnd::DefinitionListTitle));
@@ -179,8 +182,8 @@ impl DefinitionItem {
     }
 }
 
-impl Table {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> Table<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::Table(self.alignments.clone())));
         self.header.push_events(events);
         self.rows.iter().for_each(|r| r.push_events(events));
@@ -188,8 +191,8 @@ impl Table {
     }
 }
 
-impl TableHead {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> TableHead<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         if !self.cells.is_empty() {
             events.push(Event::Start(Tag::TableHead));
             self.cells.iter().for_each(|c| c.push_events(events));
@@ -198,8 +201,8 @@ impl TableHead {
     }
 }
 
-impl TableRow {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> TableRow<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         if !self.cells.is_empty() {
             events.push(Event::Start(Tag::TableRow));
             self.cells.iter().for_each(|c| c.push_events(events));
@@ -208,16 +211,16 @@ impl TableRow {
     }
 }
 
-impl TableCell {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> TableCell<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::TableCell));
         self.body.iter().for_each(|i| i.push_events(events));
         events.push(Event::End(TagEnd::TableCell));
     }
 }
 
-impl Image {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> Image<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::Image {
             link_type: self.link_type,
             dest_url: self.dest_url.clone(),
@@ -229,8 +232,8 @@ impl Image {
     }
 }
 
-impl Link {
-    fn push_events(&self, events: &mut Vec<Event<'static>>) {
+impl<'a> Link<'a> {
+    fn push_events(&self, events: &mut Vec<Event<'a>>) {
         events.push(Event::Start(Tag::Link {
             link_type: self.link_type,
             dest_url: self.dest_url.clone(),
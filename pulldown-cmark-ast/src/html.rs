@@ -0,0 +1,477 @@
+//! Rendering the AST straight to HTML
+//!
+//! The output follows what `pulldown_cmark::html` produces for the same
+//! events, so that documents which have been through the AST render as they
+//! would have done directly from the parser.
+
+use std::collections::HashMap;
+
+use pulldown_cmark_escape::{escape_href, escape_html, escape_html_body_text};
+
+use crate::ast::*;
+
+/// Options for [`Document::render_html`]
+#[derive(Debug, Clone)]
+pub struct HtmlOptions {
+    /// Emit the id, classes, and attributes attached to headings
+    pub heading_attributes: bool,
+    /// Give GitHub style block quotes (`> [!NOTE]` etc.) a `markdown-alert-*` class
+    pub block_quote_kinds: bool,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            heading_attributes: true,
+            block_quote_kinds: true,
+        }
+    }
+}
+
+/// A hook for customising how individual nodes are rendered
+///
+/// Each node is offered to the hook before being rendered.  If the hook
+/// returns `true` then it is assumed to have written the node itself, and
+/// the default rendering is skipped.  Hooks which only want to wrap a node
+/// can use [`HtmlWriter::blocks`] and [`HtmlWriter::inlines`] to render its
+/// children in the usual way.
+pub trait HtmlHook {
+    fn block(&mut self, _writer: &mut HtmlWriter, _block: &Block<'_>) -> bool {
+        false
+    }
+
+    fn inline(&mut self, _writer: &mut HtmlWriter, _inline: &Inline<'_>) -> bool {
+        false
+    }
+}
+
+impl HtmlHook for () {}
+
+/// The output state while rendering HTML
+pub struct HtmlWriter {
+    out: String,
+    options: HtmlOptions,
+    footnotes: HashMap<String, usize>,
+}
+
+impl Document<'_> {
+    pub fn render_html(&self, options: HtmlOptions) -> String {
+        self.render_html_with(options, &mut ())
+    }
+
+    pub fn render_html_with(&self, options: HtmlOptions, hook: &mut dyn HtmlHook) -> String {
+        let mut writer = HtmlWriter {
+            out: String::new(),
+            options,
+            footnotes: HashMap::new(),
+        };
+        writer.blocks(hook, &self.blocks);
+        writer.out
+    }
+}
+
+impl HtmlWriter {
+    /// The options in force for this rendering
+    pub fn options(&self) -> &HtmlOptions {
+        &self.options
+    }
+
+    /// Write raw HTML
+    pub fn raw(&mut self, html: &str) {
+        self.out.push_str(html);
+    }
+
+    // Escaping into a String never fails, hence the unwraps below
+
+    /// Write text, escaping it as needed for element content
+    pub fn text(&mut self, text: &str) {
+        escape_html_body_text(&mut self.out, text).unwrap();
+    }
+
+    /// Write text, escaping it as needed for an attribute value
+    pub fn attr(&mut self, text: &str) {
+        escape_html(&mut self.out, text).unwrap();
+    }
+
+    /// Write a URL, escaping it as needed for an `href` or `src`
+    pub fn href(&mut self, url: &str) {
+        escape_href(&mut self.out, url).unwrap();
+    }
+
+    /// Ensure that the output is at the start of a line
+    pub fn fresh_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    pub fn blocks(&mut self, hook: &mut dyn HtmlHook, blocks: &[Block<'_>]) {
+        blocks.iter().for_each(|b| self.block(hook, b));
+    }
+
+    pub fn inlines(&mut self, hook: &mut dyn HtmlHook, inlines: &[Inline<'_>]) {
+        inlines.iter().for_each(|i| self.inline(hook, i));
+    }
+
+    pub fn block(&mut self, hook: &mut dyn HtmlHook, block: &Block<'_>) {
+        if hook.block(self, block) {
+            return;
+        }
+        match block {
+            // Metadata is for tooling, not for display
+            Block::Metadata(_) => {}
+            Block::Heading(heading) => self.heading(hook, heading),
+            Block::Paragraph(paragraph) => {
+                self.fresh_line();
+                self.raw("<p>");
+                self.inlines(hook, &paragraph.body);
+                self.raw("</p>\n");
+            }
+            Block::BlockQuote(block_quote) => self.block_quote(hook, block_quote),
+            Block::CodeBlock(code_block) => self.code_block(hook, code_block),
+            Block::HtmlBlock(html_block) => {
+                self.fresh_line();
+                html_block.body.iter().for_each(|h| self.raw(h));
+            }
+            Block::FootnoteDefinition(footnote_definition) => {
+                self.fresh_line();
+                self.raw("<div class=\"footnote-definition\" id=\"");
+                self.attr(&footnote_definition.label);
+                self.raw("\"><sup class=\"footnote-definition-label\">");
+                let number = self.footnote_number(&footnote_definition.label);
+                self.raw(&number.to_string());
+                self.raw("</sup>");
+                self.blocks(hook, &footnote_definition.body);
+                self.raw("</div>\n");
+            }
+            Block::Rule => {
+                self.fresh_line();
+                self.raw("<hr />\n");
+            }
+            Block::List(list) => self.list(hook, list),
+            Block::DefinitionList(definition_list) => {
+                self.fresh_line();
+                self.raw("<dl>\n");
+                for item in &definition_list.items {
+                    self.raw("<dt>");
+                    self.inlines(hook, &item.title);
+                    self.raw("</dt>\n");
+                    for def in &item.definitions {
+                        self.raw("<dd>");
+                        self.inlines(hook, &def.body);
+                        self.raw("</dd>\n");
+                    }
+                }
+                self.raw("</dl>\n");
+            }
+            Block::Table(table) => self.table(hook, table),
+        }
+    }
+
+    pub fn inline(&mut self, hook: &mut dyn HtmlHook, inline: &Inline<'_>) {
+        if hook.inline(self, inline) {
+            return;
+        }
+        match inline {
+            Inline::Text(text) => self.text(text),
+            Inline::Image(image) => {
+                self.raw("<img src=\"");
+                self.href(&image.dest_url);
+                self.raw("\" alt=\"");
+                let mut alt = String::new();
+                alt_text(&image.body, &mut alt);
+                self.attr(&alt);
+                if !image.title.is_empty() {
+                    self.raw("\" title=\"");
+                    self.attr(&image.title);
+                }
+                self.raw("\" />");
+            }
+            Inline::Link(link) => {
+                self.raw("<a href=\"");
+                if link.link_type == LinkType::Email {
+                    self.raw("mailto:");
+                }
+                self.href(&link.dest_url);
+                if !link.title.is_empty() {
+                    self.raw("\" title=\"");
+                    self.attr(&link.title);
+                }
+                self.raw("\">");
+                self.inlines(hook, &link.body);
+                self.raw("</a>");
+            }
+            Inline::Html(html) => self.raw(html),
+            Inline::SoftBreak => self.raw("\n"),
+            Inline::HardBreak => self.raw("<br />\n"),
+            Inline::InlineMath(math) => {
+                self.raw("<span class=\"math math-inline\">");
+                self.attr(math);
+                self.raw("</span>");
+            }
+            Inline::DisplayMath(math) => {
+                self.raw("<span class=\"math math-display\">");
+                self.attr(math);
+                self.raw("</span>");
+            }
+            Inline::Code(code) => {
+                self.raw("<code>");
+                self.text(code);
+                self.raw("</code>");
+            }
+            Inline::FootnoteReference(label) => {
+                self.raw("<sup class=\"footnote-reference\"><a href=\"#");
+                self.attr(label);
+                self.raw("\">");
+                let number = self.footnote_number(label);
+                self.raw(&number.to_string());
+                self.raw("</a></sup>");
+            }
+            Inline::TasklistMarker('x' | 'X') => {
+                self.raw("<input disabled=\"\" type=\"checkbox\" checked=\"\"/>\n")
+            }
+            Inline::TasklistMarker(' ') => self.raw("<input disabled=\"\" type=\"checkbox\"/>\n"),
+            Inline::TasklistMarker(marker) => self.text(&format!("[{marker}] ")),
+            Inline::Emphasis(body) => {
+                self.raw("<em>");
+                self.inlines(hook, body);
+                self.raw("</em>");
+            }
+            Inline::Strong(body) => {
+                self.raw("<strong>");
+                self.inlines(hook, body);
+                self.raw("</strong>");
+            }
+            Inline::Strikethrough(body) => {
+                self.raw("<del>");
+                self.inlines(hook, body);
+                self.raw("</del>");
+            }
+            Inline::InlineBlock(block) => self.block(hook, block),
+        }
+    }
+
+    fn footnote_number(&mut self, label: &str) -> usize {
+        let next = self.footnotes.len() + 1;
+        *self.footnotes.entry(label.to_string()).or_insert(next)
+    }
+
+    fn heading(&mut self, hook: &mut dyn HtmlHook, heading: &Heading<'_>) {
+        self.fresh_line();
+        self.raw(&format!("<{}", heading.level));
+        if self.options.heading_attributes {
+            if let Some(id) = &heading.id {
+                self.raw(" id=\"");
+                self.attr(id);
+                self.raw("\"");
+            }
+            if !heading.classes.is_empty() {
+                self.raw(" class=\"");
+                self.attr(&heading.classes.join(" "));
+                self.raw("\"");
+            }
+            for (attr, value) in &heading.attrs {
+                self.raw(" ");
+                self.attr(attr);
+                self.raw("=\"");
+                if let Some(value) = value {
+                    self.attr(value);
+                }
+                self.raw("\"");
+            }
+        }
+        self.raw(">");
+        self.inlines(hook, &heading.body);
+        self.raw(&format!("</{}>\n", heading.level));
+    }
+
+    fn block_quote(&mut self, hook: &mut dyn HtmlHook, block_quote: &BlockQuote<'_>) {
+        self.fresh_line();
+        let class = match block_quote.kind {
+            Some(kind) if self.options.block_quote_kinds => match kind {
+                BlockQuoteKind::Note => " class=\"markdown-alert-note\"",
+                BlockQuoteKind::Tip => " class=\"markdown-alert-tip\"",
+                BlockQuoteKind::Important => " class=\"markdown-alert-important\"",
+                BlockQuoteKind::Warning => " class=\"markdown-alert-warning\"",
+                BlockQuoteKind::Caution => " class=\"markdown-alert-caution\"",
+            },
+            _ => "",
+        };
+        self.raw(&format!("<blockquote{class}>\n"));
+        self.blocks(hook, &block_quote.body);
+        self.fresh_line();
+        self.raw("</blockquote>\n");
+    }
+
+    fn code_block(&mut self, hook: &mut dyn HtmlHook, code_block: &CodeBlock<'_>) {
+        self.fresh_line();
+        let lang = match &code_block.kind {
+            CodeBlockKind::Fenced(info) => info.split(' ').next().unwrap_or(""),
+            CodeBlockKind::Indented => "",
+        };
+        if lang.is_empty() {
+            self.raw("<pre><code>");
+        } else {
+            self.raw("<pre><code class=\"language-");
+            self.attr(lang);
+            self.raw("\">");
+        }
+        self.inlines(hook, &code_block.body);
+        self.raw("</code></pre>\n");
+    }
+
+    fn list(&mut self, hook: &mut dyn HtmlHook, list: &List<'_>) {
+        self.fresh_line();
+        match list.start {
+            None => self.raw("<ul>\n"),
+            Some(1) => self.raw("<ol>\n"),
+            Some(start) => self.raw(&format!("<ol start=\"{start}\">\n")),
+        }
+        for item in &list.items {
+            self.fresh_line();
+            self.raw("<li>");
+            match item {
+                ListItem::Inline(body) => self.inlines(hook, body),
+                ListItem::Block(body) => self.blocks(hook, body),
+            }
+            self.raw("</li>\n");
+        }
+        match list.start {
+            None => self.raw("</ul>\n"),
+            Some(_) => self.raw("</ol>\n"),
+        }
+    }
+
+    fn table(&mut self, hook: &mut dyn HtmlHook, table: &Table<'_>) {
+        self.fresh_line();
+        self.raw("<table>");
+        if !table.header.cells.is_empty() {
+            self.raw("<thead><tr>");
+            self.table_cells(hook, "th", &table.alignments, &table.header.cells);
+            self.raw("</tr></thead>");
+        }
+        self.raw("<tbody>\n");
+        for row in &table.rows {
+            self.raw("<tr>");
+            self.table_cells(hook, "td", &table.alignments, &row.cells);
+            self.raw("</tr>\n");
+        }
+        self.raw("</tbody></table>\n");
+    }
+
+    fn table_cells(
+        &mut self,
+        hook: &mut dyn HtmlHook,
+        element: &str,
+        alignments: &[Alignment],
+        cells: &[TableCell<'_>],
+    ) {
+        for (i, cell) in cells.iter().enumerate() {
+            self.raw(&format!("<{element}"));
+            match alignments.get(i) {
+                Some(Alignment::Left) => self.raw(" style=\"text-align: left\">"),
+                Some(Alignment::Center) => self.raw(" style=\"text-align: center\">"),
+                Some(Alignment::Right) => self.raw(" style=\"text-align: right\">"),
+                _ => self.raw(">"),
+            }
+            self.inlines(hook, &cell.body);
+            self.raw(&format!("</{element}>"));
+        }
+    }
+}
+
+/// Alt text is the plain text of the image description
+fn alt_text(inlines: &[Inline<'_>], out: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(s) | Inline::Code(s) | Inline::InlineMath(s) | Inline::DisplayMath(s) => {
+                out.push_str(s)
+            }
+            Inline::SoftBreak | Inline::HardBreak => out.push(' '),
+            Inline::Image(Image { body, .. })
+            | Inline::Link(Link { body, .. })
+            | Inline::Emphasis(body)
+            | Inline::Strong(body)
+            | Inline::Strikethrough(body) => alt_text(body, out),
+            Inline::Html(_)
+            | Inline::FootnoteReference(_)
+            | Inline::TasklistMarker(_)
+            | Inline::InlineBlock(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use insta::assert_snapshot;
+
+    use crate::{Document, ParseOptions};
+
+    use super::*;
+
+    const SAMPLE: &str = r###"
+# Heading { #ident .todo key=value }
+
+Some _emphasis_, **strong** and ~~struck~~ text with a footnote[^1].
+
+[^1]: The footnote
+
+- [ ] A task with `code` and a [link](https://example.com "title")
+- [x] A finished task
+
+> [!WARNING]
+> Beware of <b>the</b> dog
+
+| Left | Middle | Right |
+| :--- | :----: | ----: |
+| a    | b      | c     |
+
+Term
+: Definition
+
+![An *image*](image.png)
+
+```rust
+fn main() {}
+```
+"###;
+
+    #[test]
+    fn render() {
+        let doc = Document::parse(SAMPLE, ParseOptions::all());
+        assert_snapshot!(doc.render_html(HtmlOptions::default()));
+    }
+
+    struct Markers;
+
+    impl HtmlHook for Markers {
+        fn inline(&mut self, writer: &mut HtmlWriter, inline: &Inline<'_>) -> bool {
+            match inline {
+                Inline::TasklistMarker(marker) => {
+                    writer.raw(if *marker == 'x' {
+                        "&#x2611; "
+                    } else {
+                        "&#x2610; "
+                    });
+                    true
+                }
+                Inline::Code(code) => {
+                    writer.raw("<kbd>");
+                    writer.text(code);
+                    writer.raw("</kbd>");
+                    true
+                }
+                _ => false,
+            }
+        }
+    }
+
+    #[test]
+    fn hooked() {
+        let doc = Document::parse("- [ ] Press `q`\n- [x] Done\n", ParseOptions::all());
+        assert_eq!(
+            doc.render_html_with(HtmlOptions::default(), &mut Markers),
+            "<ul>\n<li>&#x2610; Press <kbd>q</kbd></li>\n<li>&#x2611; Done</li>\n</ul>\n"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Detaching documents from the text they were parsed from
+//!
+//! Parsing borrows wherever it can, so a [`Document`] normally can't
+//! outlive its input.  The `into_static()` methods copy out any borrowed
+//! strings so that the result owns all of its content.
+
+use crate::ast::*;
+
+fn inlines(inlines: Vec<Inline<'_>>) -> Vec<Inline<'static>> {
+    inlines.into_iter().map(Inline::into_static).collect()
+}
+
+fn blocks(blocks: Vec<Block<'_>>) -> Vec<Block<'static>> {
+    blocks.into_iter().map(Block::into_static).collect()
+}
+
+impl Document<'_> {
+    pub fn into_static(self) -> Document<'static> {
+        Document {
+            blocks: blocks(self.blocks),
+        }
+    }
+}
+
+impl Block<'_> {
+    pub fn into_static(self) -> Block<'static> {
+        match self {
+            Block::Metadata(metadata_block) => Block::Metadata(metadata_block.into_static()),
+            Block::Heading(heading) => Block::Heading(heading.into_static()),
+            Block::Paragraph(paragraph) => Block::Paragraph(paragraph.into_static()),
+            Block::BlockQuote(block_quote) => Block::BlockQuote(block_quote.into_static()),
+            Block::CodeBlock(code_block) => Block::CodeBlock(code_block.into_static()),
+            Block::HtmlBlock(html_block) => Block::HtmlBlock(html_block.into_static()),
+            Block::FootnoteDefinition(footnote_definition) => {
+                Block::FootnoteDefinition(footnote_definition.into_static())
+            }
+            Block::Rule => Block::Rule,
+            Block::List(list) => Block::List(list.into_static()),
+            Block::DefinitionList(definition_list) => {
+                Block::DefinitionList(definition_list.into_static())
+            }
+            Block::Table(table) => Block::Table(table.into_static()),
+        }
+    }
+}
+
+impl Inline<'_> {
+    pub fn into_static(self) -> Inline<'static> {
+        match self {
+            Inline::Text(s) => Inline::Text(s.into_static()),
+            Inline::Image(image) => Inline::Image(image.into_static()),
+            Inline::Link(link) => Inline::Link(link.into_static()),
+            Inline::Html(s) => Inline::Html(s.into_static()),
+            Inline::SoftBreak => Inline::SoftBreak,
+            Inline::HardBreak => Inline::HardBreak,
+            Inline::InlineMath(s) => Inline::InlineMath(s.into_static()),
+            Inline::DisplayMath(s) => Inline::DisplayMath(s.into_static()),
+            Inline::Code(s) => Inline::Code(s.into_static()),
+            Inline::FootnoteReference(s) => Inline::FootnoteReference(s.into_static()),
+            Inline::TasklistMarker(marker) => Inline::TasklistMarker(marker),
+            Inline::Emphasis(body) => Inline::Emphasis(inlines(body)),
+            Inline::Strong(body) => Inline::Strong(inlines(body)),
+            Inline::Strikethrough(body) => Inline::Strikethrough(inlines(body)),
+            Inline::InlineBlock(block) => Inline::InlineBlock(block.into_static()),
+        }
+    }
+}
+
+impl MetadataBlock<'_> {
+    pub fn into_static(self) -> MetadataBlock<'static> {
+        MetadataBlock {
+            kind: self.kind,
+            content: self.content.into_static(),
+        }
+    }
+}
+
+impl Heading<'_> {
+    pub fn into_static(self) -> Heading<'static> {
+        Heading {
+            level: self.level,
+            id: self.id.map(CowStr::into_static),
+            classes: self.classes.into_iter().map(CowStr::into_static).collect(),
+            attrs: self
+                .attrs
+                .into_iter()
+                .map(|(k, v)| (k.into_static(), v.map(CowStr::into_static)))
+                .collect(),
+            body: inlines(self.body),
+        }
+    }
+}
+
+impl Paragraph<'_> {
+    pub fn into_static(self) -> Paragraph<'static> {
+        Paragraph {
+            body: inlines(self.body),
+        }
+    }
+}
+
+impl Image<'_> {
+    pub fn into_static(self) -> Image<'static> {
+        Image {
+            link_type: self.link_type,
+            dest_url: self.dest_url.into_static(),
+            title: self.title.into_static(),
+            id: self.id.into_static(),
+            body: inlines(self.body),
+        }
+    }
+}
+
+impl Link<'_> {
+    pub fn into_static(self) -> Link<'static> {
+        Link {
+            link_type: self.link_type,
+            dest_url: self.dest_url.into_static(),
+            title: self.title.into_static(),
+            id: self.id.into_static(),
+            body: inlines(self.body),
+        }
+    }
+}
+
+impl BlockQuote<'_> {
+    pub fn into_static(self) -> BlockQuote<'static> {
+        BlockQuote {
+            kind: self.kind,
+            body: blocks(self.body),
+        }
+    }
+}
+
+impl CodeBlock<'_> {
+    pub fn into_static(self) -> CodeBlock<'static> {
+        CodeBlock {
+            kind: self.kind.into_static(),
+            body: inlines(self.body),
+        }
+    }
+}
+
+impl HtmlBlock<'_> {
+    pub fn into_static(self) -> HtmlBlock<'static> {
+        HtmlBlock {
+            body: self.body.into_iter().map(CowStr::into_static).collect(),
+        }
+    }
+}
+
+impl FootnoteDefinition<'_> {
+    pub fn into_static(self) -> FootnoteDefinition<'static> {
+        FootnoteDefinition {
+            label: self.label.into_static(),
+            body: blocks(self.body),
+        }
+    }
+}
+
+impl List<'_> {
+    pub fn into_static(self) -> List<'static> {
+        List {
+            start: self.start,
+            items: self.items.into_iter().map(ListItem::into_static).collect(),
+        }
+    }
+}
+
+impl ListItem<'_> {
+    pub fn into_static(self) -> ListItem<'static> {
+        match self {
+            ListItem::Inline(body) => ListItem::Inline(inlines(body)),
+            ListItem::Block(body) => ListItem::Block(blocks(body)),
+        }
+    }
+}
+
+impl DefinitionList<'_> {
+    pub fn into_static(self) -> DefinitionList<'static> {
+        DefinitionList {
+            items: self
+                .items
+                .into_iter()
+                .map(DefinitionItem::into_static)
+                .collect(),
+        }
+    }
+}
+
+impl DefinitionItem<'_> {
+    pub fn into_static(self) -> DefinitionItem<'static> {
+        DefinitionItem {
+            title: inlines(self.title),
+            definitions: self
+                .definitions
+                .into_iter()
+                .map(DefinitionDefinition::into_static)
+                .collect(),
+        }
+    }
+}
+
+impl DefinitionDefinition<'_> {
+    pub fn into_static(self) -> DefinitionDefinition<'static> {
+        DefinitionDefinition {
+            body: inlines(self.body),
+        }
+    }
+}
+
+impl Table<'_> {
+    pub fn into_static(self) -> Table<'static> {
+        Table {
+            alignments: self.alignments,
+            header: self.header.into_static(),
+            rows: self.rows.into_iter().map(TableRow::into_static).collect(),
+        }
+    }
+}
+
+impl TableHead<'_> {
+    pub fn into_static(self) -> TableHead<'static> {
+        TableHead {
+            cells: self.cells.into_iter().map(TableCell::into_static).collect(),
+        }
+    }
+}
+
+impl TableRow<'_> {
+    pub fn into_static(self) -> TableRow<'static> {
+        TableRow {
+            cells: self.cells.into_iter().map(TableCell::into_static).collect(),
+        }
+    }
+}
+
+impl TableCell<'_> {
+    pub fn into_static(self) -> TableCell<'static> {
+        TableCell {
+            body: inlines(self.body),
+        }
+    }
+}
@@ -1,8 +1,22 @@
 //! An AST model for [pulldown_cmark] supporting "reserialisation"
 //!
+//! Documents borrow text from whatever they were parsed or deserialised
+//! from, and [`Document::into_static`] detaches one when it has to outlive
+//! that.  With the `serde` feature, this is how to deserialise from a reader:
+//! read it into a `String`, deserialise a [`Document`] from that, and call
+//! `into_static()` on the result.
 
 pub(crate) mod ast;
+pub mod builder;
+pub mod diff;
+mod into_static;
 mod parse;
+pub mod section;
+pub mod table;
+pub mod text;
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_test;
 
 #[cfg(any(test, feature = "generate"))]
 mod generate;
@@ -13,18 +27,45 @@ pub use ast::*;
 #[cfg(feature = "generate")]
 pub mod fold;
 
+#[cfg(feature = "generate")]
+pub mod normalise;
+
+#[cfg(feature = "generate")]
+pub mod try_fold;
+
+#[cfg(feature = "visit")]
+pub mod visit;
+
+#[cfg(feature = "visit")]
+pub mod visit_mut;
+
+#[cfg(feature = "visit")]
+pub mod select;
+
+#[cfg(feature = "html")]
+pub mod html;
+
+#[cfg(feature = "mdast")]
+pub mod mdast;
+
 pub use pulldown_cmark::Options as ParseOptions;
 
 #[cfg(feature = "generate")]
-pub use pulldown_cmark_to_cmark::Options as RenderOptions;
+mod render;
+
+#[cfg(feature = "generate")]
+pub use render::{RenderError, RenderOptions};
 
 #[cfg(test)]
 mod test {
+    use std::ops::ControlFlow;
+
     use fold::MarkdownFold;
     use insta::{assert_debug_snapshot, assert_snapshot};
     use pulldown_cmark::{Event, Parser};
 
     use super::*;
+    use visit::Visit;
 
     const EVERYTHING: &str = r###"
 # Purpose
@@ -162,10 +203,9 @@ yaml: metadata
         })
     }
 
-    fn render_opts() -> pulldown_cmark_to_cmark::Options<'static> {
-        use pulldown_cmark_to_cmark::Options;
-        Options {
-            list_token: '-',
+    fn render_opts() -> RenderOptions<'static> {
+        RenderOptions {
+            list_tokens: vec!['-'],
             increment_ordered_list_bullets: true,
             emphasis_token: '_',
             strong_token: "**",
@@ -179,8 +219,50 @@ yaml: metadata
         let doc = Document::from_events(noisy_parser(EVERYTHING, opts()));
         assert_debug_snapshot!(doc);
         struct NullFolder;
-        impl MarkdownFold for NullFolder {}
-        let rendered = NullFolder.fold_document(doc).render(render_opts());
+        impl MarkdownFold<'_> for NullFolder {}
+        let rendered = NullFolder.fold_document(doc).render(render_opts()).unwrap();
         assert_snapshot!(rendered);
     }
+
+    #[test]
+    fn custom_task_markers() {
+        let input = r###"
+- [ ] open
+- [.] partial
+- [x] done
+- [P] *paused*
+  - [d] nested
+- [.]no space
+- [..] too long
+"###;
+        let doc = Document::parse(input, opts());
+        struct Markers(Vec<char>);
+        impl visit::Visit<'_> for Markers {
+            fn visit_tasklist_marker(&mut self, marker: char) -> ControlFlow<()> {
+                self.0.push(marker);
+                ControlFlow::Continue(())
+            }
+        }
+        let mut markers = Markers(Vec::new());
+        let _ = markers.visit_document(&doc);
+        assert_eq!(markers.0, [' ', '.', 'x', 'P', 'd']);
+        assert_snapshot!(doc.render(render_opts()).unwrap(), @r###"
+        - [ ] open
+        - [.] partial
+        - [x] done
+        - [P] _paused_
+          - [d] nested
+        - \[.\]no space
+        - \[..\] too long
+        "###);
+    }
+
+    #[test]
+    fn task_marker_roundtrip() {
+        let input = "- [X] Capital\n- [X]\n- [.]\n- [.] partial\n- [x] lower";
+        let doc = Document::parse(input, opts());
+        let rendered = doc.render(render_opts()).unwrap();
+        assert_eq!(rendered, input);
+        assert_eq!(Document::parse(&rendered, opts()), doc);
+    }
 }
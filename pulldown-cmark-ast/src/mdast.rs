@@ -0,0 +1,1044 @@
+//! Conversion to and from [mdast](https://github.com/syntax-tree/mdast) JSON
+//!
+//! mdast is the syntax tree used by remark and much of the unified
+//! ecosystem, so converting lets documents be handed to those tools and
+//! taken back again.  The GFM nodes (`table`, `delete`, `footnoteDefinition`
+//! and friends, plus `checked` on list items) are used where they apply,
+//! and frontmatter becomes `yaml` or `toml` nodes.
+//!
+//! Anything mdast has no field for is kept in the node's `data`:
+//!
+//! * headings carry `id`, `classes` and `attributes`, along with the
+//!   equivalent `hProperties` for the benefit of rehype
+//! * block quotes carry the `kind` of a GFM alert
+//! * list items with a task marker other than `[ ]` or `[x]` carry it as
+//!   `marker`, with only `[X]` counting as `checked`; if another tool ticks
+//!   or unticks the item, so that `checked` no longer agrees, `marker` is
+//!   ignored
+//! * display maths is an `inlineMath` node with `display` set
+//!
+//! Definition lists use the `defList` nodes of `mdast-util-definition-list`.
+//! Some detail is lost on the way through mdast: link reference styles,
+//! whether a code block with no info string was fenced, and any formatting
+//! in image descriptions.
+//!
+//! ```
+//! use pulldown_cmark_ast::{Document, ParseOptions};
+//!
+//! let doc = Document::parse("Some *emphasis*", ParseOptions::empty());
+//! let mdast = doc.to_mdast();
+//! assert_eq!(mdast["children"][0]["children"][1]["type"], "emphasis");
+//! assert_eq!(Document::from_mdast(&mdast).unwrap(), doc);
+//! ```
+
+use std::{collections::HashMap, fmt};
+
+use serde_json::{json, Map, Value};
+
+use crate::ast::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MdastError {
+    /// Something which should have been a node wasn't a JSON object
+    NotANode(String),
+    /// A node of a type which has no equivalent here
+    UnknownNode(String),
+    /// A node was missing a field, or the field had the wrong type
+    BadField { node: String, field: &'static str },
+}
+
+impl fmt::Display for MdastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MdastError::NotANode(value) => write!(f, "Expected an mdast node, found {value}"),
+            MdastError::UnknownNode(kind) => write!(f, "Unsupported mdast node type `{kind}`"),
+            MdastError::BadField { node, field } => {
+                write!(f, "Missing or invalid `{field}` in mdast `{node}` node")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MdastError {}
+
+impl Document<'_> {
+    /// The document as an mdast `root` node
+    pub fn to_mdast(&self) -> Value {
+        json!({
+            "type": "root",
+            "children": blocks_to(&self.blocks),
+        })
+    }
+}
+
+impl Document<'static> {
+    /// A document from an mdast `root` node
+    ///
+    /// Reference-style links and images are resolved against the
+    /// `definition` nodes in the tree, which are then dropped.
+    pub fn from_mdast(root: &Value) -> Result<Self, MdastError> {
+        let mut importer = Importer::default();
+        importer.collect_definitions(root);
+        if kind(root)? != "root" {
+            return Err(MdastError::UnknownNode(kind(root)?.to_string()));
+        }
+        Ok(Document {
+            blocks: importer.blocks(root)?,
+        })
+    }
+}
+
+fn blocks_to(blocks: &[Block<'_>]) -> Vec<Value> {
+    blocks.iter().map(block_to).collect()
+}
+
+fn block_to(block: &Block<'_>) -> Value {
+    match block {
+        Block::Metadata(MetadataBlock { kind, content }) => json!({
+            "type": match kind {
+                MetadataBlockKind::YamlStyle => "yaml",
+                MetadataBlockKind::PlusesStyle => "toml",
+            },
+            "value": content.trim_end_matches('\n'),
+        }),
+        Block::Heading(heading) => {
+            let mut node = json!({
+                "type": "heading",
+                "depth": heading.level as usize,
+                "children": inlines_to(&heading.body),
+            });
+            if heading.id.is_some() || !heading.classes.is_empty() || !heading.attrs.is_empty() {
+                let mut properties = Map::new();
+                if let Some(id) = &heading.id {
+                    properties.insert("id".into(), id.as_ref().into());
+                }
+                if !heading.classes.is_empty() {
+                    properties.insert("className".into(), strings(&heading.classes));
+                }
+                for (name, value) in &heading.attrs {
+                    let value = value.as_deref().map_or(Value::Bool(true), Value::from);
+                    properties.insert(name.to_string(), value);
+                }
+                node["data"] = json!({
+                    "id": heading.id.as_deref(),
+                    "classes": strings(&heading.classes),
+                    "attributes": heading
+                        .attrs
+                        .iter()
+                        .map(|(name, value)| json!([name.as_ref(), value.as_deref()]))
+                        .collect::<Vec<_>>(),
+                    "hProperties": properties,
+                });
+            }
+            node
+        }
+        Block::Paragraph(Paragraph { body }) => json!({
+            "type": "paragraph",
+            "children": inlines_to(body),
+        }),
+        Block::BlockQuote(BlockQuote { kind, body }) => {
+            let mut node = json!({
+                "type": "blockquote",
+                "children": blocks_to(body),
+            });
+            if let Some(kind) = kind {
+                node["data"] = json!({ "kind": alert_name(*kind) });
+            }
+            node
+        }
+        Block::CodeBlock(CodeBlock { kind, body }) => {
+            let (lang, meta) = match kind {
+                CodeBlockKind::Indented => (None, None),
+                CodeBlockKind::Fenced(info) => match info.trim().split_once(char::is_whitespace) {
+                    Some((lang, meta)) => (Some(lang), Some(meta.trim())),
+                    None if info.trim().is_empty() => (None, None),
+                    None => (Some(info.trim()), None),
+                },
+            };
+            let mut value = String::new();
+            for inline in body {
+                if let Inline::Text(text) = inline {
+                    value.push_str(text);
+                }
+            }
+            json!({
+                "type": "code",
+                "lang": lang,
+                "meta": meta,
+                "value": value.strip_suffix('\n').unwrap_or(&value),
+            })
+        }
+        Block::HtmlBlock(HtmlBlock { body }) => json!({
+            "type": "html",
+            "value": body.concat().trim_end_matches('\n'),
+        }),
+        Block::FootnoteDefinition(FootnoteDefinition { label, body }) => json!({
+            "type": "footnoteDefinition",
+            "identifier": label.to_lowercase(),
+            "label": label.as_ref(),
+            "children": blocks_to(body),
+        }),
+        Block::Rule => json!({ "type": "thematicBreak" }),
+        Block::List(List { start, items }) => {
+            let items = items.iter().map(item_to).collect::<Vec<_>>();
+            json!({
+                "type": "list",
+                "ordered": start.is_some(),
+                "start": start,
+                "spread": items.iter().any(|item| item["spread"] == true),
+                "children": items,
+            })
+        }
+        Block::DefinitionList(DefinitionList { items }) => {
+            let mut children = Vec::new();
+            for item in items {
+                children.push(json!({
+                    "type": "defListTerm",
+                    "children": inlines_to(&item.title),
+                }));
+                for definition in &item.definitions {
+                    children.push(json!({
+                        "type": "defListDescription",
+                        "children": [{
+                            "type": "paragraph",
+                            "children": inlines_to(&definition.body),
+                        }],
+                    }));
+                }
+            }
+            json!({ "type": "defList", "children": children })
+        }
+        Block::Table(Table {
+            alignments,
+            header,
+            rows,
+        }) => {
+            let row = |cells: &[TableCell<'_>]| {
+                json!({
+                    "type": "tableRow",
+                    "children": cells
+                        .iter()
+                        .map(|cell| json!({ "type": "tableCell", "children": inlines_to(&cell.body) }))
+                        .collect::<Vec<_>>(),
+                })
+            };
+            json!({
+                "type": "table",
+                "align": alignments
+                    .iter()
+                    .map(|alignment| match alignment {
+                        Alignment::None => None,
+                        Alignment::Left => Some("left"),
+                        Alignment::Center => Some("center"),
+                        Alignment::Right => Some("right"),
+                    })
+                    .collect::<Vec<_>>(),
+                "children": std::iter::once(row(&header.cells))
+                    .chain(rows.iter().map(|r| row(&r.cells)))
+                    .collect::<Vec<_>>(),
+            })
+        }
+    }
+}
+
+/// A list item, which in mdast always holds blocks
+fn item_to(item: &ListItem<'_>) -> Value {
+    let (spread, marker, children) = match item {
+        ListItem::Inline(body) => {
+            let (marker, body) = split_marker(body);
+            // Runs of inline content become paragraphs between any nested blocks
+            let mut children = Vec::new();
+            let mut run: Vec<Inline<'_>> = Vec::new();
+            for inline in body {
+                match inline {
+                    Inline::InlineBlock(block) => {
+                        if !run.is_empty() {
+                            children.push(block_to(&Block::Paragraph(Paragraph {
+                                body: std::mem::take(&mut run),
+                            })));
+                        }
+                        children.push(block_to(block));
+                    }
+                    inline => run.push(inline.clone()),
+                }
+            }
+            if !run.is_empty() {
+                children.push(block_to(&Block::Paragraph(Paragraph { body: run })));
+            }
+            (false, marker, children)
+        }
+        ListItem::Block(body) => match body.first() {
+            Some(Block::Paragraph(Paragraph { body: first })) => {
+                let (marker, first) = split_marker(first);
+                let mut children = vec![block_to(&Block::Paragraph(Paragraph {
+                    body: first.to_vec(),
+                }))];
+                children.extend(blocks_to(&body[1..]));
+                (true, marker, children)
+            }
+            _ => (true, None, blocks_to(body)),
+        },
+    };
+    let mut node = json!({
+        "type": "listItem",
+        "spread": spread,
+        "checked": marker.map(|marker| matches!(marker, 'x' | 'X')),
+        "children": children,
+    });
+    if let Some(marker) = marker.filter(|marker| !matches!(marker, ' ' | 'x')) {
+        node["data"] = json!({ "marker": marker.to_string() });
+    }
+    node
+}
+
+fn split_marker<'b, 'a>(body: &'b [Inline<'a>]) -> (Option<char>, &'b [Inline<'a>]) {
+    match body.split_first() {
+        Some((Inline::TasklistMarker(marker), rest)) => (Some(*marker), rest),
+        _ => (None, body),
+    }
+}
+
+/// Inline content, with adjacent text and soft breaks merged as mdast expects
+fn inlines_to(inlines: &[Inline<'_>]) -> Vec<Value> {
+    let mut ret: Vec<Value> = Vec::new();
+    for inline in inlines {
+        let node = match inline {
+            Inline::Text(text) => json!({ "type": "text", "value": text.as_ref() }),
+            Inline::SoftBreak => json!({ "type": "text", "value": "\n" }),
+            Inline::HardBreak => json!({ "type": "break" }),
+            Inline::Image(Image {
+                dest_url,
+                title,
+                body,
+                ..
+            }) => {
+                let mut alt = String::new();
+                alt_text(body, &mut alt);
+                json!({
+                    "type": "image",
+                    "url": dest_url.as_ref(),
+                    "title": (!title.is_empty()).then_some(title.as_ref()),
+                    "alt": alt,
+                })
+            }
+            Inline::Link(Link {
+                dest_url,
+                title,
+                body,
+                ..
+            }) => json!({
+                "type": "link",
+                "url": dest_url.as_ref(),
+                "title": (!title.is_empty()).then_some(title.as_ref()),
+                "children": inlines_to(body),
+            }),
+            Inline::Html(html) => json!({ "type": "html", "value": html.as_ref() }),
+            Inline::InlineMath(maths) => json!({ "type": "inlineMath", "value": maths.as_ref() }),
+            Inline::DisplayMath(maths) => json!({
+                "type": "inlineMath",
+                "value": maths.as_ref(),
+                "data": { "display": true },
+            }),
+            Inline::Code(code) => json!({ "type": "inlineCode", "value": code.as_ref() }),
+            Inline::FootnoteReference(label) => json!({
+                "type": "footnoteReference",
+                "identifier": label.to_lowercase(),
+                "label": label.as_ref(),
+            }),
+            // Only meaningful at the start of a list item, which deals with it
+            Inline::TasklistMarker(marker) => {
+                json!({ "type": "text", "value": format!("[{marker}] ") })
+            }
+            Inline::Emphasis(body) => json!({ "type": "emphasis", "children": inlines_to(body) }),
+            Inline::Strong(body) => json!({ "type": "strong", "children": inlines_to(body) }),
+            Inline::Strikethrough(body) => {
+                json!({ "type": "delete", "children": inlines_to(body) })
+            }
+            Inline::InlineBlock(block) => block_to(block),
+        };
+        match (ret.last_mut(), node) {
+            (Some(last), node) if last["type"] == "text" && node["type"] == "text" => {
+                let joined = format!(
+                    "{}{}",
+                    last["value"].as_str().unwrap_or_default(),
+                    node["value"].as_str().unwrap_or_default()
+                );
+                last["value"] = joined.into();
+            }
+            (_, node) => ret.push(node),
+        }
+    }
+    ret
+}
+
+fn alt_text(inlines: &[Inline<'_>], out: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(s) | Inline::Code(s) | Inline::InlineMath(s) | Inline::DisplayMath(s) => {
+                out.push_str(s)
+            }
+            Inline::SoftBreak | Inline::HardBreak => out.push(' '),
+            Inline::Link(Link { body, .. })
+            | Inline::Image(Image { body, .. })
+            | Inline::Emphasis(body)
+            | Inline::Strong(body)
+            | Inline::Strikethrough(body) => alt_text(body, out),
+            _ => {}
+        }
+    }
+}
+
+fn strings(strings: &[CowStr<'_>]) -> Value {
+    strings.iter().map(|s| Value::from(s.as_ref())).collect()
+}
+
+fn alert_name(kind: BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "note",
+        BlockQuoteKind::Tip => "tip",
+        BlockQuoteKind::Important => "important",
+        BlockQuoteKind::Warning => "warning",
+        BlockQuoteKind::Caution => "caution",
+    }
+}
+
+fn kind(node: &Value) -> Result<&str, MdastError> {
+    node.get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| MdastError::NotANode(node.to_string()))
+}
+
+fn bad(node: &Value, field: &'static str) -> MdastError {
+    MdastError::BadField {
+        node: kind(node).unwrap_or_default().to_string(),
+        field,
+    }
+}
+
+fn string(node: &Value, field: &'static str) -> Result<String, MdastError> {
+    node.get(field)
+        .and_then(Value::as_str)
+        .map(String::from)
+        .ok_or_else(|| bad(node, field))
+}
+
+/// A field which may be absent or null
+fn optional_string(node: &Value, field: &'static str) -> Result<Option<String>, MdastError> {
+    match node.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(bad(node, field)),
+    }
+}
+
+fn children(node: &Value) -> Result<&[Value], MdastError> {
+    node.get("children")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .ok_or_else(|| bad(node, "children"))
+}
+
+fn text(value: impl Into<String>) -> CowStr<'static> {
+    CowStr::from(value.into())
+}
+
+#[derive(Default)]
+struct Importer {
+    /// Link reference definitions, by identifier
+    definitions: HashMap<String, (String, Option<String>)>,
+}
+
+impl Importer {
+    fn collect_definitions(&mut self, node: &Value) {
+        if kind(node) == Ok("definition") {
+            if let (Ok(identifier), Ok(url)) = (string(node, "identifier"), string(node, "url")) {
+                let title = optional_string(node, "title").ok().flatten();
+                self.definitions
+                    .entry(identifier.to_lowercase())
+                    .or_insert((url, title));
+            }
+        }
+        if let Some(children) = node.get("children").and_then(Value::as_array) {
+            children.iter().for_each(|c| self.collect_definitions(c));
+        }
+    }
+
+    fn blocks(&self, node: &Value) -> Result<Vec<Block<'static>>, MdastError> {
+        let mut ret = Vec::new();
+        for child in children(node)? {
+            if kind(child)? != "definition" {
+                ret.push(self.block(child)?);
+            }
+        }
+        Ok(ret)
+    }
+
+    fn block(&self, node: &Value) -> Result<Block<'static>, MdastError> {
+        Ok(match kind(node)? {
+            kind @ ("yaml" | "toml") => Block::Metadata(MetadataBlock {
+                kind: if kind == "yaml" {
+                    MetadataBlockKind::YamlStyle
+                } else {
+                    MetadataBlockKind::PlusesStyle
+                },
+                content: text(format!("{}\n", string(node, "value")?)),
+            }),
+            "heading" => {
+                let level = node
+                    .get("depth")
+                    .and_then(Value::as_u64)
+                    .and_then(|depth| HeadingLevel::try_from(depth as usize).ok())
+                    .ok_or_else(|| bad(node, "depth"))?;
+                let data = node.get("data");
+                let id = data
+                    .and_then(|d| d.get("id"))
+                    .and_then(Value::as_str)
+                    .map(text);
+                let classes = data
+                    .and_then(|d| d.get("classes"))
+                    .and_then(Value::as_array)
+                    .map(|classes| classes.iter().filter_map(Value::as_str).map(text).collect())
+                    .unwrap_or_default();
+                let attrs = data
+                    .and_then(|d| d.get("attributes"))
+                    .and_then(Value::as_array)
+                    .map(|attrs| {
+                        attrs
+                            .iter()
+                            .filter_map(|attr| {
+                                let name = attr.get(0)?.as_str()?;
+                                Some((text(name), attr.get(1)?.as_str().map(text)))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Block::Heading(Heading {
+                    level,
+                    id,
+                    classes,
+                    attrs,
+                    body: self.inlines(node)?,
+                })
+            }
+            "paragraph" => Block::Paragraph(Paragraph {
+                body: self.inlines(node)?,
+            }),
+            "blockquote" => Block::BlockQuote(BlockQuote {
+                kind: match node.pointer("/data/kind").and_then(Value::as_str) {
+                    Some("note") => Some(BlockQuoteKind::Note),
+                    Some("tip") => Some(BlockQuoteKind::Tip),
+                    Some("important") => Some(BlockQuoteKind::Important),
+                    Some("warning") => Some(BlockQuoteKind::Warning),
+                    Some("caution") => Some(BlockQuoteKind::Caution),
+                    _ => None,
+                },
+                body: self.blocks(node)?,
+            }),
+            "code" => {
+                let info = match (
+                    optional_string(node, "lang")?,
+                    optional_string(node, "meta")?,
+                ) {
+                    (None, None) => None,
+                    (Some(lang), None) => Some(lang),
+                    (lang, Some(meta)) => Some(format!("{} {meta}", lang.unwrap_or_default())),
+                };
+                let value = string(node, "value")?;
+                Block::CodeBlock(CodeBlock {
+                    kind: match info {
+                        Some(info) => CodeBlockKind::Fenced(text(info)),
+                        None => CodeBlockKind::Indented,
+                    },
+                    body: if value.is_empty() {
+                        Vec::new()
+                    } else {
+                        vec![Inline::Text(text(format!("{value}\n")))]
+                    },
+                })
+            }
+            "html" => Block::HtmlBlock(HtmlBlock {
+                body: vec![text(format!("{}\n", string(node, "value")?))],
+            }),
+            "footnoteDefinition" => Block::FootnoteDefinition(FootnoteDefinition {
+                label: text(match optional_string(node, "label")? {
+                    Some(label) => label,
+                    None => string(node, "identifier")?,
+                }),
+                body: self.blocks(node)?,
+            }),
+            "thematicBreak" => Block::Rule,
+            "list" => {
+                let start = if node.get("ordered").and_then(Value::as_bool) == Some(true) {
+                    Some(node.get("start").and_then(Value::as_u64).unwrap_or(1))
+                } else {
+                    None
+                };
+                let items = children(node)?
+                    .iter()
+                    .map(|item| self.item(item))
+                    .collect::<Result<_, _>>()?;
+                Block::List(List { start, items })
+            }
+            "defList" => {
+                let mut items: Vec<DefinitionItem<'static>> = Vec::new();
+                for child in children(node)? {
+                    match kind(child)? {
+                        "defListTerm" => items.push(DefinitionItem {
+                            title: self.inlines(child)?,
+                            definitions: Vec::new(),
+                        }),
+                        "defListDescription" => {
+                            let Some(item) = items.last_mut() else {
+                                return Err(bad(node, "children"));
+                            };
+                            // Descriptions hold blocks, but definitions here are only inline
+                            let mut body = Vec::new();
+                            for block in self.blocks(child)? {
+                                if !body.is_empty() {
+                                    body.push(Inline::SoftBreak);
+                                }
+                                match block {
+                                    Block::Paragraph(p) => body.extend(p.body),
+                                    block => body.push(Inline::InlineBlock(block)),
+                                }
+                            }
+                            item.definitions.push(DefinitionDefinition { body });
+                        }
+                        other => return Err(MdastError::UnknownNode(other.to_string())),
+                    }
+                }
+                Block::DefinitionList(DefinitionList { items })
+            }
+            "table" => {
+                let mut rows = children(node)?.iter().map(|row| {
+                    Ok(TableRow {
+                        cells: children(row)?
+                            .iter()
+                            .map(|cell| {
+                                Ok(TableCell {
+                                    body: self.inlines(cell)?,
+                                })
+                            })
+                            .collect::<Result<_, MdastError>>()?,
+                    })
+                });
+                let header = rows.next().ok_or_else(|| bad(node, "children"))??;
+                let rows = rows.collect::<Result<Vec<_>, MdastError>>()?;
+                let alignments = match node.get("align").and_then(Value::as_array) {
+                    Some(align) => align
+                        .iter()
+                        .map(|a| match a.as_str() {
+                            Some("left") => Alignment::Left,
+                            Some("center") => Alignment::Center,
+                            Some("right") => Alignment::Right,
+                            _ => Alignment::None,
+                        })
+                        .collect(),
+                    None => vec![Alignment::None; header.cells.len()],
+                };
+                Block::Table(Table {
+                    alignments,
+                    header: TableHead {
+                        cells: header.cells,
+                    },
+                    rows,
+                })
+            }
+            other => return Err(MdastError::UnknownNode(other.to_string())),
+        })
+    }
+
+    fn item(&self, node: &Value) -> Result<ListItem<'static>, MdastError> {
+        if kind(node)? != "listItem" {
+            return Err(MdastError::UnknownNode(kind(node)?.to_string()));
+        }
+        let checked = node.get("checked").and_then(Value::as_bool);
+        let marker = match node
+            .pointer("/data/marker")
+            .and_then(Value::as_str)
+            .and_then(|marker| marker.chars().next())
+        {
+            Some(marker) if checked.is_none_or(|c| c == matches!(marker, 'x' | 'X')) => {
+                Some(marker)
+            }
+            _ => checked.map(|checked| if checked { 'x' } else { ' ' }),
+        };
+        let blocks = self.blocks(node)?;
+        if node.get("spread").and_then(Value::as_bool) == Some(true) {
+            let mut blocks = blocks;
+            if let Some(marker) = marker {
+                match blocks.first_mut() {
+                    Some(Block::Paragraph(p)) => p.body.insert(0, Inline::TasklistMarker(marker)),
+                    _ => blocks.insert(
+                        0,
+                        Block::Paragraph(Paragraph {
+                            body: vec![Inline::TasklistMarker(marker)],
+                        }),
+                    ),
+                }
+            }
+            Ok(ListItem::Block(blocks))
+        } else {
+            let mut body: Vec<Inline<'static>> =
+                marker.map(Inline::TasklistMarker).into_iter().collect();
+            for block in blocks {
+                match block {
+                    Block::Paragraph(p) => body.extend(p.body),
+                    block => body.push(Inline::InlineBlock(block)),
+                }
+            }
+            Ok(ListItem::Inline(body))
+        }
+    }
+
+    fn inlines(&self, node: &Value) -> Result<Vec<Inline<'static>>, MdastError> {
+        let mut ret = Vec::new();
+        for child in children(node)? {
+            self.inline(child, &mut ret)?;
+        }
+        Ok(ret)
+    }
+
+    fn inline(&self, node: &Value, out: &mut Vec<Inline<'static>>) -> Result<(), MdastError> {
+        let link = |url: String, title: Option<String>, body| Link {
+            link_type: LinkType::Inline,
+            dest_url: text(url),
+            title: text(title.unwrap_or_default()),
+            id: text(""),
+            body,
+        };
+        let image = |url: String, title: Option<String>, alt: Option<String>| Image {
+            link_type: LinkType::Inline,
+            dest_url: text(url),
+            title: text(title.unwrap_or_default()),
+            id: text(""),
+            body: alt.into_iter().map(|alt| Inline::Text(text(alt))).collect(),
+        };
+        let inline = match kind(node)? {
+            "text" => {
+                // Soft breaks are just newlines within mdast text
+                for (n, line) in string(node, "value")?.split('\n').enumerate() {
+                    if n > 0 {
+                        out.push(Inline::SoftBreak);
+                    }
+                    if !line.is_empty() {
+                        out.push(Inline::Text(text(line)));
+                    }
+                }
+                return Ok(());
+            }
+            "break" => Inline::HardBreak,
+            "emphasis" => Inline::Emphasis(self.inlines(node)?),
+            "strong" => Inline::Strong(self.inlines(node)?),
+            "delete" => Inline::Strikethrough(self.inlines(node)?),
+            "inlineCode" => Inline::Code(text(string(node, "value")?)),
+            "inlineMath" if node.pointer("/data/display") == Some(&Value::Bool(true)) => {
+                Inline::DisplayMath(text(string(node, "value")?))
+            }
+            "inlineMath" => Inline::InlineMath(text(string(node, "value")?)),
+            "html" => Inline::Html(text(string(node, "value")?)),
+            "footnoteReference" => {
+                Inline::FootnoteReference(text(match optional_string(node, "label")? {
+                    Some(label) => label,
+                    None => string(node, "identifier")?,
+                }))
+            }
+            "link" => Inline::Link(link(
+                string(node, "url")?,
+                optional_string(node, "title")?,
+                self.inlines(node)?,
+            )),
+            "image" => Inline::Image(image(
+                string(node, "url")?,
+                optional_string(node, "title")?,
+                optional_string(node, "alt")?,
+            )),
+            kind @ ("linkReference" | "imageReference") => {
+                let identifier = string(node, "identifier")?.to_lowercase();
+                let Some((url, title)) = self.definitions.get(&identifier).cloned() else {
+                    // Without its definition, a reference is just its text
+                    match kind {
+                        "linkReference" => out.extend(self.inlines(node)?),
+                        _ => {
+                            let alt = optional_string(node, "alt")?.unwrap_or_default();
+                            out.push(Inline::Text(text(alt)));
+                        }
+                    }
+                    return Ok(());
+                };
+                if kind == "linkReference" {
+                    Inline::Link(link(url, title, self.inlines(node)?))
+                } else {
+                    Inline::Image(image(url, title, optional_string(node, "alt")?))
+                }
+            }
+            _ => Inline::InlineBlock(self.block(node)?),
+        };
+        out.push(inline);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use insta::assert_snapshot;
+
+    use crate::ParseOptions;
+
+    use super::*;
+
+    const SAMPLE: &str = r###"+++
+title = "Sample"
++++
+
+# Heading { #ident .todo key=value }
+
+- [ ] A task with `code` and a [link](https://example.com "title")
+- [x] Done
+  - [.] In progress
+
+> [!NOTE]
+> A note, with a footnote[^1]
+> and a second line
+
+[^1]: The footnote
+
+| Left | Right |
+| :--- | ----: |
+| a    | ~~b~~ |
+
+```rust ignore
+fn main() {}
+```
+
+Term
+: Definition
+
+---
+"###;
+
+    #[test]
+    fn roundtrip() {
+        let doc = Document::parse(SAMPLE, ParseOptions::all());
+        let mdast = doc.to_mdast();
+        let back = Document::from_mdast(&mdast).unwrap();
+        assert_eq!(back.to_mdast(), mdast);
+        assert_eq!(back.to_plain_text(), doc.to_plain_text());
+    }
+
+    #[test]
+    fn task_markers() {
+        let doc = Document::parse(
+            "- [.] Partial\n- [X] Capital\n- [p] Paused\n",
+            ParseOptions::all(),
+        );
+        let mut mdast = doc.to_mdast();
+        let items = &mdast["children"][0]["children"];
+        assert_eq!(
+            [0, 1, 2].map(|i| items[i]["checked"].clone()),
+            [json!(false), json!(true), json!(false)]
+        );
+        assert_eq!(Document::from_mdast(&mdast).unwrap(), doc);
+
+        // Ticked and unticked by another tool
+        mdast["children"][0]["children"][0]["checked"] = json!(true);
+        mdast["children"][0]["children"][1]["checked"] = json!(false);
+        let doc = Document::from_mdast(&mdast).unwrap();
+        let Block::List(list) = &doc.blocks[0] else {
+            panic!("Expected a list, got {:?}", doc.blocks[0]);
+        };
+        let markers = list
+            .items
+            .iter()
+            .map(|item| match item {
+                ListItem::Inline(body) => body.first().cloned(),
+                ListItem::Block(_) => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            markers,
+            [
+                Some(Inline::TasklistMarker('x')),
+                Some(Inline::TasklistMarker(' ')),
+                Some(Inline::TasklistMarker('p')),
+            ]
+        );
+    }
+
+    #[test]
+    fn mdast_shape() {
+        let doc = Document::parse(SAMPLE, ParseOptions::all());
+        let mdast = doc.to_mdast();
+        assert_snapshot!(serde_json::to_string_pretty(&mdast["children"][1]).unwrap(), @r###"
+        {
+          "children": [
+            {
+              "type": "text",
+              "value": "Heading"
+            }
+          ],
+          "data": {
+            "attributes": [
+              [
+                "key",
+                "value"
+              ]
+            ],
+            "classes": [
+              "todo"
+            ],
+            "hProperties": {
+              "className": [
+                "todo"
+              ],
+              "id": "ident",
+              "key": "value"
+            },
+            "id": "ident"
+          },
+          "depth": 1,
+          "type": "heading"
+        }
+        "###);
+        assert_snapshot!(serde_json::to_string_pretty(&mdast["children"][2]).unwrap(), @r###"
+        {
+          "children": [
+            {
+              "checked": false,
+              "children": [
+                {
+                  "children": [
+                    {
+                      "type": "text",
+                      "value": "A task with "
+                    },
+                    {
+                      "type": "inlineCode",
+                      "value": "code"
+                    },
+                    {
+                      "type": "text",
+                      "value": " and a "
+                    },
+                    {
+                      "children": [
+                        {
+                          "type": "text",
+                          "value": "link"
+                        }
+                      ],
+                      "title": "title",
+                      "type": "link",
+                      "url": "https://example.com"
+                    }
+                  ],
+                  "type": "paragraph"
+                }
+              ],
+              "spread": false,
+              "type": "listItem"
+            },
+            {
+              "checked": true,
+              "children": [
+                {
+                  "children": [
+                    {
+                      "type": "text",
+                      "value": "Done"
+                    }
+                  ],
+                  "type": "paragraph"
+                },
+                {
+                  "children": [
+                    {
+                      "checked": false,
+                      "children": [
+                        {
+                          "children": [
+                            {
+                              "type": "text",
+                              "value": "In progress"
+                            }
+                          ],
+                          "type": "paragraph"
+                        }
+                      ],
+                      "data": {
+                        "marker": "."
+                      },
+                      "spread": false,
+                      "type": "listItem"
+                    }
+                  ],
+                  "ordered": false,
+                  "spread": false,
+                  "start": null,
+                  "type": "list"
+                }
+              ],
+              "spread": false,
+              "type": "listItem"
+            }
+          ],
+          "ordered": false,
+          "spread": false,
+          "start": null,
+          "type": "list"
+        }
+        "###);
+    }
+
+    #[test]
+    fn references() {
+        let mdast = json!({
+            "type": "root",
+            "children": [
+                {
+                    "type": "paragraph",
+                    "children": [
+                        { "type": "text", "value": "See " },
+                        {
+                            "type": "linkReference",
+                            "identifier": "docs",
+                            "children": [{ "type": "text", "value": "the docs" }],
+                        },
+                        { "type": "text", "value": " or " },
+                        {
+                            "type": "linkReference",
+                            "identifier": "missing",
+                            "children": [{ "type": "text", "value": "nothing" }],
+                        },
+                    ],
+                },
+                { "type": "definition", "identifier": "DOCS", "url": "https://example.com" },
+            ],
+        });
+        let doc = Document::from_mdast(&mdast).unwrap();
+        let Block::Paragraph(p) = &doc.blocks[0] else {
+            panic!("Expected a paragraph, got {:?}", doc.blocks[0]);
+        };
+        assert!(
+            matches!(&p.body[1], Inline::Link(link) if link.dest_url.as_ref() == "https://example.com")
+        );
+        assert_eq!(doc.to_plain_text(), "See the docs or nothing\n");
+    }
+
+    #[test]
+    fn errors() {
+        let unknown = json!({ "type": "root", "children": [{ "type": "mdxjsEsm" }] });
+        assert_eq!(
+            Document::from_mdast(&unknown).unwrap_err().to_string(),
+            "Unsupported mdast node type `mdxjsEsm`"
+        );
+        let missing = json!({ "type": "root", "children": [{ "type": "code" }] });
+        assert_eq!(
+            Document::from_mdast(&missing),
+            Err(MdastError::BadField {
+                node: "code".into(),
+                field: "value"
+            })
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Tidying up runs of inline content
+//!
+//! pulldown-cmark splits text into several events around brackets, entities
+//! and escapes, so `a [b] c` parses as five separate [`Inline::Text`] nodes.
+//! [`Normalise`] is a fold which joins such runs back together, and drops
+//! soft breaks which do nothing, so that the result can be matched on as
+//! whole strings.
+//!
+//! ```
+//! use pulldown_cmark_ast::{fold::MarkdownFold, normalise::Normalise, Document, ParseOptions};
+//!
+//! let doc = Document::parse("a [b] c", ParseOptions::empty());
+//! let doc = Normalise::new().fold_document(doc);
+//! assert_eq!(doc.to_plain_text(), "a [b] c\n");
+//! ```
+
+use pulldown_cmark::CowStr;
+
+use crate::{ast::*, fold::MarkdownFold};
+
+/// Merges adjacent text, and removes redundant soft breaks
+#[derive(Debug, Clone, Default)]
+pub struct Normalise {
+    unescape: bool,
+}
+
+impl Normalise {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also remove backslash escapes from text
+    ///
+    /// Text which has been through a markdown renderer and then been put
+    /// into the tree as-is, rather than being parsed again, can carry
+    /// escapes such as `\[`.  These are reduced to the plain character.
+    pub fn unescape(mut self, unescape: bool) -> Self {
+        self.unescape = unescape;
+        self
+    }
+}
+
+impl<'a> MarkdownFold<'a> for Normalise {
+    fn fold_inlines(&mut self, inlines: Vec<Inline<'a>>) -> Vec<Inline<'a>> {
+        let mut ret: Vec<Inline<'a>> = Vec::with_capacity(inlines.len());
+        for inline in crate::fold::fold_inlines(self, inlines) {
+            match (ret.last_mut(), inline) {
+                (Some(Inline::Text(prev)), Inline::Text(text)) => {
+                    *prev = CowStr::from(format!("{prev}{text}"));
+                }
+                // A soft break at the start, or next to another break
+                (None | Some(Inline::SoftBreak | Inline::HardBreak), Inline::SoftBreak) => {}
+                (Some(last @ Inline::SoftBreak), Inline::HardBreak) => *last = Inline::HardBreak,
+                (_, inline) => ret.push(inline),
+            }
+        }
+        if matches!(ret.last(), Some(Inline::SoftBreak)) {
+            ret.pop();
+        }
+        // Only now is each escape sure to be in the same node as what it escapes
+        if self.unescape {
+            for inline in &mut ret {
+                match inline {
+                    Inline::Text(text) if text.contains('\\') => *text = unescape(text).into(),
+                    _ => {}
+                }
+            }
+        }
+        ret
+    }
+
+    fn fold_code_block(&mut self, code_block: CodeBlock<'a>) -> CodeBlock<'a> {
+        // Backslashes in code are meant literally, not as escapes
+        let unescape = std::mem::replace(&mut self.unescape, false);
+        let ret = crate::fold::fold_code_block(self, code_block);
+        self.unescape = unescape;
+        ret
+    }
+}
+
+/// Remove backslashes which escape ASCII punctuation
+fn unescape(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '\\' && next.is_ascii_punctuation() => {
+                ret.push(*next);
+                chars.next();
+            }
+            _ => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{builder::*, ParseOptions};
+
+    use super::*;
+
+    fn body<'d>(doc: &'d Document<'_>) -> &'d [Inline<'d>] {
+        let Block::Paragraph(p) = &doc.blocks[0] else {
+            panic!("Expected a paragraph, got {:?}", doc.blocks[0]);
+        };
+        &p.body
+    }
+
+    #[test]
+    fn merges_text() {
+        let doc = Document::parse("Some [text] &amp; *more [text]*", ParseOptions::all());
+        assert!(body(&doc).len() > 3);
+        let doc = Normalise::new().fold_document(doc);
+        assert_eq!(
+            body(&doc),
+            [text("Some [text] & "), emphasis(text("more [text]"))]
+        );
+    }
+
+    #[test]
+    fn soft_breaks() {
+        let doc = Document::builder()
+            .paragraph([
+                soft_break(),
+                text("a"),
+                soft_break(),
+                soft_break(),
+                text("b"),
+                soft_break(),
+                hard_break(),
+                text("c"),
+                soft_break(),
+            ])
+            .build();
+        let doc = Normalise::new().fold_document(doc);
+        assert_eq!(
+            body(&doc),
+            [text("a"), soft_break(), text("b"), hard_break(), text("c")]
+        );
+    }
+
+    #[test]
+    fn unescapes() {
+        let doc = Document::builder()
+            .paragraph([text("\\"), text("[.\\] "), text("C:\\Windows")])
+            .build();
+        let kept = Normalise::new().fold_document(doc.clone());
+        assert_eq!(body(&kept), [text("\\[.\\] C:\\Windows")]);
+        let unescaped = Normalise::new().unescape(true).fold_document(doc);
+        assert_eq!(body(&unescaped), [text("[.] C:\\Windows")]);
+    }
+
+    #[test]
+    fn code_is_not_unescaped() {
+        let doc = Document::parse("```sh\necho \\\"hi\\\" \\*\n```\n", ParseOptions::all());
+        let doc = Normalise::new().unescape(true).fold_document(doc);
+        let Block::CodeBlock(code) = &doc.blocks[0] else {
+            panic!("Expected a code block, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(code.body, [text("echo \\\"hi\\\" \\*\n")]);
+    }
+}
@@ -1,11 +1,11 @@
 use std::iter::Peekable;
 
-use pulldown_cmark::{Alignment, CowStr, Event, MetadataBlockKind, Tag, TagEnd};
+use pulldown_cmark::{Alignment, Event, MetadataBlockKind, Tag, TagEnd};
 
 use crate::ast::*;
 
-impl Document {
-    pub fn from_events<'a>(events: impl IntoIterator<Item = Event<'a>>) -> Self {
+impl<'a> Document<'a> {
+    pub fn from_events(events: impl IntoIterator<Item = Event<'a>>) -> Self {
         let mut events = events.into_iter().peekable();
         let mut blocks = Vec::new();
         while events.peek().is_some() {
@@ -15,14 +15,90 @@ impl Document {
         Self { blocks }
     }
 
-    pub fn parse(input: &str, options: pulldown_cmark::Options) -> Self {
-        let parser = pulldown_cmark::Parser::new_ext(input, options);
-        Self::from_events(parser)
+    /// Parse some markdown
+    ///
+    /// When task lists are enabled, any single character in square brackets
+    /// at the start of a list item is taken as a task marker, not just the
+    /// `[ ]` and `[x]` which pulldown-cmark recognises.  `[X]` is kept as
+    /// written rather than being read as `[x]`.
+    pub fn parse(input: &'a str, options: pulldown_cmark::Options) -> Self {
+        let parser = pulldown_cmark::Parser::new_ext(input, options)
+            .into_offset_iter()
+            .map(|(event, range)| match event {
+                // Left as text to be picked up with the other custom markers
+                Event::TaskListMarker(true) if input[range].contains('X') => {
+                    Event::Text("[X] ".into())
+                }
+                event => event,
+            });
+        let mut ret = Self::from_events(parser);
+        if options.contains(pulldown_cmark::Options::ENABLE_TASKLISTS) {
+            task_markers(&mut ret.blocks);
+        }
+        ret
+    }
+}
+
+fn task_markers(blocks: &mut [Block<'_>]) {
+    for block in blocks {
+        match block {
+            Block::BlockQuote(BlockQuote { body, .. })
+            | Block::FootnoteDefinition(FootnoteDefinition { body, .. }) => task_markers(body),
+            Block::List(list) => {
+                for item in &mut list.items {
+                    match item {
+                        ListItem::Inline(body) => {
+                            task_marker(body);
+                            for inline in body {
+                                if let Inline::InlineBlock(block) = inline {
+                                    task_markers(std::slice::from_mut(block));
+                                }
+                            }
+                        }
+                        ListItem::Block(body) => {
+                            if let Some(Block::Paragraph(p)) = body.first_mut() {
+                                task_marker(&mut p.body);
+                            }
+                            task_markers(body);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Turn a leading `[c]` in the text of a list item into a task marker
+///
+/// pulldown-cmark splits the brackets into separate text events, so the
+/// leading text nodes are considered together.
+fn task_marker(body: &mut Vec<Inline<'_>>) {
+    let mut prefix = String::new();
+    let mut used = 0;
+    while prefix.chars().count() < 4 {
+        let Some(Inline::Text(text)) = body.get(used) else {
+            break;
+        };
+        prefix.push_str(text);
+        used += 1;
     }
+    let mut chars = prefix.chars();
+    let marker = match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some('['), Some(c), Some(']'), Some(' ')) if !matches!(c, '[' | ']') => c,
+        (Some('['), Some(c), Some(']'), None) if !matches!(c, '[' | ']') && used == body.len() => c,
+        _ => return,
+    };
+    let rest = chars.as_str().to_string();
+    body.splice(
+        ..used,
+        std::iter::once(Inline::TasklistMarker(marker))
+            .chain((!rest.is_empty()).then(|| Inline::Text(rest.into()))),
+    );
 }
 
-impl Block {
-    fn from_events<'a>(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
+impl<'a> Block<'a> {
+    fn from_events(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
         // We're only ever called for blocks, so there will always be something here
         match events.next().unwrap() {
             Event::Start(tag) => {
@@ -85,7 +161,7 @@ impl Block {
         }
     }
 
-    fn many_from_events<'a>(
+    fn many_from_events(
         events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
         end: TagEnd,
     ) -> Vec<Self> {
@@ -100,11 +176,8 @@ impl Block {
     }
 }
 
-impl Heading {
-    fn from_events<'a>(
-        tag: Tag<'a>,
-        events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
-    ) -> Self {
+impl<'a> Heading<'a> {
+    fn from_events(tag: Tag<'a>, events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
         let Tag::Heading {
             level,
             id,
@@ -120,19 +193,16 @@ impl Heading {
 
         Self {
             level,
-            id: id.map(CowStr::into_static),
-            classes: classes.into_iter().map(CowStr::into_static).collect(),
-            attrs: attrs
-                .into_iter()
-                .map(|(k, v)| (k.into_static(), v.map(CowStr::into_static)))
-                .collect(),
+            id,
+            classes,
+            attrs,
             body,
         }
     }
 }
 
-impl Inline {
-    fn from_events<'a>(
+impl<'a> Inline<'a> {
+    fn from_events(
         events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
         end: TagEnd,
     ) -> Vec<Self> {
@@ -143,7 +213,7 @@ impl Inline {
                     break ret;
                 }
 
-                Event::Text(text) => ret.push(Inline::Text(text.into_static())),
+                Event::Text(text) => ret.push(Inline::Text(text)),
                 Event::SoftBreak => ret.push(Inline::SoftBreak),
                 Event::HardBreak => ret.push(Inline::HardBreak),
                 Event::Start(tag) => match tag {
@@ -215,12 +285,14 @@ impl Inline {
                     }
                 },
 
-                Event::InlineHtml(h) => ret.push(Self::Html(h.into_static())),
-                Event::InlineMath(m) => ret.push(Self::InlineMath(m.into_static())),
-                Event::DisplayMath(m) => ret.push(Self::DisplayMath(m.into_static())),
-                Event::Code(c) => ret.push(Self::Code(c.into_static())),
-                Event::FootnoteReference(f) => ret.push(Self::FootnoteReference(f.into_static())),
-                Event::TaskListMarker(b) => ret.push(Self::TasklistMarker(b)),
+                Event::InlineHtml(h) => ret.push(Self::Html(h)),
+                Event::InlineMath(m) => ret.push(Self::InlineMath(m)),
+                Event::DisplayMath(m) => ret.push(Self::DisplayMath(m)),
+                Event::Code(c) => ret.push(Self::Code(c)),
+                Event::FootnoteReference(f) => ret.push(Self::FootnoteReference(f)),
+                Event::TaskListMarker(ticked) => {
+                    ret.push(Self::TasklistMarker(if ticked { 'x' } else { ' ' }))
+                }
 
                 e => {
                     panic!("Unable to process event {e:?} for an inline");
@@ -230,8 +302,8 @@ impl Inline {
     }
 }
 
-impl Paragraph {
-    fn from_events<'a>(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
+impl<'a> Paragraph<'a> {
+    fn from_events(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
         // We run until we end a paragraph
         Self {
             body: Inline::from_events(events, TagEnd::Paragraph),
@@ -239,11 +311,8 @@ impl Paragraph {
     }
 }
 
-impl Image {
-    fn from_events<'a>(
-        events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
-        img: Tag<'a>,
-    ) -> Self {
+impl<'a> Image<'a> {
+    fn from_events(events: &mut Peekable<impl Iterator<Item = Event<'a>>>, img: Tag<'a>) -> Self {
         let Tag::Image {
             link_type,
             dest_url,
@@ -256,19 +325,16 @@ impl Image {
         let body = Inline::from_events(events, TagEnd::Image);
         Self {
             link_type,
-            dest_url: dest_url.into_static(),
-            title: title.into_static(),
-            id: id.into_static(),
+            dest_url,
+            title,
+            id,
             body,
         }
     }
 }
 
-impl Link {
-    fn from_events<'a>(
-        events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
-        link: Tag<'a>,
-    ) -> Self {
+impl<'a> Link<'a> {
+    fn from_events(events: &mut Peekable<impl Iterator<Item = Event<'a>>>, link: Tag<'a>) -> Self {
         let Tag::Link {
             link_type,
             dest_url,
@@ -281,16 +347,16 @@ impl Link {
         let body = Inline::from_events(events, TagEnd::Link);
         Self {
             link_type,
-            dest_url: dest_url.into_static(),
-            title: title.into_static(),
-            id: id.into_static(),
+            dest_url,
+            title,
+            id,
             body,
         }
     }
 }
 
-impl BlockQuote {
-    fn from_events<'a>(
+impl<'a> BlockQuote<'a> {
+    fn from_events(
         events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
         blockquote: Tag<'a>,
     ) -> Self {
@@ -305,8 +371,8 @@ impl BlockQuote {
     }
 }
 
-impl CodeBlock {
-    fn from_events<'a>(
+impl<'a> CodeBlock<'a> {
+    fn from_events(
         events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
         codeblock: Tag<'a>,
     ) -> Self {
@@ -315,18 +381,18 @@ impl CodeBlock {
         };
 
         Self {
-            kind: kind.into_static(),
+            kind,
             body: Inline::from_events(events, TagEnd::CodeBlock),
         }
     }
 }
 
-impl HtmlBlock {
-    fn from_events<'a>(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
+impl<'a> HtmlBlock<'a> {
+    fn from_events(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
         let mut body = Vec::new();
         loop {
             match events.next().unwrap() {
-                Event::Html(s) => body.push(s.into_static()),
+                Event::Html(s) => body.push(s),
                 Event::End(TagEnd::HtmlBlock) => break,
                 _ => unreachable!(),
             }
@@ -335,8 +401,8 @@ impl HtmlBlock {
     }
 }
 
-impl FootnoteDefinition {
-    fn from_events<'a>(
+impl<'a> FootnoteDefinition<'a> {
+    fn from_events(
         events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
         footnote: Tag<'a>,
     ) -> Self {
@@ -345,14 +411,14 @@ impl FootnoteDefinition {
         };
 
         Self {
-            label: label.into_static(),
+            label,
             body: Block::many_from_events(events, TagEnd::FootnoteDefinition),
         }
     }
 }
 
-impl List {
-    fn from_events<'a>(
+impl<'a> List<'a> {
+    fn from_events(
         events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
         start: Option<u64>,
     ) -> Self {
@@ -370,8 +436,8 @@ impl List {
     }
 }
 
-impl ListItem {
-    fn from_events<'a>(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
+impl<'a> ListItem<'a> {
+    fn from_events(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
         if matches!(
             events.peek().unwrap(),
             Event::Text(_)
@@ -397,8 +463,8 @@ impl ListItem {
     }
 }
 
-impl DefinitionList {
-    fn from_events<'a>(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
+impl<'a> DefinitionList<'a> {
+    fn from_events(events: &mut Peekable<impl Iterator<Item = Event<'a>>>) -> Self {
         let mut items = Vec::new();
 
         let empty_item = DefinitionItem {
@@ -436,8 +502,8 @@ impl DefinitionList {
     }
 }
 
-impl MetadataBlock {
-    fn from_events<'a>(
+impl<'a> MetadataBlock<'a> {
+    fn from_events(
         events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
         kind: MetadataBlockKind,
     ) -> Self {
@@ -451,18 +517,15 @@ impl MetadataBlock {
 
         assert_eq!(kind, endkind);
 
-        Self {
-            kind,
-            content: content.into_static(),
-        }
+        Self { kind, content }
     }
 }
 
-impl Table {
-    fn from_events<'a>(
+impl<'a> Table<'a> {
+    fn from_events(
         events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
         alignments: Vec<Alignment>,
-    ) -> Table {
+    ) -> Self {
         let mut ret = Table {
             alignments,
             header: TableHead { cells: Vec::new() },
@@ -486,8 +549,8 @@ impl Table {
     }
 }
 
-impl TableCell {
-    fn from_events<'a>(
+impl<'a> TableCell<'a> {
+    fn from_events(
         events: &mut Peekable<impl Iterator<Item = Event<'a>>>,
         end: TagEnd,
     ) -> Vec<Self> {
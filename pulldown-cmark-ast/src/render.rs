@@ -0,0 +1,494 @@
+//! Rendering documents as markdown text
+//!
+//! The heavy lifting is done by [pulldown_cmark_to_cmark].  On top of that,
+//! [`RenderOptions`] can give each level of nested list its own bullet, and
+//! can reflow paragraphs to fit a line width.
+
+use std::fmt;
+
+use pulldown_cmark::{Event, Tag, TagEnd};
+use pulldown_cmark_to_cmark::{
+    calculate_code_block_token_count, cmark_resume_with_options, Options, State,
+    DEFAULT_CODE_BLOCK_TOKEN_COUNT,
+};
+
+use crate::ast::*;
+
+/// The markdown style to render documents in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions<'a> {
+    /// The bullets for unordered lists, outermost first
+    ///
+    /// Lists nested more deeply than this reuse the last bullet.
+    pub list_tokens: Vec<char>,
+    /// What follows the number of an ordered list item, `.` or `)`
+    pub ordered_list_token: char,
+    /// Number ordered list items in sequence, rather than repeating the first number
+    pub increment_ordered_list_bullets: bool,
+    pub emphasis_token: char,
+    pub strong_token: &'a str,
+    /// The character to fence code blocks with, `` ` `` or `~`
+    pub code_block_token: char,
+    /// Reflow paragraphs to fit within this many columns
+    ///
+    /// Lines are only broken between words, and never where the next word
+    /// could be mistaken for the start of a new block, so some lines may
+    /// still run long.
+    pub wrap_width: Option<usize>,
+}
+
+impl Default for RenderOptions<'_> {
+    fn default() -> Self {
+        Self {
+            list_tokens: vec!['*'],
+            ordered_list_token: '.',
+            increment_ordered_list_bullets: false,
+            emphasis_token: '*',
+            strong_token: "**",
+            code_block_token: '`',
+            wrap_width: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RenderError {
+    /// An option was given a value which markdown has no syntax for
+    InvalidOption {
+        option: &'static str,
+        value: String,
+    },
+    Format(fmt::Error),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::InvalidOption { option, value } => {
+                write!(f, "`{value}` is not a valid {option}")
+            }
+            RenderError::Format(e) => write!(f, "Unable to write markdown: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::InvalidOption { .. } => None,
+            RenderError::Format(e) => Some(e),
+        }
+    }
+}
+
+impl From<fmt::Error> for RenderError {
+    fn from(value: fmt::Error) -> Self {
+        RenderError::Format(value)
+    }
+}
+
+impl RenderOptions<'_> {
+    /// Check that every option has a value which markdown can express
+    pub fn validate(&self) -> Result<(), RenderError> {
+        fn check(
+            option: &'static str,
+            valid: bool,
+            value: impl ToString,
+        ) -> Result<(), RenderError> {
+            if valid {
+                Ok(())
+            } else {
+                Err(RenderError::InvalidOption {
+                    option,
+                    value: value.to_string(),
+                })
+            }
+        }
+
+        check("list token", !self.list_tokens.is_empty(), "")?;
+        for token in &self.list_tokens {
+            check("list token", matches!(token, '-' | '*' | '+'), token)?;
+        }
+        check(
+            "ordered list token",
+            matches!(self.ordered_list_token, '.' | ')'),
+            self.ordered_list_token,
+        )?;
+        check(
+            "emphasis token",
+            matches!(self.emphasis_token, '*' | '_'),
+            self.emphasis_token,
+        )?;
+        check(
+            "strong token",
+            matches!(self.strong_token, "**" | "__"),
+            self.strong_token,
+        )?;
+        check(
+            "code block token",
+            matches!(self.code_block_token, '`' | '~'),
+            self.code_block_token,
+        )?;
+        if let Some(width) = self.wrap_width {
+            check("wrap width", width > 0, width)?;
+        }
+        Ok(())
+    }
+
+    /// The options for pulldown-cmark-to-cmark, within `depth` lists
+    fn cmark(&self, depth: usize) -> Options<'_> {
+        let bullet = depth.saturating_sub(1).min(self.list_tokens.len() - 1);
+        Options {
+            list_token: self.list_tokens[bullet],
+            ordered_list_token: self.ordered_list_token,
+            increment_ordered_list_bullets: self.increment_ordered_list_bullets,
+            emphasis_token: self.emphasis_token,
+            strong_token: self.strong_token,
+            code_block_token: self.code_block_token,
+            ..Options::default()
+        }
+    }
+}
+
+impl Document<'_> {
+    pub fn render(&self, options: RenderOptions<'_>) -> Result<String, RenderError> {
+        options.validate()?;
+        let mut events = Vec::new();
+        self.push_events(&mut events);
+        if let Some(width) = options.wrap_width {
+            events = wrap(events, width);
+        }
+        let code_block_token_count =
+            calculate_code_block_token_count(&events).unwrap_or(DEFAULT_CODE_BLOCK_TOKEN_COUNT);
+
+        // pulldown-cmark-to-cmark only knows one bullet, so each stretch of
+        // events between list starts and ends is rendered separately, with
+        // the bullet for its depth.
+        let mut ret = String::new();
+        let mut state: Option<State<'_>> = None;
+        let (mut depth, mut start) = (0, 0);
+        for i in 0..=events.len() {
+            let change = match events.get(i) {
+                Some(Event::Start(Tag::List(_))) => 1,
+                Some(Event::End(TagEnd::List(_))) => -1,
+                Some(_) => continue,
+                None => 0,
+            };
+            let options = Options {
+                code_block_token_count,
+                ..options.cmark(depth)
+            };
+            state = Some(cmark_resume_with_options(
+                events[start..i].iter(),
+                &mut ret,
+                state,
+                options,
+            )?);
+            depth = depth.saturating_add_signed(change);
+            start = i;
+        }
+        if let Some(state) = state {
+            state.finalize(&mut ret)?;
+        }
+        Ok(ret)
+    }
+}
+
+/// Reflow the text of paragraphs and list items to fit within `width` columns
+fn wrap(events: Vec<Event<'_>>, width: usize) -> Vec<Event<'_>> {
+    let mut ret = Vec::with_capacity(events.len());
+    // The width of each block quote and list item marker we're within
+    let mut indents: Vec<usize> = Vec::new();
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // The inline content currently being gathered, and its indentation
+    let mut text: Option<(Vec<Event<'_>>, usize)> = None;
+
+    for event in events {
+        match &event {
+            Event::Start(Tag::BlockQuote(_)) => indents.push(2),
+            Event::End(TagEnd::BlockQuote(_)) => _ = indents.pop(),
+            Event::Start(Tag::List(start)) => lists.push(*start),
+            Event::End(TagEnd::List(_)) => _ = lists.pop(),
+            Event::Start(Tag::Item) => {
+                indents.push(match lists.last() {
+                    Some(Some(n)) => n.to_string().len() + 2,
+                    _ => 2,
+                });
+            }
+            Event::End(TagEnd::Item) => _ = indents.pop(),
+            _ => {}
+        }
+        let ends_text = match &event {
+            Event::Start(
+                Tag::Emphasis
+                | Tag::Strong
+                | Tag::Strikethrough
+                | Tag::Link { .. }
+                | Tag::Image { .. },
+            ) => false,
+            Event::Start(_) | Event::End(TagEnd::Paragraph | TagEnd::Item) => true,
+            _ => false,
+        };
+        if ends_text {
+            if let Some((run, indent)) = text.take() {
+                ret.extend(reflow(run, indent, width));
+            }
+        }
+        match &mut text {
+            Some((run, _)) => run.push(event),
+            None => {
+                let starts_text = matches!(event, Event::Start(Tag::Paragraph | Tag::Item));
+                ret.push(event);
+                if starts_text {
+                    text = Some((Vec::new(), indents.iter().sum()));
+                }
+            }
+        }
+    }
+    if let Some((run, indent)) = text {
+        ret.extend(reflow(run, indent, width));
+    }
+    ret
+}
+
+enum Piece<'a> {
+    /// Somewhere the line could be broken
+    Space,
+    HardBreak,
+    /// Something which can't be broken, and how wide it is once rendered
+    Atom(Event<'a>, usize),
+}
+
+/// Break a run of inline content into lines, starting at column `indent`
+///
+/// Existing soft breaks are replaced, and new ones are put between words
+/// wherever the line would otherwise be too long.  The width of markup is
+/// estimated from how pulldown-cmark-to-cmark writes it.
+fn reflow(run: Vec<Event<'_>>, indent: usize, width: usize) -> Vec<Event<'_>> {
+    let mut pieces = Vec::new();
+    // The widths of the destinations of the links and images we're within
+    let mut destinations: Vec<usize> = Vec::new();
+    for event in run {
+        let width = match &event {
+            Event::Text(text) => {
+                for (n, word) in text.split(' ').enumerate() {
+                    if n > 0 {
+                        pieces.push(Piece::Space);
+                    }
+                    if !word.is_empty() {
+                        let width = word.chars().count();
+                        pieces.push(Piece::Atom(Event::Text(word.to_string().into()), width));
+                    }
+                }
+                continue;
+            }
+            Event::SoftBreak => {
+                pieces.push(Piece::Space);
+                continue;
+            }
+            Event::HardBreak => {
+                pieces.push(Piece::HardBreak);
+                continue;
+            }
+            Event::Code(s) | Event::InlineMath(s) => s.chars().count() + 2,
+            Event::DisplayMath(s) => s.chars().count() + 4,
+            Event::FootnoteReference(s) => s.chars().count() + 3,
+            Event::InlineHtml(s) => s.chars().count(),
+            Event::TaskListMarker(_) => 4,
+            Event::Start(Tag::Emphasis) | Event::End(TagEnd::Emphasis) => 1,
+            Event::Start(Tag::Strong | Tag::Strikethrough)
+            | Event::End(TagEnd::Strong | TagEnd::Strikethrough) => 2,
+            Event::Start(Tag::Link {
+                dest_url, title, ..
+            })
+            | Event::Start(Tag::Image {
+                dest_url, title, ..
+            }) => {
+                let title = match title.chars().count() {
+                    0 => 0,
+                    n => n + 3,
+                };
+                destinations.push(dest_url.chars().count() + title + 3);
+                if matches!(event, Event::Start(Tag::Image { .. })) {
+                    2
+                } else {
+                    1
+                }
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => destinations.pop().unwrap_or(0),
+            _ => 0,
+        };
+        pieces.push(Piece::Atom(event, width));
+    }
+
+    let mut ret: Vec<Event<'_>> = Vec::with_capacity(pieces.len());
+    let mut column = indent;
+    for i in 0..pieces.len() {
+        match &pieces[i] {
+            Piece::Space => {
+                let next = pieces[i + 1..]
+                    .iter()
+                    .map_while(|piece| match piece {
+                        Piece::Atom(event, width) => Some((event, *width)),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let next_width: usize = next.iter().map(|(_, width)| width).sum();
+                let can_break = next.first().is_some_and(|(event, _)| can_start_line(event));
+                if column + 1 + next_width > width && column > indent && can_break {
+                    ret.push(Event::SoftBreak);
+                    column = indent;
+                } else {
+                    push_text(&mut ret, " ");
+                    column += 1;
+                }
+            }
+            Piece::HardBreak => {
+                ret.push(Event::HardBreak);
+                column = indent;
+            }
+            Piece::Atom(_, width) => column += width,
+        }
+        if let Piece::Atom(..) = &pieces[i] {
+            let Piece::Atom(event, _) = std::mem::replace(&mut pieces[i], Piece::Space) else {
+                unreachable!()
+            };
+            match event {
+                Event::Text(text) => push_text(&mut ret, &text),
+                event => ret.push(event),
+            }
+        }
+    }
+    ret
+}
+
+/// Add text, joining it onto any text just before it
+fn push_text(events: &mut Vec<Event<'_>>, text: &str) {
+    match events.last_mut() {
+        Some(Event::Text(prev)) => *prev = format!("{prev}{text}").into(),
+        _ => events.push(Event::Text(text.to_string().into())),
+    }
+}
+
+/// Whether a line of a paragraph could start with this, without it being
+/// read as the start of some other block
+fn can_start_line(event: &Event<'_>) -> bool {
+    match event {
+        Event::Text(word) => match word.chars().next() {
+            None => false,
+            Some('-' | '+' | '*' | '#' | '>' | '=' | '`' | '~' | '|' | '<' | '_' | ':') => false,
+            Some(c) if c.is_ascii_digit() => !word
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .starts_with(['.', ')']),
+            Some(_) => true,
+        },
+        Event::InlineHtml(_) | Event::TaskListMarker(_) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use insta::assert_snapshot;
+
+    use crate::ParseOptions;
+
+    use super::*;
+
+    const LISTS: &str = r###"
+- One
+  - Two
+    - Three
+      - Four
+- Five
+
+1. First
+2. Second
+"###;
+
+    #[test]
+    fn list_tokens() {
+        let doc = Document::parse(LISTS, ParseOptions::all());
+        let options = RenderOptions {
+            list_tokens: vec!['-', '*', '+'],
+            ordered_list_token: ')',
+            increment_ordered_list_bullets: true,
+            ..Default::default()
+        };
+        assert_snapshot!(doc.render(options).unwrap(), @r###"
+        - One
+          * Two
+            + Three
+              + Four
+        - Five
+
+        1) First
+        2) Second
+        "###);
+    }
+
+    const PROSE: &str = r###"
+This paragraph is long enough that it will need to be wrapped, and it has *some
+emphasis* and a [link](https://example.com) in it, which all count towards the width.
+
+- A list item which is also rather long, and so it should be wrapped to fit within the width
+  > And a quote within it, which is long enough to need wrapping too, at least once
+
+Don't break before a number like 1. or a dash - because those would start a new list.
+
+    Code blocks are never wrapped, no matter how long their lines are, since that would change them
+"###;
+
+    #[test]
+    fn wrapping() {
+        let doc = Document::parse(PROSE, ParseOptions::all());
+        let options = RenderOptions {
+            wrap_width: Some(40),
+            ..Default::default()
+        };
+        let rendered = doc.render(options).unwrap();
+        assert_snapshot!(rendered, @r###"
+        This paragraph is long enough that it
+        will need to be wrapped, and it has
+        *some emphasis* and a
+        [link](https://example.com) in it, which
+        all count towards the width.
+
+        * A list item which is also rather long,
+          and so it should be wrapped to fit
+          within the width
+           > 
+           > And a quote within it, which is long
+           > enough to need wrapping too, at
+           > least once
+
+        Don’t break before a number like 1. or a
+        dash - because those would start a new
+        list.
+
+            Code blocks are never wrapped, no matter how long their lines are, since that would change them
+        "###);
+        assert_eq!(
+            Document::parse(&rendered, ParseOptions::all()).to_plain_text(),
+            doc.to_plain_text()
+        );
+    }
+
+    #[test]
+    fn invalid_options() {
+        let doc = Document::parse(LISTS, ParseOptions::all());
+        let options = RenderOptions {
+            list_tokens: vec!['-', '!'],
+            ..Default::default()
+        };
+        assert_eq!(
+            doc.render(options).unwrap_err().to_string(),
+            "`!` is not a valid list token"
+        );
+        let options = RenderOptions {
+            list_tokens: vec![],
+            ..Default::default()
+        };
+        assert!(doc.render(options).is_err());
+    }
+}
@@ -0,0 +1,403 @@
+//! The heading hierarchy of a list of blocks
+//!
+//! Markdown has no explicit nesting of headings, a heading simply runs
+//! until the next heading of the same or a shallower level.  [`Sections`]
+//! makes that structure explicit so that whole sections can be found,
+//! replaced, removed, or moved.  Converting back with
+//! [`Sections::into_blocks`] always yields the original blocks in their
+//! original order.
+
+use pulldown_cmark::HeadingLevel;
+
+use crate::ast::*;
+
+/// A list of blocks, arranged by heading
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sections<'a> {
+    /// Any blocks which come before the first heading
+    pub preamble: Vec<Block<'a>>,
+    pub sections: Vec<Section<'a>>,
+}
+
+/// A heading, along with everything up to the next heading which isn't deeper
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section<'a> {
+    pub heading: Heading<'a>,
+    /// The blocks between the heading and the first subsection
+    pub body: Vec<Block<'a>>,
+    pub children: Vec<Section<'a>>,
+}
+
+/// The location of a section, as indices into successive `children`
+///
+/// The first index selects from [`Sections::sections`].
+pub type SectionPath = Vec<usize>;
+
+impl<'a> Sections<'a> {
+    pub fn from_blocks(blocks: Vec<Block<'a>>) -> Self {
+        let mut ret = Sections::default();
+        // The chain of sections currently open, outermost first
+        let mut open: Vec<Section<'a>> = Vec::new();
+
+        fn close<'a>(ret: &mut Sections<'a>, open: &mut Vec<Section<'a>>) {
+            let section = open.pop().unwrap();
+            match open.last_mut() {
+                Some(parent) => parent.children.push(section),
+                None => ret.sections.push(section),
+            }
+        }
+
+        for block in blocks {
+            match block {
+                Block::Heading(heading) => {
+                    while open
+                        .last()
+                        .is_some_and(|s| s.heading.level >= heading.level)
+                    {
+                        close(&mut ret, &mut open);
+                    }
+                    open.push(Section::new(heading));
+                }
+                block => match open.last_mut() {
+                    Some(section) => section.body.push(block),
+                    None => ret.preamble.push(block),
+                },
+            }
+        }
+        while !open.is_empty() {
+            close(&mut ret, &mut open);
+        }
+
+        ret
+    }
+
+    pub fn into_blocks(self) -> Vec<Block<'a>> {
+        let mut ret = self.preamble;
+        self.sections
+            .into_iter()
+            .for_each(|s| s.push_blocks(&mut ret));
+        ret
+    }
+
+    /// Depth-first iteration over every section
+    pub fn iter(&self) -> impl Iterator<Item = &Section<'a>> {
+        let mut stack: Vec<&Section<'a>> = self.sections.iter().rev().collect();
+        std::iter::from_fn(move || {
+            let next = stack.pop()?;
+            stack.extend(next.children.iter().rev());
+            Some(next)
+        })
+    }
+
+    /// The path to the first section (depth-first) matching the predicate
+    pub fn path_of(&self, pred: impl Fn(&Section<'a>) -> bool) -> Option<SectionPath> {
+        fn search<'a>(
+            sections: &[Section<'a>],
+            pred: &dyn Fn(&Section<'a>) -> bool,
+            path: &mut SectionPath,
+        ) -> bool {
+            for (i, section) in sections.iter().enumerate() {
+                path.push(i);
+                if pred(section) || search(&section.children, pred, path) {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+        let mut path = Vec::new();
+        search(&self.sections, &pred, &mut path).then_some(path)
+    }
+
+    pub fn find(&self, pred: impl Fn(&Section<'a>) -> bool) -> Option<&Section<'a>> {
+        self.iter().find(|s| pred(s))
+    }
+
+    pub fn find_mut(&mut self, pred: impl Fn(&Section<'a>) -> bool) -> Option<&mut Section<'a>> {
+        let path = self.path_of(pred)?;
+        self.get_mut(&path)
+    }
+
+    pub fn find_by_class(&self, class: &str) -> Option<&Section<'a>> {
+        self.find(|s| s.has_class(class))
+    }
+
+    pub fn find_by_id(&self, id: &str) -> Option<&Section<'a>> {
+        self.find(|s| s.id() == Some(id))
+    }
+
+    pub fn find_by_title(&self, title: &str) -> Option<&Section<'a>> {
+        self.find(|s| s.title() == title)
+    }
+
+    pub fn get(&self, path: &[usize]) -> Option<&Section<'a>> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.sections.get(*first)?, |s, i| s.children.get(*i))
+    }
+
+    pub fn get_mut(&mut self, path: &[usize]) -> Option<&mut Section<'a>> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.sections.get_mut(*first)?, |s, i| {
+                s.children.get_mut(*i)
+            })
+    }
+
+    /// The list of sections which the last element of `path` indexes into
+    fn siblings_mut(&mut self, path: &[usize]) -> Option<&mut Vec<Section<'a>>> {
+        match path.split_last()? {
+            (_, []) => Some(&mut self.sections),
+            (_, parent) => Some(&mut self.get_mut(parent)?.children),
+        }
+    }
+
+    /// Replace the section at `path`, returning the section which was there
+    pub fn replace(&mut self, path: &[usize], section: Section<'a>) -> Option<Section<'a>> {
+        let target = self.get_mut(path)?;
+        Some(std::mem::replace(target, section))
+    }
+
+    /// Remove the section at `path`, along with all of its subsections
+    pub fn remove(&mut self, path: &[usize]) -> Option<Section<'a>> {
+        let index = *path.last()?;
+        let siblings = self.siblings_mut(path)?;
+        (index < siblings.len()).then(|| siblings.remove(index))
+    }
+
+    /// Insert a section such that it ends up at `path`
+    ///
+    /// Hands the section back if `path` does not lead anywhere it could
+    /// be inserted.
+    pub fn insert(&mut self, path: &[usize], section: Section<'a>) -> Option<Section<'a>> {
+        let Some(index) = path.last().copied() else {
+            return Some(section);
+        };
+        match self.siblings_mut(path) {
+            Some(siblings) if index <= siblings.len() => {
+                siblings.insert(index, section);
+                None
+            }
+            _ => Some(section),
+        }
+    }
+
+    /// Move the section at `from` so that it ends up at `to`
+    ///
+    /// `to` is interpreted after the section has been removed from `from`.
+    /// If `to` is not valid the section is left where it was.  Heading
+    /// levels are left alone, use [`Section::set_level`] if the section
+    /// needs to fit in at its new depth.
+    pub fn move_section(&mut self, from: &[usize], to: &[usize]) -> bool {
+        let Some(section) = self.remove(from) else {
+            return false;
+        };
+        match self.insert(to, section) {
+            None => true,
+            Some(section) => {
+                let unplaced = self.insert(from, section);
+                assert!(
+                    unplaced.is_none(),
+                    "Section must fit back where it came from"
+                );
+                false
+            }
+        }
+    }
+}
+
+impl<'a> From<Document<'a>> for Sections<'a> {
+    fn from(document: Document<'a>) -> Self {
+        Self::from_blocks(document.blocks)
+    }
+}
+
+impl<'a> From<Sections<'a>> for Document<'a> {
+    fn from(sections: Sections<'a>) -> Self {
+        Document {
+            blocks: sections.into_blocks(),
+        }
+    }
+}
+
+impl<'a> Section<'a> {
+    pub fn new(heading: Heading<'a>) -> Self {
+        Self {
+            heading,
+            body: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn level(&self) -> HeadingLevel {
+        self.heading.level
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.heading.id.as_deref()
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        self.heading.has_class(class)
+    }
+
+    /// The text of the heading, without any formatting
+    pub fn title(&self) -> String {
+        self.heading.title()
+    }
+
+    /// Change the level of this section, moving its subsections by the same amount
+    ///
+    /// Levels are clamped to the range `H1` to `H6`.
+    pub fn set_level(&mut self, level: HeadingLevel) {
+        let delta = level as isize - self.heading.level as isize;
+        self.shift_level(delta);
+    }
+
+    fn shift_level(&mut self, delta: isize) {
+        let level = (self.heading.level as isize + delta).clamp(1, 6) as usize;
+        self.heading.level = HeadingLevel::try_from(level).unwrap();
+        self.children.iter_mut().for_each(|c| c.shift_level(delta));
+    }
+
+    pub fn into_blocks(self) -> Vec<Block<'a>> {
+        let mut ret = Vec::new();
+        self.push_blocks(&mut ret);
+        ret
+    }
+
+    fn push_blocks(self, blocks: &mut Vec<Block<'a>>) {
+        blocks.push(Block::Heading(self.heading));
+        blocks.extend(self.body);
+        self.children
+            .into_iter()
+            .for_each(|c| c.push_blocks(blocks));
+    }
+}
+
+impl Heading<'_> {
+    pub fn has_class(&self, class: &str) -> bool {
+        self.classes.iter().any(|c| c.as_ref() == class)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Document, HeadingLevel, ParseOptions, RenderOptions};
+
+    use super::*;
+
+    const SECTIONS: &str = r###"
+Preamble
+
+## Early {#early}
+
+Before the first top level heading
+
+# Actions { .todo }
+
+- [ ] Something
+
+### Deep
+
+Skipped a level
+
+## Shallower
+
+Still within actions
+
+# Notes
+
+Some notes
+
+## Detail {.todo}
+
+More detail
+"###;
+
+    fn sections() -> Sections<'static> {
+        Document::parse(SECTIONS, ParseOptions::all()).into()
+    }
+
+    fn render(sections: Sections<'_>) -> String {
+        Document::from(sections)
+            .render(RenderOptions::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn structure() {
+        let sections = sections();
+        assert_eq!(sections.preamble.len(), 1);
+        let titles: Vec<_> = sections.sections.iter().map(Section::title).collect();
+        assert_eq!(titles, ["Early", "Actions", "Notes"]);
+        let actions = &sections.sections[1];
+        let titles: Vec<_> = actions.children.iter().map(Section::title).collect();
+        assert_eq!(titles, ["Deep", "Shallower"]);
+        let all: Vec<_> = sections.iter().map(Section::title).collect();
+        assert_eq!(
+            all,
+            ["Early", "Actions", "Deep", "Shallower", "Notes", "Detail"]
+        );
+    }
+
+    #[test]
+    fn lossless() {
+        let doc = Document::parse(SECTIONS, ParseOptions::all());
+        let expected = doc.render(RenderOptions::default()).unwrap();
+        assert_eq!(render(doc.into()), expected);
+    }
+
+    #[test]
+    fn finding() {
+        let sections = sections();
+        assert_eq!(sections.find_by_id("early").unwrap().title(), "Early");
+        assert_eq!(sections.find_by_class("todo").unwrap().title(), "Actions");
+        assert_eq!(
+            sections.path_of(|s| s.title() == "Detail"),
+            Some(vec![2, 0])
+        );
+        assert!(sections.find_by_title("Missing").is_none());
+    }
+
+    #[test]
+    fn editing() {
+        let mut sections = sections();
+        let removed = sections.remove(&[0]).unwrap();
+        assert_eq!(removed.title(), "Early");
+
+        // Move "Detail" out to the top level, ahead of "Actions"
+        let from = sections.path_of(|s| s.title() == "Detail").unwrap();
+        assert!(sections.move_section(&from, &[0]));
+        sections.sections[0].set_level(HeadingLevel::H1);
+        assert!(!sections.move_section(&[0], &[7, 3]));
+
+        let notes = sections.path_of(|s| s.title() == "Notes").unwrap();
+        let mut replacement = sections.get(&notes).unwrap().clone();
+        replacement.body.clear();
+        sections.replace(&notes, replacement);
+
+        assert_eq!(
+            render(sections),
+            r###"Preamble
+
+# Detail { .todo }
+
+More detail
+
+# Actions { .todo }
+
+* [ ] Something
+
+### Deep
+
+Skipped a level
+
+## Shallower
+
+Still within actions
+
+# Notes"###
+        );
+    }
+}
@@ -0,0 +1,801 @@
+//! Selecting nodes with a CSS-like syntax
+//!
+//! A [`Selector`] is a list of compound selectors separated by combinators,
+//! for example `heading.todo > list > task` or `heading#notes paragraph`.
+//! Several selectors may be given at once, separated by commas.
+//!
+//! Compound selectors are an optional node type (or `*`) followed by any
+//! number of `.class` and `#id` tests, which only apply to headings.  The
+//! node types are:
+//!
+//! | Type | Matches |
+//! | --- | --- |
+//! | `heading`, `h1` ... `h6` | Headings, optionally of a given level |
+//! | `paragraph`, `blockquote`, `codeblock`, `html`, `footnote`, `rule`, `metadata` | The corresponding blocks |
+//! | `list`, `ul`, `ol` | Lists, optionally only unordered or ordered ones |
+//! | `item`, `task` | List items, optionally only those with a task marker |
+//! | `deflist`, `table`, `row`, `cell` | Definition lists and tables |
+//! | `link`, `image`, `emphasis`, `strong`, `strikethrough`, `code`, `math`, `text` | Inline content |
+//!
+//! The combinator ` ` matches descendants and `>` matches direct children.
+//! Since a heading runs until the next heading of the same or a shallower
+//! level, the blocks following a heading are treated as its children.  Thus
+//! `heading.todo > list` selects the lists directly within a `.todo`
+//! section, but not those within its subsections.
+
+use std::{fmt, ops::ControlFlow, str::FromStr};
+
+use pulldown_cmark::HeadingLevel;
+
+use crate::{ast::*, visit::*, visit_mut::*};
+
+/// A parsed selector, see the [module documentation](self) for the syntax
+#[derive(Debug, Clone)]
+pub struct Selector {
+    alternatives: Vec<Vec<(Combinator, Compound)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Compound {
+    kind: Option<KindTest>,
+    classes: Vec<String>,
+    id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum KindTest {
+    Kind(Kind),
+    Level(HeadingLevel),
+    Task,
+    Ordered(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Heading,
+    Paragraph,
+    BlockQuote,
+    CodeBlock,
+    Html,
+    Footnote,
+    Rule,
+    Metadata,
+    List,
+    Item,
+    DefinitionList,
+    Table,
+    Row,
+    Cell,
+    Link,
+    Image,
+    Emphasis,
+    Strong,
+    Strikethrough,
+    Code,
+    Math,
+    Text,
+}
+
+/// What a selector can know about a node
+#[derive(Debug, Clone)]
+struct NodeInfo {
+    kind: Kind,
+    level: Option<HeadingLevel>,
+    id: Option<String>,
+    classes: Vec<String>,
+    task: bool,
+    ordered: bool,
+}
+
+/// An error found while parsing a [`Selector`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorError {
+    /// The byte offset into the selector at which the problem was found
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+/// A node selected from a [`Document`]
+#[derive(Debug, Clone, Copy)]
+pub enum NodeRef<'a> {
+    Block(&'a Block<'a>),
+    ListItem(&'a ListItem<'a>),
+    TableRow(&'a TableRow<'a>),
+    TableCell(&'a TableCell<'a>),
+    Inline(&'a Inline<'a>),
+}
+
+/// A node selected from a [`Document`] for editing
+#[derive(Debug)]
+pub enum NodeMut<'n, 'a> {
+    Block(&'n mut Block<'a>),
+    ListItem(&'n mut ListItem<'a>),
+    TableRow(&'n mut TableRow<'a>),
+    TableCell(&'n mut TableCell<'a>),
+    Inline(&'n mut Inline<'a>),
+}
+
+impl Selector {
+    pub fn parse(input: &str) -> Result<Self, SelectorError> {
+        let mut parser = SelectorParser { input, pos: 0 };
+        let mut alternatives = vec![parser.complex()?];
+        while parser.eat(',') {
+            alternatives.push(parser.complex()?);
+        }
+        parser.skip_ws();
+        if parser.pos != input.len() {
+            return Err(parser.error("Unexpected character"));
+        }
+        Ok(Self { alternatives })
+    }
+
+    fn matches(&self, node: &NodeInfo, ancestors: &[NodeInfo]) -> bool {
+        self.alternatives.iter().any(|parts| {
+            let last = parts.len() - 1;
+            parts[last].1.matches(node) && Self::match_ancestors(parts, last, ancestors)
+        })
+    }
+
+    /// Having matched `parts[idx]`, try to match the rest against the ancestors
+    fn match_ancestors(
+        parts: &[(Combinator, Compound)],
+        idx: usize,
+        ancestors: &[NodeInfo],
+    ) -> bool {
+        if idx == 0 {
+            return true;
+        }
+        let wanted = &parts[idx - 1].1;
+        match parts[idx].0 {
+            Combinator::Child => match ancestors.split_last() {
+                Some((parent, rest)) => {
+                    wanted.matches(parent) && Self::match_ancestors(parts, idx - 1, rest)
+                }
+                None => false,
+            },
+            Combinator::Descendant => (0..ancestors.len()).rev().any(|i| {
+                wanted.matches(&ancestors[i])
+                    && Self::match_ancestors(parts, idx - 1, &ancestors[..i])
+            }),
+        }
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Compound {
+    fn matches(&self, node: &NodeInfo) -> bool {
+        let kind_ok = match self.kind {
+            None => true,
+            Some(KindTest::Kind(kind)) => kind == node.kind,
+            Some(KindTest::Level(level)) => node.level == Some(level),
+            Some(KindTest::Task) => node.kind == Kind::Item && node.task,
+            Some(KindTest::Ordered(ordered)) => node.kind == Kind::List && node.ordered == ordered,
+        };
+        kind_ok
+            && self.classes.iter().all(|c| node.classes.contains(c))
+            && self
+                .id
+                .as_ref()
+                .is_none_or(|id| node.id.as_ref() == Some(id))
+    }
+}
+
+struct SelectorParser<'i> {
+    input: &'i str,
+    pos: usize,
+}
+
+impl SelectorParser<'_> {
+    fn error(&self, message: &str) -> SelectorError {
+        SelectorError {
+            position: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn skip_ws(&mut self) -> bool {
+        let start = self.pos;
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+        self.pos != start
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(ch) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self) -> Result<&str, SelectorError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            self.pos += self.peek().unwrap().len_utf8();
+        }
+        if start == self.pos {
+            Err(self.error("Expected a name"))
+        } else {
+            Ok(&self.input[start..self.pos])
+        }
+    }
+
+    fn complex(&mut self) -> Result<Vec<(Combinator, Compound)>, SelectorError> {
+        self.skip_ws();
+        let mut parts = vec![(Combinator::Descendant, self.compound()?)];
+        loop {
+            let had_ws = self.skip_ws();
+            let combinator = match self.peek() {
+                Some('>') => {
+                    self.pos += 1;
+                    self.skip_ws();
+                    Combinator::Child
+                }
+                Some(',') | None => break,
+                Some(_) if had_ws => Combinator::Descendant,
+                Some(_) => return Err(self.error("Unexpected character")),
+            };
+            parts.push((combinator, self.compound()?));
+        }
+        Ok(parts)
+    }
+
+    fn compound(&mut self) -> Result<Compound, SelectorError> {
+        let start = self.pos;
+        let mut ret = Compound::default();
+        if self.peek() == Some('*') {
+            self.pos += 1;
+        } else if self.peek().is_some_and(char::is_alphabetic) {
+            let name_pos = self.pos;
+            let name = self.ident()?;
+            ret.kind = Some(kind_test(name).ok_or_else(|| SelectorError {
+                position: name_pos,
+                message: format!("Unknown node type `{name}`"),
+            })?);
+        }
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.pos += 1;
+                    let class = self.ident()?.to_string();
+                    ret.classes.push(class);
+                }
+                Some('#') => {
+                    self.pos += 1;
+                    let id = self.ident()?.to_string();
+                    ret.id = Some(id);
+                }
+                _ => break,
+            }
+        }
+        if self.pos == start {
+            return Err(self.error("Expected a selector"));
+        }
+        Ok(ret)
+    }
+}
+
+fn kind_test(name: &str) -> Option<KindTest> {
+    use KindTest::Kind as K;
+    Some(match name {
+        "heading" => K(Kind::Heading),
+        "h1" => KindTest::Level(HeadingLevel::H1),
+        "h2" => KindTest::Level(HeadingLevel::H2),
+        "h3" => KindTest::Level(HeadingLevel::H3),
+        "h4" => KindTest::Level(HeadingLevel::H4),
+        "h5" => KindTest::Level(HeadingLevel::H5),
+        "h6" => KindTest::Level(HeadingLevel::H6),
+        "paragraph" => K(Kind::Paragraph),
+        "blockquote" => K(Kind::BlockQuote),
+        "codeblock" => K(Kind::CodeBlock),
+        "html" => K(Kind::Html),
+        "footnote" => K(Kind::Footnote),
+        "rule" => K(Kind::Rule),
+        "metadata" => K(Kind::Metadata),
+        "list" => K(Kind::List),
+        "ul" => KindTest::Ordered(false),
+        "ol" => KindTest::Ordered(true),
+        "item" => K(Kind::Item),
+        "task" => KindTest::Task,
+        "deflist" => K(Kind::DefinitionList),
+        "table" => K(Kind::Table),
+        "row" => K(Kind::Row),
+        "cell" => K(Kind::Cell),
+        "link" => K(Kind::Link),
+        "image" => K(Kind::Image),
+        "emphasis" => K(Kind::Emphasis),
+        "strong" => K(Kind::Strong),
+        "strikethrough" => K(Kind::Strikethrough),
+        "code" => K(Kind::Code),
+        "math" => K(Kind::Math),
+        "text" => K(Kind::Text),
+        _ => return None,
+    })
+}
+
+impl NodeInfo {
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            level: None,
+            id: None,
+            classes: Vec::new(),
+            task: false,
+            ordered: false,
+        }
+    }
+
+    fn block(block: &Block<'_>) -> Self {
+        match block {
+            Block::Metadata(_) => Self::new(Kind::Metadata),
+            Block::Heading(heading) => Self {
+                level: Some(heading.level),
+                id: heading.id.as_ref().map(|id| id.to_string()),
+                classes: heading.classes.iter().map(|c| c.to_string()).collect(),
+                ..Self::new(Kind::Heading)
+            },
+            Block::Paragraph(_) => Self::new(Kind::Paragraph),
+            Block::BlockQuote(_) => Self::new(Kind::BlockQuote),
+            Block::CodeBlock(_) => Self::new(Kind::CodeBlock),
+            Block::HtmlBlock(_) => Self::new(Kind::Html),
+            Block::FootnoteDefinition(_) => Self::new(Kind::Footnote),
+            Block::Rule => Self::new(Kind::Rule),
+            Block::List(list) => Self {
+                ordered: list.start.is_some(),
+                ..Self::new(Kind::List)
+            },
+            Block::DefinitionList(_) => Self::new(Kind::DefinitionList),
+            Block::Table(_) => Self::new(Kind::Table),
+        }
+    }
+
+    fn list_item(item: &ListItem<'_>) -> Self {
+        let first = match item {
+            ListItem::Inline(body) => body.first(),
+            ListItem::Block(blocks) => match blocks.first() {
+                Some(Block::Paragraph(p)) => p.body.first(),
+                _ => None,
+            },
+        };
+        Self {
+            task: matches!(first, Some(Inline::TasklistMarker(_))),
+            ..Self::new(Kind::Item)
+        }
+    }
+
+    fn inline(inline: &Inline<'_>) -> Option<Self> {
+        let kind = match inline {
+            Inline::Text(_) => Kind::Text,
+            Inline::Image(_) => Kind::Image,
+            Inline::Link(_) => Kind::Link,
+            Inline::Html(_) => Kind::Html,
+            Inline::InlineMath(_) | Inline::DisplayMath(_) => Kind::Math,
+            Inline::Code(_) => Kind::Code,
+            Inline::FootnoteReference(_) => Kind::Footnote,
+            Inline::Emphasis(_) => Kind::Emphasis,
+            Inline::Strong(_) => Kind::Strong,
+            Inline::Strikethrough(_) => Kind::Strikethrough,
+            // Blocks are dealt with as blocks, and breaks and markers aren't
+            // interesting enough to select
+            Inline::InlineBlock(_)
+            | Inline::SoftBreak
+            | Inline::HardBreak
+            | Inline::TasklistMarker(_) => return None,
+        };
+        Some(Self::new(kind))
+    }
+}
+
+/// The matching state shared by the borrowing and mutating walks
+struct Matcher<'s> {
+    selector: &'s Selector,
+    ancestors: Vec<NodeInfo>,
+    /// For each block list being walked, the levels of its open sections
+    scopes: Vec<Vec<HeadingLevel>>,
+}
+
+impl<'s> Matcher<'s> {
+    fn new(selector: &'s Selector) -> Self {
+        Self {
+            selector,
+            ancestors: Vec::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn exit_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        let remaining = self.ancestors.len() - scope.len();
+        self.ancestors.truncate(remaining);
+    }
+
+    /// A heading closes any sections in its scope which are at least as deep
+    fn close_sections(&mut self, level: HeadingLevel) {
+        let scope = self.scopes.last_mut().unwrap();
+        while scope.last().is_some_and(|l| *l >= level) {
+            scope.pop();
+            self.ancestors.pop();
+        }
+    }
+
+    fn matches(&self, node: &NodeInfo) -> bool {
+        self.selector.matches(node, &self.ancestors)
+    }
+
+    /// Enter a node, returning whether it matched
+    fn enter(&mut self, node: NodeInfo) -> bool {
+        let matched = self.matches(&node);
+        self.ancestors.push(node);
+        matched
+    }
+
+    fn leave(&mut self) {
+        self.ancestors.pop();
+    }
+
+    /// A heading's body is walked with the heading as an ancestor, after
+    /// which the heading remains open as the parent of its section.
+    fn open_section(&mut self, level: HeadingLevel) {
+        self.scopes.last_mut().unwrap().push(level);
+    }
+}
+
+struct Select<'s, 'ast> {
+    matcher: Matcher<'s>,
+    found: Vec<NodeRef<'ast>>,
+}
+
+impl<'ast> Visit<'ast> for Select<'_, 'ast> {
+    fn visit_document(&mut self, document: &'ast Document<'ast>) -> ControlFlow<()> {
+        self.matcher.enter_scope();
+        visit_document(self, document)?;
+        self.matcher.exit_scope();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_block(&mut self, block: &'ast Block<'ast>) -> ControlFlow<()> {
+        let info = NodeInfo::block(block);
+        if let Block::Heading(heading) = block {
+            self.matcher.close_sections(heading.level);
+        }
+        if self.matcher.enter(info) {
+            self.found.push(NodeRef::Block(block));
+        }
+        visit_block(self, block)?;
+        match block {
+            Block::Heading(heading) => self.matcher.open_section(heading.level),
+            _ => self.matcher.leave(),
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn visit_block_quote(&mut self, block_quote: &'ast BlockQuote<'ast>) -> ControlFlow<()> {
+        self.matcher.enter_scope();
+        visit_block_quote(self, block_quote)?;
+        self.matcher.exit_scope();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_footnote_definition(
+        &mut self,
+        footnote_definition: &'ast FootnoteDefinition<'ast>,
+    ) -> ControlFlow<()> {
+        self.matcher.enter_scope();
+        visit_footnote_definition(self, footnote_definition)?;
+        self.matcher.exit_scope();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_list_item(&mut self, list_item: &'ast ListItem<'ast>) -> ControlFlow<()> {
+        if self.matcher.enter(NodeInfo::list_item(list_item)) {
+            self.found.push(NodeRef::ListItem(list_item));
+        }
+        self.matcher.enter_scope();
+        visit_list_item(self, list_item)?;
+        self.matcher.exit_scope();
+        self.matcher.leave();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_table_row(&mut self, table_row: &'ast TableRow<'ast>) -> ControlFlow<()> {
+        if self.matcher.enter(NodeInfo::new(Kind::Row)) {
+            self.found.push(NodeRef::TableRow(table_row));
+        }
+        visit_table_row(self, table_row)?;
+        self.matcher.leave();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_table_cell(&mut self, table_cell: &'ast TableCell<'ast>) -> ControlFlow<()> {
+        if self.matcher.enter(NodeInfo::new(Kind::Cell)) {
+            self.found.push(NodeRef::TableCell(table_cell));
+        }
+        visit_table_cell(self, table_cell)?;
+        self.matcher.leave();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_inline(&mut self, inline: &'ast Inline<'ast>) -> ControlFlow<()> {
+        let Some(info) = NodeInfo::inline(inline) else {
+            return visit_inline(self, inline);
+        };
+        if self.matcher.enter(info) {
+            self.found.push(NodeRef::Inline(inline));
+        }
+        visit_inline(self, inline)?;
+        self.matcher.leave();
+        ControlFlow::Continue(())
+    }
+}
+
+struct SelectMut<'s, F> {
+    matcher: Matcher<'s>,
+    callback: F,
+}
+
+impl<'a, F: FnMut(NodeMut<'_, 'a>) -> ControlFlow<()>> VisitMut<'a> for SelectMut<'_, F> {
+    fn visit_document_mut(&mut self, document: &mut Document<'a>) -> ControlFlow<()> {
+        self.matcher.enter_scope();
+        visit_document_mut(self, document)?;
+        self.matcher.exit_scope();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block<'a>) -> ControlFlow<()> {
+        let info = NodeInfo::block(block);
+        if let Block::Heading(heading) = block {
+            self.matcher.close_sections(heading.level);
+        }
+        if self.matcher.enter(info) {
+            (self.callback)(NodeMut::Block(block))?;
+        }
+        visit_block_mut(self, block)?;
+        // The callback may have replaced a heading, so look again
+        match block {
+            Block::Heading(heading) => self.matcher.open_section(heading.level),
+            _ => self.matcher.leave(),
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn visit_block_quote_mut(&mut self, block_quote: &mut BlockQuote<'a>) -> ControlFlow<()> {
+        self.matcher.enter_scope();
+        visit_block_quote_mut(self, block_quote)?;
+        self.matcher.exit_scope();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_footnote_definition_mut(
+        &mut self,
+        footnote_definition: &mut FootnoteDefinition<'a>,
+    ) -> ControlFlow<()> {
+        self.matcher.enter_scope();
+        visit_footnote_definition_mut(self, footnote_definition)?;
+        self.matcher.exit_scope();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_list_item_mut(&mut self, list_item: &mut ListItem<'a>) -> ControlFlow<()> {
+        if self.matcher.enter(NodeInfo::list_item(list_item)) {
+            (self.callback)(NodeMut::ListItem(list_item))?;
+        }
+        self.matcher.enter_scope();
+        visit_list_item_mut(self, list_item)?;
+        self.matcher.exit_scope();
+        self.matcher.leave();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_table_row_mut(&mut self, table_row: &mut TableRow<'a>) -> ControlFlow<()> {
+        if self.matcher.enter(NodeInfo::new(Kind::Row)) {
+            (self.callback)(NodeMut::TableRow(table_row))?;
+        }
+        visit_table_row_mut(self, table_row)?;
+        self.matcher.leave();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_table_cell_mut(&mut self, table_cell: &mut TableCell<'a>) -> ControlFlow<()> {
+        if self.matcher.enter(NodeInfo::new(Kind::Cell)) {
+            (self.callback)(NodeMut::TableCell(table_cell))?;
+        }
+        visit_table_cell_mut(self, table_cell)?;
+        self.matcher.leave();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_inline_mut(&mut self, inline: &mut Inline<'a>) -> ControlFlow<()> {
+        let Some(info) = NodeInfo::inline(inline) else {
+            return visit_inline_mut(self, inline);
+        };
+        if self.matcher.enter(info) {
+            (self.callback)(NodeMut::Inline(inline))?;
+        }
+        visit_inline_mut(self, inline)?;
+        self.matcher.leave();
+        ControlFlow::Continue(())
+    }
+}
+
+impl<'a> Document<'a> {
+    /// Every node matching the selector, in document order
+    pub fn select(&self, selector: &Selector) -> Vec<NodeRef<'_>> {
+        let mut select = Select {
+            matcher: Matcher::new(selector),
+            found: Vec::new(),
+        };
+        let _ = select.visit_document(self);
+        select.found
+    }
+
+    /// Call `f` on every node matching the selector, in document order
+    ///
+    /// Each node is offered to `f` before its children are walked, so any
+    /// changes made are reflected in what gets matched beneath it.  Returning
+    /// [`ControlFlow::Break`] stops the walk.
+    pub fn select_mut<F>(&mut self, selector: &Selector, f: F) -> ControlFlow<()>
+    where
+        F: FnMut(NodeMut<'_, 'a>) -> ControlFlow<()>,
+    {
+        SelectMut {
+            matcher: Matcher::new(selector),
+            callback: f,
+        }
+        .visit_document_mut(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ParseOptions, RenderOptions};
+
+    use super::*;
+
+    const ENTRY: &str = r###"
+# Actions {.todo}
+
+- [ ] Top level task
+- Not a task
+  - [ ] Nested task
+
+## Waiting
+
+- [ ] Waiting task
+
+# Notes {#notes}
+
+A paragraph with a [link](https://example.com).
+
+> - [ ] Quoted task
+
+| Who | What |
+| --- | ---- |
+| me  | this |
+"###;
+
+    fn doc() -> Document<'static> {
+        Document::parse(ENTRY, ParseOptions::all())
+    }
+
+    fn texts(nodes: &[NodeRef<'_>]) -> Vec<String> {
+        fn text(inlines: &[Inline<'_>], out: &mut String) {
+            for i in inlines {
+                match i {
+                    Inline::Text(t) => out.push_str(t),
+                    Inline::Link(l) => text(&l.body, out),
+                    _ => {}
+                }
+            }
+        }
+        nodes
+            .iter()
+            .map(|n| {
+                let mut out = String::new();
+                match n {
+                    NodeRef::ListItem(ListItem::Inline(body)) => text(body, &mut out),
+                    NodeRef::Block(Block::Heading(h)) => text(&h.body, &mut out),
+                    NodeRef::Block(Block::Paragraph(p)) => text(&p.body, &mut out),
+                    NodeRef::Inline(i) => text(std::slice::from_ref(*i), &mut out),
+                    NodeRef::TableCell(c) => text(&c.body, &mut out),
+                    _ => out.push('?'),
+                }
+                out
+            })
+            .collect()
+    }
+
+    fn select(selector: &str) -> Vec<String> {
+        texts(&doc().select(&selector.parse().unwrap()))
+    }
+
+    #[test]
+    fn descendants() {
+        assert_eq!(
+            select("heading.todo task"),
+            ["Top level task", "Nested task", "Waiting task"]
+        );
+        assert_eq!(select("heading#notes link"), ["link"]);
+        assert_eq!(select("blockquote task"), ["Quoted task"]);
+        assert_eq!(select("h1"), ["Actions", "Notes"]);
+        // Whitespace needn't be ASCII
+        assert_eq!(select("heading#notes\u{a0}link"), ["link"]);
+        assert_eq!(select("heading#notes\u{3000}link"), ["link"]);
+    }
+
+    #[test]
+    fn children() {
+        assert_eq!(
+            select("heading.todo > list > item"),
+            ["Top level task", "Not a task"]
+        );
+        assert_eq!(select("h2 > list > task"), ["Waiting task"]);
+        assert_eq!(select("#notes > paragraph"), ["A paragraph with a link."]);
+    }
+
+    #[test]
+    fn alternatives() {
+        assert_eq!(select("table row cell, h2"), ["Waiting", "me", "this"]);
+    }
+
+    #[test]
+    fn errors() {
+        let err = Selector::parse("heading.todo >").unwrap_err();
+        assert_eq!(err.position, 14);
+        let err = Selector::parse("heading > wibble").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown node type `wibble` at position 10");
+        assert!(Selector::parse("heading.").is_err());
+        assert!(Selector::parse("heading..todo").is_err());
+    }
+
+    #[test]
+    fn mutation() {
+        let mut doc = doc();
+        let selector = "heading.todo > list > task".parse().unwrap();
+        let flow = doc.select_mut(&selector, |node| {
+            if let NodeMut::ListItem(ListItem::Inline(body)) = node {
+                body[0] = Inline::TasklistMarker('x');
+            }
+            ControlFlow::Continue(())
+        });
+        assert_eq!(flow, ControlFlow::Continue(()));
+        let rendered = doc.render(RenderOptions::default()).unwrap();
+        assert!(rendered.contains("* [x] Top level task"));
+        assert!(rendered.contains("  * [ ] Nested task"));
+        assert!(rendered.contains("* [ ] Waiting task"));
+    }
+}
@@ -0,0 +1,66 @@
+//! Serde support is derived on the AST types
+//!
+//! Deserialising borrows strings from the input where it can, exactly as
+//! parsing markdown does, so these tests check both round-tripping and
+//! that nothing is needlessly copied.
+//!
+//! That means `Document<'static>` isn't `DeserializeOwned`, so it can't come
+//! straight from `serde_json::from_reader` and the like.  Read the input
+//! into a `String` instead, deserialise a `Document` borrowing from that,
+//! and detach it with `into_static()`, as `owned_documents` does.
+
+use pulldown_cmark::CowStr;
+
+use crate::{Block, Document, Inline, ParseOptions};
+
+const SAMPLE: &str = r###"
+# Heading { #ident .todo key=value }
+
+- [ ] A task with `code` and a [link](https://example.com "title")
+
+> [!NOTE]
+> A note
+
+| Left | Right |
+| :--- | ----: |
+| a    | b     |
+
+```rust
+fn main() {}
+```
+"###;
+
+#[test]
+fn json_roundtrip() {
+    let doc = Document::parse(SAMPLE, ParseOptions::all());
+    let json = serde_json::to_string(&doc).unwrap();
+    let back: Document = serde_json::from_str(&json).unwrap();
+    assert_eq!(json, serde_json::to_string(&back).unwrap());
+}
+
+#[test]
+fn borrows_strings() {
+    let json = r#"{"blocks":[{"Paragraph":{"body":[{"Text":"plain text"}]}}]}"#;
+    let doc: Document<'_> = serde_json::from_str(json).unwrap();
+    let Block::Paragraph(paragraph) = &doc.blocks[0] else {
+        panic!("Expected a paragraph");
+    };
+    assert!(matches!(
+        paragraph.body[0],
+        Inline::Text(CowStr::Borrowed("plain text"))
+    ));
+}
+
+#[test]
+fn owned_documents() {
+    let doc = Document::parse(SAMPLE, ParseOptions::all());
+    let json = serde_json::to_vec(&doc).unwrap();
+    let owned: Document<'static> = {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut json.as_slice(), &mut text).unwrap();
+        serde_json::from_str::<Document>(&text)
+            .unwrap()
+            .into_static()
+    };
+    assert_eq!(owned, doc);
+}
@@ -0,0 +1,423 @@
+//! Editing tables, and moving them in and out of CSV
+//!
+//! Markdown tables are a header row and then body rows, each of which should
+//! have one cell per column.  The helpers here keep the header, the column
+//! alignments and every row the same width as columns come and go, which is
+//! easy to get wrong when poking at the fields directly.
+//!
+//! ```
+//! use pulldown_cmark_ast::{Alignment, Table, TableRow};
+//!
+//! let mut table = Table::from_csv("Task,Hours\nReview,2\n").unwrap();
+//! table.push_row(TableRow::new(["Meetings", "3"]));
+//! table.set_alignment(1, Alignment::Right);
+//! assert_eq!(table.column("hours"), Some(1));
+//! assert_eq!(table.to_csv(), "Task,Hours\nReview,2\nMeetings,3\n");
+//! ```
+
+use std::fmt;
+
+use crate::{ast::*, builder::IntoInlines};
+
+/// A column taken out of a table, or to be put into one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column<'a> {
+    pub alignment: Alignment,
+    pub header: TableCell<'a>,
+    /// The column's cell in each row of the table
+    pub cells: Vec<TableCell<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvError {
+    /// There wasn't even a header row
+    Empty,
+    /// A quoted field ran to the end of the input
+    UnterminatedQuote { line: usize },
+    /// A record had more fields than the header
+    TooManyFields {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Empty => write!(f, "No header row in CSV"),
+            CsvError::UnterminatedQuote { line } => {
+                write!(f, "Quoted field starting on line {line} is never closed")
+            }
+            CsvError::TooManyFields {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Record on line {line} has {found} fields, but the header only has {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl<'a> TableCell<'a> {
+    pub fn new(body: impl IntoInlines<'a>) -> Self {
+        Self {
+            body: body.into_inlines(),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self { body: Vec::new() }
+    }
+}
+
+impl<'a> TableRow<'a> {
+    pub fn new<T: IntoInlines<'a>>(cells: impl IntoIterator<Item = T>) -> Self {
+        Self {
+            cells: cells.into_iter().map(TableCell::new).collect(),
+        }
+    }
+
+    pub fn get(&self, column: usize) -> Option<&TableCell<'a>> {
+        self.cells.get(column)
+    }
+
+    pub fn get_mut(&mut self, column: usize) -> Option<&mut TableCell<'a>> {
+        self.cells.get_mut(column)
+    }
+
+    /// Pad with empty cells, or drop cells, to be `columns` wide
+    fn fit(&mut self, columns: usize) {
+        self.cells.resize_with(columns, TableCell::empty);
+    }
+}
+
+impl<'a> Column<'a> {
+    /// A column with no cells, which will be filled with empty ones when inserted
+    pub fn new(header: impl IntoInlines<'a>, alignment: Alignment) -> Self {
+        Self {
+            alignment,
+            header: TableCell::new(header),
+            cells: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Table<'a> {
+    pub fn columns(&self) -> usize {
+        self.header.cells.len()
+    }
+
+    /// The index of the column whose header has this text
+    ///
+    /// Formatting is ignored, as is ASCII case.
+    pub fn column(&self, header: &str) -> Option<usize> {
+        self.header
+            .cells
+            .iter()
+            .position(|cell| cell.text().eq_ignore_ascii_case(header.trim()))
+    }
+
+    pub fn cell(&self, row: usize, column: usize) -> Option<&TableCell<'a>> {
+        self.rows.get(row)?.get(column)
+    }
+
+    pub fn cell_mut(&mut self, row: usize, column: usize) -> Option<&mut TableCell<'a>> {
+        self.rows.get_mut(row)?.get_mut(column)
+    }
+
+    /// Every cell in a column, in row order
+    pub fn column_cells(&self, column: usize) -> impl Iterator<Item = &TableCell<'a>> {
+        self.rows.iter().filter_map(move |row| row.get(column))
+    }
+
+    /// Add a row at the end, padding or truncating it to fit
+    pub fn push_row(&mut self, row: TableRow<'a>) {
+        self.insert_row(self.rows.len(), row);
+    }
+
+    /// Add a row before the row at `index`, padding or truncating it to fit
+    ///
+    /// # Panics
+    ///
+    /// If `index` is greater than the number of rows
+    pub fn insert_row(&mut self, index: usize, mut row: TableRow<'a>) {
+        row.fit(self.columns());
+        self.rows.insert(index, row);
+    }
+
+    pub fn remove_row(&mut self, index: usize) -> Option<TableRow<'a>> {
+        (index < self.rows.len()).then(|| self.rows.remove(index))
+    }
+
+    /// Add a column at the right hand side
+    pub fn push_column(&mut self, column: Column<'a>) {
+        self.insert_column(self.columns(), column);
+    }
+
+    /// Add a column before the column at `index`
+    ///
+    /// The column's cells are padded or truncated to the number of rows.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is greater than the number of columns
+    pub fn insert_column(&mut self, index: usize, column: Column<'a>) {
+        let Column {
+            alignment,
+            header,
+            cells,
+        } = column;
+        let columns = self.columns();
+        self.alignments.resize(columns, Alignment::None);
+        self.alignments.insert(index, alignment);
+        self.header.cells.insert(index, header);
+        let mut cells = cells.into_iter();
+        for row in &mut self.rows {
+            row.fit(columns);
+            row.cells
+                .insert(index, cells.next().unwrap_or_else(TableCell::empty));
+        }
+    }
+
+    pub fn remove_column(&mut self, index: usize) -> Option<Column<'a>> {
+        if index >= self.columns() {
+            return None;
+        }
+        let alignment = if index < self.alignments.len() {
+            self.alignments.remove(index)
+        } else {
+            Alignment::None
+        };
+        let header = self.header.cells.remove(index);
+        let cells = self
+            .rows
+            .iter_mut()
+            .map(|row| {
+                if index < row.cells.len() {
+                    row.cells.remove(index)
+                } else {
+                    TableCell::empty()
+                }
+            })
+            .collect();
+        Some(Column {
+            alignment,
+            header,
+            cells,
+        })
+    }
+
+    /// # Panics
+    ///
+    /// If `column` is not a column of the table
+    pub fn set_alignment(&mut self, column: usize, alignment: Alignment) {
+        assert!(column < self.columns(), "No column {column} in table");
+        self.alignments.resize(self.columns(), Alignment::None);
+        self.alignments[column] = alignment;
+    }
+
+    /// The text of the table as CSV, header first
+    ///
+    /// Formatting within cells is dropped, in the same way as for
+    /// [`Document::to_plain_text`].
+    pub fn to_csv(&self) -> String {
+        let mut ret = String::new();
+        for row in std::iter::once(&self.header.cells).chain(self.rows.iter().map(|r| &r.cells)) {
+            let fields = row
+                .iter()
+                .map(|cell| csv_field(&cell.text()))
+                .collect::<Vec<_>>();
+            ret.push_str(&fields.join(","));
+            ret.push('\n');
+        }
+        ret
+    }
+}
+
+impl Table<'static> {
+    /// A table from CSV, whose first record is the header
+    ///
+    /// Fields are taken as plain text rather than being parsed as markdown.
+    /// Line breaks within fields become spaces, since table cells can't
+    /// hold them, and records shorter than the header are padded.
+    pub fn from_csv(csv: &str) -> Result<Self, CsvError> {
+        let mut records = parse_csv(csv)?.into_iter();
+        let (_, header) = records.next().ok_or(CsvError::Empty)?;
+        let cells = |fields: Vec<String>| -> Vec<TableCell<'static>> {
+            fields
+                .into_iter()
+                .map(|field| {
+                    let field = field.replace("\r\n", " ").replace(['\r', '\n'], " ");
+                    if field.is_empty() {
+                        TableCell::empty()
+                    } else {
+                        TableCell::new(CowStr::from(field))
+                    }
+                })
+                .collect()
+        };
+        let columns = header.len();
+        let mut table = Table {
+            alignments: vec![Alignment::None; columns],
+            header: TableHead {
+                cells: cells(header),
+            },
+            rows: Vec::new(),
+        };
+        for (line, fields) in records {
+            if fields.len() > columns {
+                return Err(CsvError::TooManyFields {
+                    line,
+                    expected: columns,
+                    found: fields.len(),
+                });
+            }
+            table.push_row(TableRow {
+                cells: cells(fields),
+            });
+        }
+        Ok(table)
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Split CSV into records of fields, each with the line it started on
+///
+/// Blank lines are skipped, as is a missing newline at the end.
+fn parse_csv(csv: &str) -> Result<Vec<(usize, Vec<String>)>, CsvError> {
+    let mut records = Vec::new();
+    let mut chars = csv.chars().peekable();
+    let mut line = 1;
+    while chars.peek().is_some() {
+        let start = line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        loop {
+            match (chars.next(), quoted) {
+                (None, true) => return Err(CsvError::UnterminatedQuote { line: start }),
+                (Some('"'), true) if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                (Some('"'), true) => quoted = false,
+                (Some('"'), false) if field.is_empty() => quoted = true,
+                (Some(c), true) => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+                (Some(','), false) => fields.push(std::mem::take(&mut field)),
+                (Some('\r'), false) if chars.peek() == Some(&'\n') => {}
+                (None | Some('\n'), false) => {
+                    line += 1;
+                    fields.push(field);
+                    break;
+                }
+                (Some(c), false) => field.push(c),
+            }
+        }
+        if fields.iter().any(|f| !f.is_empty()) || fields.len() > 1 {
+            records.push((start, fields));
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod test {
+    use insta::assert_snapshot;
+
+    use crate::{builder::*, ParseOptions};
+
+    use super::*;
+
+    const TIMES: &str = r###"
+| Task | Hours | Notes |
+|:-----|------:|-------|
+| Review | 2 | *urgent*, apparently |
+| Meetings | 3 | |
+"###;
+
+    fn table(doc: &Document<'_>) -> Table<'static> {
+        let Block::Table(table) = &doc.blocks[0] else {
+            panic!("Expected a table, got {:?}", doc.blocks[0]);
+        };
+        table.clone().into_static()
+    }
+
+    #[test]
+    fn columns() {
+        let doc = Document::parse(TIMES, ParseOptions::all());
+        let mut table = table(&doc);
+        assert_eq!(table.column(" notes "), Some(2));
+        assert_eq!(table.column("Minutes"), None);
+
+        let notes = table.remove_column(2).unwrap();
+        assert_eq!(notes.header.text(), "Notes");
+        assert_eq!(notes.cells.len(), 2);
+        table.insert_column(0, Column::new("When", Alignment::Center));
+        table.push_column(notes);
+        table.set_alignment(3, Alignment::Right);
+        table.push_row(TableRow::new(["Tomorrow", "Planning"]));
+        table.insert_row(0, TableRow::new(["Today", "Email", "1", "", "ignored"]));
+        assert_eq!(table.remove_row(4), None);
+        assert_eq!(table.cell(1, 2).map(TableCell::text).as_deref(), Some("2"));
+
+        let doc = Document::builder().table(table).build();
+        assert_snapshot!(doc.render(Default::default()).unwrap(), @r###"
+        |When|Task|Hours|Notes|
+        |:--:|:---|----:|----:|
+        |Today|Email|1||
+        ||Review|2|*urgent*, apparently|
+        ||Meetings|3||
+        |Tomorrow|Planning|||
+        "###);
+    }
+
+    #[test]
+    fn csv() {
+        let doc = Document::parse(TIMES, ParseOptions::all());
+        let csv = table(&doc).to_csv();
+        assert_eq!(
+            csv,
+            "Task,Hours,Notes\nReview,2,\"urgent, apparently\"\nMeetings,3,\n"
+        );
+        let table = Table::from_csv(&csv).unwrap();
+        assert_eq!(table.to_csv(), csv);
+
+        let table =
+            Table::from_csv("A,\"B \"\"quoted\"\"\"\r\n1,\"two\nlines\"\r\n\r\n3\r\n").unwrap();
+        let mut expected = TableBuilder::new(["A", "B \"quoted\""])
+            .row(["1", "two lines"])
+            .build();
+        expected.push_row(TableRow::new(["3"]));
+        assert_eq!(table, expected);
+    }
+
+    #[test]
+    fn csv_errors() {
+        assert_eq!(Table::from_csv(""), Err(CsvError::Empty));
+        assert_eq!(
+            Table::from_csv("a,b\n\"1,2\n"),
+            Err(CsvError::UnterminatedQuote { line: 2 })
+        );
+        assert_eq!(
+            Table::from_csv("a,b\n1,2\n1,2,3").unwrap_err().to_string(),
+            "Record on line 3 has 3 fields, but the header only has 2"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Plain text and outlines of documents
+//!
+//! [`Document::to_plain_text`] drops all formatting, keeping only what a
+//! reader would see: each block is separated by a blank line, list items
+//! and table rows get a line each, and nested lists are indented.  Links
+//! and images are reduced to their text, while HTML, footnote references
+//! and task markers are dropped entirely.
+//!
+//! [`Document::outline`] gives just the heading tree of a document.
+
+use pulldown_cmark::HeadingLevel;
+
+use crate::ast::*;
+
+/// A heading, along with the headings nested beneath it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineEntry<'d> {
+    pub level: HeadingLevel,
+    pub id: Option<&'d str>,
+    pub classes: Vec<&'d str>,
+    pub title: String,
+    pub children: Vec<OutlineEntry<'d>>,
+}
+
+impl Document<'_> {
+    /// The text of the document, without any formatting
+    pub fn to_plain_text(&self) -> String {
+        let mut ret = blocks(&self.blocks, "\n\n");
+        if !ret.is_empty() {
+            ret.push('\n');
+        }
+        ret
+    }
+
+    /// The headings of the document, nested by level
+    ///
+    /// Only top level headings are included, not those in block quotes or
+    /// list items.
+    pub fn outline(&self) -> Vec<OutlineEntry<'_>> {
+        let mut ret = Vec::new();
+        // The chain of entries currently open, outermost first
+        let mut open: Vec<OutlineEntry<'_>> = Vec::new();
+
+        fn close<'d>(ret: &mut Vec<OutlineEntry<'d>>, open: &mut Vec<OutlineEntry<'d>>) {
+            let entry = open.pop().unwrap();
+            match open.last_mut() {
+                Some(parent) => parent.children.push(entry),
+                None => ret.push(entry),
+            }
+        }
+
+        for block in &self.blocks {
+            let Block::Heading(heading) = block else {
+                continue;
+            };
+            while open.last().is_some_and(|e| e.level >= heading.level) {
+                close(&mut ret, &mut open);
+            }
+            open.push(OutlineEntry {
+                level: heading.level,
+                id: heading.id.as_deref(),
+                classes: heading.classes.iter().map(|c| &**c).collect(),
+                title: heading.title(),
+                children: Vec::new(),
+            });
+        }
+        while !open.is_empty() {
+            close(&mut ret, &mut open);
+        }
+
+        ret
+    }
+}
+
+impl Heading<'_> {
+    /// The text of the heading, without any formatting
+    pub fn title(&self) -> String {
+        let mut ret = String::new();
+        inlines(&self.body, &mut ret);
+        ret.trim().to_string()
+    }
+}
+
+impl TableCell<'_> {
+    /// The text of the cell, without any formatting
+    pub fn text(&self) -> String {
+        let mut ret = String::new();
+        inlines(&self.body, &mut ret);
+        ret.trim().to_string()
+    }
+}
+
+fn blocks(blocks: &[Block<'_>], separator: &str) -> String {
+    blocks
+        .iter()
+        .map(block)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn block(node: &Block<'_>) -> String {
+    match node {
+        Block::Metadata(_) | Block::HtmlBlock(_) | Block::Rule => String::new(),
+        Block::Heading(Heading { body, .. })
+        | Block::Paragraph(Paragraph { body })
+        | Block::CodeBlock(CodeBlock { body, .. }) => {
+            let mut ret = String::new();
+            inlines(body, &mut ret);
+            ret.trim_end().to_string()
+        }
+        Block::BlockQuote(BlockQuote { body, .. })
+        | Block::FootnoteDefinition(FootnoteDefinition { body, .. }) => blocks(body, "\n\n"),
+        Block::List(list) => list
+            .items
+            .iter()
+            .map(|item| match item {
+                ListItem::Inline(body) => {
+                    let mut ret = String::new();
+                    inlines(body, &mut ret);
+                    ret.trim().to_string()
+                }
+                ListItem::Block(body) => body
+                    .iter()
+                    .map(item_block)
+                    .filter(|text| !text.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Block::DefinitionList(list) => {
+            let mut lines = Vec::new();
+            for item in &list.items {
+                let mut title = String::new();
+                inlines(&item.title, &mut title);
+                lines.push(title.trim().to_string());
+                for definition in &item.definitions {
+                    let mut text = String::new();
+                    inlines(&definition.body, &mut text);
+                    lines.push(indent(text.trim()));
+                }
+            }
+            lines.join("\n")
+        }
+        Block::Table(table) => std::iter::once(&table.header.cells)
+            .chain(table.rows.iter().map(|row| &row.cells))
+            .map(|cells| {
+                cells
+                    .iter()
+                    .map(TableCell::text)
+                    .collect::<Vec<_>>()
+                    .join("\t")
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// A block within a list item, where nested lists are indented
+fn item_block(node: &Block<'_>) -> String {
+    match node {
+        Block::List(_) => indent(&block(node)),
+        _ => block(node),
+    }
+}
+
+fn inlines(inlines: &[Inline<'_>], out: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(s) | Inline::Code(s) | Inline::InlineMath(s) | Inline::DisplayMath(s) => {
+                out.push_str(s)
+            }
+            Inline::SoftBreak => out.push(' '),
+            Inline::HardBreak => out.push('\n'),
+            Inline::Link(Link { body, .. })
+            | Inline::Image(Image { body, .. })
+            | Inline::Emphasis(body)
+            | Inline::Strong(body)
+            | Inline::Strikethrough(body) => self::inlines(body, out),
+            // Blocks inside tight list items, which need a line of their own
+            Inline::InlineBlock(b) => {
+                let text = item_block(b);
+                if !text.is_empty() {
+                    if !out.is_empty() && !out.ends_with('\n') {
+                        out.push('\n');
+                    }
+                    out.push_str(&text);
+                    out.push('\n');
+                }
+            }
+            Inline::Html(_) | Inline::FootnoteReference(_) | Inline::TasklistMarker(_) => {}
+        }
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("  {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use insta::assert_snapshot;
+
+    use crate::ParseOptions;
+
+    use super::*;
+
+    const ENTRY: &str = r###"
++++
+title = "Ignored"
++++
+
+# Actions { #actions .todo }
+
+- [ ] Read [the *docs*](https://example.com)
+- [x] Write `code`
+  - Nested
+  - Items
+
+## Waiting
+
+> Quoted **text**
+> on two lines
+
+# Notes { .drop .private }
+
+| Who | What |
+| --- | ---- |
+| me  | this |
+
+```rust
+fn main() {}
+```
+
+<div>Dropped</div>
+
+Term
+: Definition
+"###;
+
+    #[test]
+    fn plain_text() {
+        let doc = Document::parse(ENTRY, ParseOptions::all());
+        assert_snapshot!(doc.to_plain_text(), @r###"
+        Actions
+
+        Read the docs
+        Write code
+          Nested
+          Items
+
+        Waiting
+
+        Quoted text on two lines
+
+        Notes
+
+        Who	What
+        me	this
+
+        fn main() {}
+
+        Term
+          Definition
+        "###);
+    }
+
+    #[test]
+    fn outline() {
+        let doc = Document::parse(ENTRY, ParseOptions::all());
+        let outline = doc.outline();
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].title, "Actions");
+        assert_eq!(outline[0].id, Some("actions"));
+        assert_eq!(outline[0].classes, ["todo"]);
+        assert_eq!(outline[0].children.len(), 1);
+        assert_eq!(outline[0].children[0].level, HeadingLevel::H2);
+        assert_eq!(outline[0].children[0].title, "Waiting");
+        assert_eq!(outline[1].title, "Notes");
+        assert_eq!(outline[1].classes, ["drop", "private"]);
+        assert!(outline[1].children.is_empty());
+    }
+}
@@ -0,0 +1,770 @@
+//! Fallible folding for the AST, with context
+//!
+//! [`TryMarkdownFold`] mirrors [`MarkdownFold`](crate::fold::MarkdownFold),
+//! except that every method can fail, stopping the fold at the first error.
+//! Each method is also given a [`FoldContext`] describing where in the
+//! document the node is, so that a folder need not track which section it
+//! is in for itself.
+//!
+//! As with `MarkdownFold`, each method has a free function of the same name
+//! which does the default work, for overriding methods to call in turn.
+//! The context is kept up to date by those functions, so an override which
+//! doesn't call its default should not expect the context to change within
+//! the node it replaces.
+
+use pulldown_cmark::{CowStr, HeadingLevel};
+
+use crate::ast::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    List,
+    BlockQuote,
+    Footnote,
+}
+
+/// Where a node being folded sits within its document
+#[derive(Debug, Clone, Default)]
+pub struct FoldContext {
+    headings: Vec<(HeadingLevel, String)>,
+    containers: Vec<Container>,
+}
+
+impl FoldContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The titles of the headings which the node sits beneath, outermost first
+    ///
+    /// Headings within block quotes, list items and footnotes only apply
+    /// until the end of their container.
+    pub fn heading_path(&self) -> impl Iterator<Item = &str> {
+        self.headings.iter().map(|(_, title)| title.as_str())
+    }
+
+    /// The level of the closest heading above the node, if there is one
+    pub fn heading_level(&self) -> Option<HeadingLevel> {
+        self.headings.last().map(|(level, _)| *level)
+    }
+
+    /// How many lists, block quotes and footnotes enclose the node
+    pub fn depth(&self) -> usize {
+        self.containers.len()
+    }
+
+    pub fn in_list(&self) -> bool {
+        self.containers.contains(&Container::List)
+    }
+
+    pub fn in_block_quote(&self) -> bool {
+        self.containers.contains(&Container::BlockQuote)
+    }
+
+    pub fn in_footnote(&self) -> bool {
+        self.containers.contains(&Container::Footnote)
+    }
+
+    fn within<T>(&mut self, container: Container, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.containers.push(container);
+        let ret = f(self);
+        self.containers.pop();
+        ret
+    }
+}
+
+pub trait TryMarkdownFold<'a> {
+    type Error;
+
+    fn try_fold_document(
+        &mut self,
+        cx: &mut FoldContext,
+        document: Document<'a>,
+    ) -> Result<Document<'a>, Self::Error> {
+        try_fold_document(self, cx, document)
+    }
+
+    fn try_fold_block(
+        &mut self,
+        cx: &mut FoldContext,
+        block: Block<'a>,
+    ) -> Result<Block<'a>, Self::Error> {
+        try_fold_block(self, cx, block)
+    }
+
+    fn try_fold_inline(
+        &mut self,
+        cx: &mut FoldContext,
+        inline: Inline<'a>,
+    ) -> Result<Inline<'a>, Self::Error> {
+        try_fold_inline(self, cx, inline)
+    }
+
+    /// Fold a run of inlines, such as the body of a paragraph or of a link
+    fn try_fold_inlines(
+        &mut self,
+        cx: &mut FoldContext,
+        inlines: Vec<Inline<'a>>,
+    ) -> Result<Vec<Inline<'a>>, Self::Error> {
+        try_fold_inlines(self, cx, inlines)
+    }
+
+    fn try_fold_rule(&mut self, _cx: &mut FoldContext) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn try_fold_soft_break(&mut self, _cx: &mut FoldContext) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn try_fold_hard_break(&mut self, _cx: &mut FoldContext) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn try_fold_emphasis(
+        &mut self,
+        cx: &mut FoldContext,
+        inlines: Vec<Inline<'a>>,
+    ) -> Result<Vec<Inline<'a>>, Self::Error> {
+        self.try_fold_inlines(cx, inlines)
+    }
+
+    fn try_fold_strong(
+        &mut self,
+        cx: &mut FoldContext,
+        inlines: Vec<Inline<'a>>,
+    ) -> Result<Vec<Inline<'a>>, Self::Error> {
+        self.try_fold_inlines(cx, inlines)
+    }
+
+    fn try_fold_strikethrough(
+        &mut self,
+        cx: &mut FoldContext,
+        inlines: Vec<Inline<'a>>,
+    ) -> Result<Vec<Inline<'a>>, Self::Error> {
+        self.try_fold_inlines(cx, inlines)
+    }
+
+    fn try_fold_text(
+        &mut self,
+        _cx: &mut FoldContext,
+        text: CowStr<'a>,
+    ) -> Result<CowStr<'a>, Self::Error> {
+        Ok(text)
+    }
+
+    fn try_fold_html(
+        &mut self,
+        _cx: &mut FoldContext,
+        html: CowStr<'a>,
+    ) -> Result<CowStr<'a>, Self::Error> {
+        Ok(html)
+    }
+
+    fn try_fold_inline_math(
+        &mut self,
+        _cx: &mut FoldContext,
+        inline_math: CowStr<'a>,
+    ) -> Result<CowStr<'a>, Self::Error> {
+        Ok(inline_math)
+    }
+
+    fn try_fold_display_math(
+        &mut self,
+        _cx: &mut FoldContext,
+        display_math: CowStr<'a>,
+    ) -> Result<CowStr<'a>, Self::Error> {
+        Ok(display_math)
+    }
+
+    fn try_fold_code(
+        &mut self,
+        _cx: &mut FoldContext,
+        code: CowStr<'a>,
+    ) -> Result<CowStr<'a>, Self::Error> {
+        Ok(code)
+    }
+
+    fn try_fold_tasklist_marker(
+        &mut self,
+        _cx: &mut FoldContext,
+        marker: char,
+    ) -> Result<char, Self::Error> {
+        Ok(marker)
+    }
+
+    fn try_fold_footnote_reference(
+        &mut self,
+        _cx: &mut FoldContext,
+        footnote_reference: CowStr<'a>,
+    ) -> Result<CowStr<'a>, Self::Error> {
+        Ok(footnote_reference)
+    }
+
+    fn try_fold_metadata_block(
+        &mut self,
+        _cx: &mut FoldContext,
+        metadata_block: MetadataBlock<'a>,
+    ) -> Result<MetadataBlock<'a>, Self::Error> {
+        Ok(metadata_block)
+    }
+
+    fn try_fold_heading(
+        &mut self,
+        cx: &mut FoldContext,
+        heading: Heading<'a>,
+    ) -> Result<Heading<'a>, Self::Error> {
+        try_fold_heading(self, cx, heading)
+    }
+
+    fn try_fold_paragraph(
+        &mut self,
+        cx: &mut FoldContext,
+        paragraph: Paragraph<'a>,
+    ) -> Result<Paragraph<'a>, Self::Error> {
+        try_fold_paragraph(self, cx, paragraph)
+    }
+
+    fn try_fold_block_quote(
+        &mut self,
+        cx: &mut FoldContext,
+        block_quote: BlockQuote<'a>,
+    ) -> Result<BlockQuote<'a>, Self::Error> {
+        try_fold_block_quote(self, cx, block_quote)
+    }
+
+    fn try_fold_code_block(
+        &mut self,
+        cx: &mut FoldContext,
+        code_block: CodeBlock<'a>,
+    ) -> Result<CodeBlock<'a>, Self::Error> {
+        try_fold_code_block(self, cx, code_block)
+    }
+
+    fn try_fold_html_block(
+        &mut self,
+        _cx: &mut FoldContext,
+        html_block: HtmlBlock<'a>,
+    ) -> Result<HtmlBlock<'a>, Self::Error> {
+        Ok(html_block)
+    }
+
+    fn try_fold_footnote_definition(
+        &mut self,
+        cx: &mut FoldContext,
+        footnote_definition: FootnoteDefinition<'a>,
+    ) -> Result<FootnoteDefinition<'a>, Self::Error> {
+        try_fold_footnote_definition(self, cx, footnote_definition)
+    }
+
+    fn try_fold_list(
+        &mut self,
+        cx: &mut FoldContext,
+        list: List<'a>,
+    ) -> Result<List<'a>, Self::Error> {
+        try_fold_list(self, cx, list)
+    }
+
+    fn try_fold_list_item(
+        &mut self,
+        cx: &mut FoldContext,
+        list_item: ListItem<'a>,
+    ) -> Result<ListItem<'a>, Self::Error> {
+        try_fold_list_item(self, cx, list_item)
+    }
+
+    fn try_fold_definition_list(
+        &mut self,
+        cx: &mut FoldContext,
+        definition_list: DefinitionList<'a>,
+    ) -> Result<DefinitionList<'a>, Self::Error> {
+        try_fold_definition_list(self, cx, definition_list)
+    }
+
+    fn try_fold_definition_item(
+        &mut self,
+        cx: &mut FoldContext,
+        definition_item: DefinitionItem<'a>,
+    ) -> Result<DefinitionItem<'a>, Self::Error> {
+        try_fold_definition_item(self, cx, definition_item)
+    }
+
+    fn try_fold_definition_definition(
+        &mut self,
+        cx: &mut FoldContext,
+        definition_definition: DefinitionDefinition<'a>,
+    ) -> Result<DefinitionDefinition<'a>, Self::Error> {
+        try_fold_definition_definition(self, cx, definition_definition)
+    }
+
+    fn try_fold_table(
+        &mut self,
+        cx: &mut FoldContext,
+        table: Table<'a>,
+    ) -> Result<Table<'a>, Self::Error> {
+        try_fold_table(self, cx, table)
+    }
+
+    fn try_fold_table_head(
+        &mut self,
+        cx: &mut FoldContext,
+        table_head: TableHead<'a>,
+    ) -> Result<TableHead<'a>, Self::Error> {
+        try_fold_table_head(self, cx, table_head)
+    }
+
+    fn try_fold_table_row(
+        &mut self,
+        cx: &mut FoldContext,
+        table_row: TableRow<'a>,
+    ) -> Result<TableRow<'a>, Self::Error> {
+        try_fold_table_row(self, cx, table_row)
+    }
+
+    fn try_fold_table_cell(
+        &mut self,
+        cx: &mut FoldContext,
+        table_cell: TableCell<'a>,
+    ) -> Result<TableCell<'a>, Self::Error> {
+        try_fold_table_cell(self, cx, table_cell)
+    }
+
+    fn try_fold_image(
+        &mut self,
+        cx: &mut FoldContext,
+        image: Image<'a>,
+    ) -> Result<Image<'a>, Self::Error> {
+        try_fold_image(self, cx, image)
+    }
+
+    fn try_fold_link(
+        &mut self,
+        cx: &mut FoldContext,
+        link: Link<'a>,
+    ) -> Result<Link<'a>, Self::Error> {
+        try_fold_link(self, cx, link)
+    }
+}
+
+pub fn try_fold_inlines<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    inlines: Vec<Inline<'a>>,
+) -> Result<Vec<Inline<'a>>, F::Error> {
+    inlines
+        .into_iter()
+        .map(|i| folder.try_fold_inline(cx, i))
+        .collect()
+}
+
+/// Fold a run of blocks, keeping track of the headings within it
+pub fn try_fold_blocks<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    blocks: Vec<Block<'a>>,
+) -> Result<Vec<Block<'a>>, F::Error> {
+    let outer = cx.headings.len();
+    let ret = blocks
+        .into_iter()
+        .map(|block| {
+            if let Block::Heading(heading) = &block {
+                // A heading ends any sections at its level or deeper
+                while cx.headings.len() > outer
+                    && cx.heading_level().is_some_and(|l| l >= heading.level)
+                {
+                    cx.headings.pop();
+                }
+            }
+            let block = folder.try_fold_block(cx, block)?;
+            if let Block::Heading(heading) = &block {
+                cx.headings.push((heading.level, heading.title()));
+            }
+            Ok(block)
+        })
+        .collect();
+    cx.headings.truncate(outer);
+    ret
+}
+
+pub fn try_fold_document<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    document: Document<'a>,
+) -> Result<Document<'a>, F::Error> {
+    Ok(Document {
+        blocks: try_fold_blocks(folder, cx, document.blocks)?,
+    })
+}
+
+pub fn try_fold_block<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    block: Block<'a>,
+) -> Result<Block<'a>, F::Error> {
+    Ok(match block {
+        Block::Metadata(metadata_block) => {
+            Block::Metadata(folder.try_fold_metadata_block(cx, metadata_block)?)
+        }
+        Block::Heading(heading) => Block::Heading(folder.try_fold_heading(cx, heading)?),
+        Block::Paragraph(paragraph) => Block::Paragraph(folder.try_fold_paragraph(cx, paragraph)?),
+        Block::BlockQuote(block_quote) => {
+            Block::BlockQuote(folder.try_fold_block_quote(cx, block_quote)?)
+        }
+        Block::CodeBlock(code_block) => {
+            Block::CodeBlock(folder.try_fold_code_block(cx, code_block)?)
+        }
+        Block::HtmlBlock(html_block) => {
+            Block::HtmlBlock(folder.try_fold_html_block(cx, html_block)?)
+        }
+        Block::FootnoteDefinition(footnote_definition) => {
+            Block::FootnoteDefinition(folder.try_fold_footnote_definition(cx, footnote_definition)?)
+        }
+        Block::List(list) => Block::List(folder.try_fold_list(cx, list)?),
+        Block::DefinitionList(definition_list) => {
+            Block::DefinitionList(folder.try_fold_definition_list(cx, definition_list)?)
+        }
+        Block::Table(table) => Block::Table(folder.try_fold_table(cx, table)?),
+        Block::Rule => {
+            folder.try_fold_rule(cx)?;
+            Block::Rule
+        }
+    })
+}
+
+pub fn try_fold_inline<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    inline: Inline<'a>,
+) -> Result<Inline<'a>, F::Error> {
+    Ok(match inline {
+        Inline::SoftBreak => {
+            folder.try_fold_soft_break(cx)?;
+            Inline::SoftBreak
+        }
+        Inline::HardBreak => {
+            folder.try_fold_hard_break(cx)?;
+            Inline::HardBreak
+        }
+        Inline::TasklistMarker(marker) => {
+            Inline::TasklistMarker(folder.try_fold_tasklist_marker(cx, marker)?)
+        }
+        Inline::Image(image) => Inline::Image(folder.try_fold_image(cx, image)?),
+        Inline::Link(link) => Inline::Link(folder.try_fold_link(cx, link)?),
+
+        Inline::Text(text) => Inline::Text(folder.try_fold_text(cx, text)?),
+        Inline::Html(html) => Inline::Html(folder.try_fold_html(cx, html)?),
+        Inline::InlineMath(inline_math) => {
+            Inline::InlineMath(folder.try_fold_inline_math(cx, inline_math)?)
+        }
+        Inline::DisplayMath(display_math) => {
+            Inline::DisplayMath(folder.try_fold_display_math(cx, display_math)?)
+        }
+        Inline::Code(code) => Inline::Code(folder.try_fold_code(cx, code)?),
+        Inline::FootnoteReference(footnote_reference) => {
+            Inline::FootnoteReference(folder.try_fold_footnote_reference(cx, footnote_reference)?)
+        }
+
+        Inline::Emphasis(inlines) => Inline::Emphasis(folder.try_fold_emphasis(cx, inlines)?),
+        Inline::Strong(inlines) => Inline::Strong(folder.try_fold_strong(cx, inlines)?),
+        Inline::Strikethrough(inlines) => {
+            Inline::Strikethrough(folder.try_fold_strikethrough(cx, inlines)?)
+        }
+        Inline::InlineBlock(b) => Inline::InlineBlock(folder.try_fold_block(cx, b)?),
+    })
+}
+
+pub fn try_fold_heading<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    heading: Heading<'a>,
+) -> Result<Heading<'a>, F::Error> {
+    Ok(Heading {
+        level: heading.level,
+        id: heading.id,
+        classes: heading.classes,
+        attrs: heading.attrs,
+        body: folder.try_fold_inlines(cx, heading.body)?,
+    })
+}
+
+pub fn try_fold_paragraph<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    paragraph: Paragraph<'a>,
+) -> Result<Paragraph<'a>, F::Error> {
+    Ok(Paragraph {
+        body: folder.try_fold_inlines(cx, paragraph.body)?,
+    })
+}
+
+pub fn try_fold_link<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    link: Link<'a>,
+) -> Result<Link<'a>, F::Error> {
+    Ok(Link {
+        link_type: link.link_type,
+        dest_url: link.dest_url,
+        title: link.title,
+        id: link.id,
+        body: folder.try_fold_inlines(cx, link.body)?,
+    })
+}
+
+pub fn try_fold_image<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    image: Image<'a>,
+) -> Result<Image<'a>, F::Error> {
+    Ok(Image {
+        link_type: image.link_type,
+        dest_url: image.dest_url,
+        title: image.title,
+        id: image.id,
+        body: folder.try_fold_inlines(cx, image.body)?,
+    })
+}
+
+pub fn try_fold_block_quote<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    block_quote: BlockQuote<'a>,
+) -> Result<BlockQuote<'a>, F::Error> {
+    Ok(BlockQuote {
+        kind: block_quote.kind,
+        body: cx.within(Container::BlockQuote, |cx| {
+            try_fold_blocks(folder, cx, block_quote.body)
+        })?,
+    })
+}
+
+pub fn try_fold_code_block<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    code_block: CodeBlock<'a>,
+) -> Result<CodeBlock<'a>, F::Error> {
+    Ok(CodeBlock {
+        kind: code_block.kind,
+        body: folder.try_fold_inlines(cx, code_block.body)?,
+    })
+}
+
+pub fn try_fold_footnote_definition<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    footnote_definition: FootnoteDefinition<'a>,
+) -> Result<FootnoteDefinition<'a>, F::Error> {
+    Ok(FootnoteDefinition {
+        label: footnote_definition.label,
+        body: cx.within(Container::Footnote, |cx| {
+            try_fold_blocks(folder, cx, footnote_definition.body)
+        })?,
+    })
+}
+
+pub fn try_fold_list<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    list: List<'a>,
+) -> Result<List<'a>, F::Error> {
+    Ok(List {
+        start: list.start,
+        items: cx.within(Container::List, |cx| {
+            list.items
+                .into_iter()
+                .map(|li| folder.try_fold_list_item(cx, li))
+                .collect::<Result<_, _>>()
+        })?,
+    })
+}
+
+pub fn try_fold_list_item<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    list_item: ListItem<'a>,
+) -> Result<ListItem<'a>, F::Error> {
+    Ok(match list_item {
+        ListItem::Inline(vec) => ListItem::Inline(folder.try_fold_inlines(cx, vec)?),
+        ListItem::Block(vec) => ListItem::Block(try_fold_blocks(folder, cx, vec)?),
+    })
+}
+
+pub fn try_fold_definition_list<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    definition_list: DefinitionList<'a>,
+) -> Result<DefinitionList<'a>, F::Error> {
+    Ok(DefinitionList {
+        items: definition_list
+            .items
+            .into_iter()
+            .map(|di| folder.try_fold_definition_item(cx, di))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+pub fn try_fold_definition_item<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    definition_item: DefinitionItem<'a>,
+) -> Result<DefinitionItem<'a>, F::Error> {
+    Ok(DefinitionItem {
+        title: folder.try_fold_inlines(cx, definition_item.title)?,
+        definitions: definition_item
+            .definitions
+            .into_iter()
+            .map(|dd| folder.try_fold_definition_definition(cx, dd))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+pub fn try_fold_definition_definition<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    definition_definition: DefinitionDefinition<'a>,
+) -> Result<DefinitionDefinition<'a>, F::Error> {
+    Ok(DefinitionDefinition {
+        body: folder.try_fold_inlines(cx, definition_definition.body)?,
+    })
+}
+
+pub fn try_fold_table<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    table: Table<'a>,
+) -> Result<Table<'a>, F::Error> {
+    Ok(Table {
+        alignments: table.alignments,
+        header: folder.try_fold_table_head(cx, table.header)?,
+        rows: table
+            .rows
+            .into_iter()
+            .map(|tr| folder.try_fold_table_row(cx, tr))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+pub fn try_fold_table_head<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    table_head: TableHead<'a>,
+) -> Result<TableHead<'a>, F::Error> {
+    Ok(TableHead {
+        cells: table_head
+            .cells
+            .into_iter()
+            .map(|c| folder.try_fold_table_cell(cx, c))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+pub fn try_fold_table_row<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    table_row: TableRow<'a>,
+) -> Result<TableRow<'a>, F::Error> {
+    Ok(TableRow {
+        cells: table_row
+            .cells
+            .into_iter()
+            .map(|c| folder.try_fold_table_cell(cx, c))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+pub fn try_fold_table_cell<'a, F: TryMarkdownFold<'a> + ?Sized>(
+    folder: &mut F,
+    cx: &mut FoldContext,
+    table_cell: TableCell<'a>,
+) -> Result<TableCell<'a>, F::Error> {
+    Ok(TableCell {
+        body: folder.try_fold_inlines(cx, table_cell.body)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ParseOptions;
+
+    use super::*;
+
+    const ENTRY: &str = r###"
+Before any headings
+
+# Actions { .todo }
+
+- [ ] One
+- [?] Two
+  > - [x] Quoted
+
+## Later
+
+- [?] Three
+
+# Notes
+
+- Not a task[^1]
+
+[^1]: - [?] In a footnote
+"###;
+
+    /// Records where each task marker was, and stops at the first `[!]`
+    #[derive(Default)]
+    struct Markers(Vec<String>);
+
+    impl<'a> TryMarkdownFold<'a> for Markers {
+        type Error = String;
+
+        fn try_fold_tasklist_marker(
+            &mut self,
+            cx: &mut FoldContext,
+            marker: char,
+        ) -> Result<char, String> {
+            if marker == '!' {
+                return Err(cx.heading_path().collect::<Vec<_>>().join(" > "));
+            }
+            self.0.push(format!(
+                "{marker} under {:?} depth={} list={} quote={} footnote={}",
+                cx.heading_path().collect::<Vec<_>>(),
+                cx.depth(),
+                cx.in_list(),
+                cx.in_block_quote(),
+                cx.in_footnote()
+            ));
+            Ok(marker)
+        }
+    }
+
+    #[test]
+    fn context() {
+        let doc = Document::parse(ENTRY, ParseOptions::all());
+        let mut markers = Markers::default();
+        let mut cx = FoldContext::new();
+        let folded = markers.try_fold_document(&mut cx, doc.clone()).unwrap();
+        assert_eq!(folded, doc);
+        assert_eq!(
+            markers.0,
+            [
+                r#"  under ["Actions"] depth=1 list=true quote=false footnote=false"#,
+                r#"? under ["Actions"] depth=1 list=true quote=false footnote=false"#,
+                r#"x under ["Actions"] depth=3 list=true quote=true footnote=false"#,
+                r#"? under ["Actions", "Later"] depth=1 list=true quote=false footnote=false"#,
+                r#"? under ["Notes"] depth=2 list=true quote=false footnote=true"#,
+            ]
+        );
+        assert_eq!(cx.depth(), 0);
+        assert_eq!(cx.heading_path().count(), 0);
+    }
+
+    #[test]
+    fn stops_at_error() {
+        let input = ENTRY.replace("[?] Three", "[!] Three");
+        let doc = Document::parse(&input, ParseOptions::all());
+        let mut markers = Markers::default();
+        let err = markers
+            .try_fold_document(&mut FoldContext::new(), doc)
+            .unwrap_err();
+        assert_eq!(err, "Actions > Later");
+        assert_eq!(markers.0.len(), 3);
+    }
+}
@@ -0,0 +1,441 @@
+//! Borrowing traversal of the AST
+//!
+//! Where [`MarkdownFold`](crate::fold::MarkdownFold) consumes and rebuilds
+//! the tree, a [`Visit`] implementation only looks at it.  Every method
+//! returns a [`ControlFlow`], returning [`ControlFlow::Break`] from any
+//! method stops the walk immediately.
+
+use std::ops::ControlFlow;
+
+use pulldown_cmark::CowStr;
+
+use crate::ast::*;
+
+pub trait Visit<'ast> {
+    fn visit_document(&mut self, document: &'ast Document<'ast>) -> ControlFlow<()> {
+        visit_document(self, document)
+    }
+
+    fn visit_block(&mut self, block: &'ast Block<'ast>) -> ControlFlow<()> {
+        visit_block(self, block)
+    }
+
+    fn visit_inline(&mut self, inline: &'ast Inline<'ast>) -> ControlFlow<()> {
+        visit_inline(self, inline)
+    }
+
+    fn visit_rule(&mut self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_soft_break(&mut self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_hard_break(&mut self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_emphasis(&mut self, inlines: &'ast [Inline<'ast>]) -> ControlFlow<()> {
+        visit_inlines(self, inlines)
+    }
+
+    fn visit_strong(&mut self, inlines: &'ast [Inline<'ast>]) -> ControlFlow<()> {
+        visit_inlines(self, inlines)
+    }
+
+    fn visit_strikethrough(&mut self, inlines: &'ast [Inline<'ast>]) -> ControlFlow<()> {
+        visit_inlines(self, inlines)
+    }
+
+    fn visit_text(&mut self, _text: &'ast CowStr<'ast>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_html(&mut self, _html: &'ast CowStr<'ast>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_inline_math(&mut self, _inline_math: &'ast CowStr<'ast>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_display_math(&mut self, _display_math: &'ast CowStr<'ast>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_code(&mut self, _code: &'ast CowStr<'ast>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_tasklist_marker(&mut self, _marker: char) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_footnote_reference(
+        &mut self,
+        _footnote_reference: &'ast CowStr<'ast>,
+    ) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_metadata_block(
+        &mut self,
+        _metadata_block: &'ast MetadataBlock<'ast>,
+    ) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_heading(&mut self, heading: &'ast Heading<'ast>) -> ControlFlow<()> {
+        visit_heading(self, heading)
+    }
+
+    fn visit_paragraph(&mut self, paragraph: &'ast Paragraph<'ast>) -> ControlFlow<()> {
+        visit_paragraph(self, paragraph)
+    }
+
+    fn visit_block_quote(&mut self, block_quote: &'ast BlockQuote<'ast>) -> ControlFlow<()> {
+        visit_block_quote(self, block_quote)
+    }
+
+    fn visit_code_block(&mut self, code_block: &'ast CodeBlock<'ast>) -> ControlFlow<()> {
+        visit_code_block(self, code_block)
+    }
+
+    fn visit_html_block(&mut self, _html_block: &'ast HtmlBlock<'ast>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_footnote_definition(
+        &mut self,
+        footnote_definition: &'ast FootnoteDefinition<'ast>,
+    ) -> ControlFlow<()> {
+        visit_footnote_definition(self, footnote_definition)
+    }
+
+    fn visit_list(&mut self, list: &'ast List<'ast>) -> ControlFlow<()> {
+        visit_list(self, list)
+    }
+
+    fn visit_list_item(&mut self, list_item: &'ast ListItem<'ast>) -> ControlFlow<()> {
+        visit_list_item(self, list_item)
+    }
+
+    fn visit_definition_list(
+        &mut self,
+        definition_list: &'ast DefinitionList<'ast>,
+    ) -> ControlFlow<()> {
+        visit_definition_list(self, definition_list)
+    }
+
+    fn visit_definition_item(
+        &mut self,
+        definition_item: &'ast DefinitionItem<'ast>,
+    ) -> ControlFlow<()> {
+        visit_definition_item(self, definition_item)
+    }
+
+    fn visit_definition_definition(
+        &mut self,
+        definition_definition: &'ast DefinitionDefinition<'ast>,
+    ) -> ControlFlow<()> {
+        visit_definition_definition(self, definition_definition)
+    }
+
+    fn visit_table(&mut self, table: &'ast Table<'ast>) -> ControlFlow<()> {
+        visit_table(self, table)
+    }
+
+    fn visit_table_head(&mut self, table_head: &'ast TableHead<'ast>) -> ControlFlow<()> {
+        visit_table_head(self, table_head)
+    }
+
+    fn visit_table_row(&mut self, table_row: &'ast TableRow<'ast>) -> ControlFlow<()> {
+        visit_table_row(self, table_row)
+    }
+
+    fn visit_table_cell(&mut self, table_cell: &'ast TableCell<'ast>) -> ControlFlow<()> {
+        visit_table_cell(self, table_cell)
+    }
+
+    fn visit_image(&mut self, image: &'ast Image<'ast>) -> ControlFlow<()> {
+        visit_image(self, image)
+    }
+
+    fn visit_link(&mut self, link: &'ast Link<'ast>) -> ControlFlow<()> {
+        visit_link(self, link)
+    }
+}
+
+pub fn visit_inlines<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    inlines: &'ast [Inline<'ast>],
+) -> ControlFlow<()> {
+    inlines.iter().try_for_each(|i| visitor.visit_inline(i))
+}
+
+pub fn visit_blocks<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    blocks: &'ast [Block<'ast>],
+) -> ControlFlow<()> {
+    blocks.iter().try_for_each(|b| visitor.visit_block(b))
+}
+
+pub fn visit_document<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    document: &'ast Document<'ast>,
+) -> ControlFlow<()> {
+    visit_blocks(visitor, &document.blocks)
+}
+
+pub fn visit_block<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    block: &'ast Block<'ast>,
+) -> ControlFlow<()> {
+    match block {
+        Block::Metadata(metadata_block) => visitor.visit_metadata_block(metadata_block),
+        Block::Heading(heading) => visitor.visit_heading(heading),
+        Block::Paragraph(paragraph) => visitor.visit_paragraph(paragraph),
+        Block::BlockQuote(block_quote) => visitor.visit_block_quote(block_quote),
+        Block::CodeBlock(code_block) => visitor.visit_code_block(code_block),
+        Block::HtmlBlock(html_block) => visitor.visit_html_block(html_block),
+        Block::FootnoteDefinition(footnote_definition) => {
+            visitor.visit_footnote_definition(footnote_definition)
+        }
+        Block::List(list) => visitor.visit_list(list),
+        Block::DefinitionList(definition_list) => visitor.visit_definition_list(definition_list),
+        Block::Table(table) => visitor.visit_table(table),
+        Block::Rule => visitor.visit_rule(),
+    }
+}
+
+pub fn visit_inline<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    inline: &'ast Inline<'ast>,
+) -> ControlFlow<()> {
+    match inline {
+        Inline::SoftBreak => visitor.visit_soft_break(),
+        Inline::HardBreak => visitor.visit_hard_break(),
+        Inline::TasklistMarker(marker) => visitor.visit_tasklist_marker(*marker),
+        Inline::Image(image) => visitor.visit_image(image),
+        Inline::Link(link) => visitor.visit_link(link),
+
+        Inline::Text(text) => visitor.visit_text(text),
+        Inline::Html(html) => visitor.visit_html(html),
+        Inline::InlineMath(inline_math) => visitor.visit_inline_math(inline_math),
+        Inline::DisplayMath(display_math) => visitor.visit_display_math(display_math),
+        Inline::Code(code) => visitor.visit_code(code),
+        Inline::FootnoteReference(footnote_reference) => {
+            visitor.visit_footnote_reference(footnote_reference)
+        }
+
+        Inline::Emphasis(inlines) => visitor.visit_emphasis(inlines),
+        Inline::Strong(inlines) => visitor.visit_strong(inlines),
+        Inline::Strikethrough(inlines) => visitor.visit_strikethrough(inlines),
+        Inline::InlineBlock(b) => visitor.visit_block(b),
+    }
+}
+
+pub fn visit_heading<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    heading: &'ast Heading<'ast>,
+) -> ControlFlow<()> {
+    visit_inlines(visitor, &heading.body)
+}
+
+pub fn visit_paragraph<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    paragraph: &'ast Paragraph<'ast>,
+) -> ControlFlow<()> {
+    visit_inlines(visitor, &paragraph.body)
+}
+
+pub fn visit_link<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    link: &'ast Link<'ast>,
+) -> ControlFlow<()> {
+    visit_inlines(visitor, &link.body)
+}
+
+pub fn visit_image<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    image: &'ast Image<'ast>,
+) -> ControlFlow<()> {
+    visit_inlines(visitor, &image.body)
+}
+
+pub fn visit_block_quote<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    block_quote: &'ast BlockQuote<'ast>,
+) -> ControlFlow<()> {
+    visit_blocks(visitor, &block_quote.body)
+}
+
+pub fn visit_code_block<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    code_block: &'ast CodeBlock<'ast>,
+) -> ControlFlow<()> {
+    visit_inlines(visitor, &code_block.body)
+}
+
+pub fn visit_footnote_definition<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    footnote_definition: &'ast FootnoteDefinition<'ast>,
+) -> ControlFlow<()> {
+    visit_blocks(visitor, &footnote_definition.body)
+}
+
+pub fn visit_list<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    list: &'ast List<'ast>,
+) -> ControlFlow<()> {
+    list.items
+        .iter()
+        .try_for_each(|li| visitor.visit_list_item(li))
+}
+
+pub fn visit_list_item<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    list_item: &'ast ListItem<'ast>,
+) -> ControlFlow<()> {
+    match list_item {
+        ListItem::Inline(vec) => visit_inlines(visitor, vec),
+        ListItem::Block(vec) => visit_blocks(visitor, vec),
+    }
+}
+
+pub fn visit_definition_list<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    definition_list: &'ast DefinitionList<'ast>,
+) -> ControlFlow<()> {
+    definition_list
+        .items
+        .iter()
+        .try_for_each(|di| visitor.visit_definition_item(di))
+}
+
+pub fn visit_definition_item<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    definition_item: &'ast DefinitionItem<'ast>,
+) -> ControlFlow<()> {
+    visit_inlines(visitor, &definition_item.title)?;
+    definition_item
+        .definitions
+        .iter()
+        .try_for_each(|dd| visitor.visit_definition_definition(dd))
+}
+
+pub fn visit_definition_definition<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    definition_definition: &'ast DefinitionDefinition<'ast>,
+) -> ControlFlow<()> {
+    visit_inlines(visitor, &definition_definition.body)
+}
+
+pub fn visit_table<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    table: &'ast Table<'ast>,
+) -> ControlFlow<()> {
+    visitor.visit_table_head(&table.header)?;
+    table
+        .rows
+        .iter()
+        .try_for_each(|tr| visitor.visit_table_row(tr))
+}
+
+pub fn visit_table_head<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    table_head: &'ast TableHead<'ast>,
+) -> ControlFlow<()> {
+    table_head
+        .cells
+        .iter()
+        .try_for_each(|c| visitor.visit_table_cell(c))
+}
+
+pub fn visit_table_row<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    table_row: &'ast TableRow<'ast>,
+) -> ControlFlow<()> {
+    table_row
+        .cells
+        .iter()
+        .try_for_each(|c| visitor.visit_table_cell(c))
+}
+
+pub fn visit_table_cell<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    table_cell: &'ast TableCell<'ast>,
+) -> ControlFlow<()> {
+    visit_inlines(visitor, &table_cell.body)
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::ControlFlow;
+
+    use crate::{Document, Heading, ParseOptions};
+
+    use super::*;
+
+    const TASKS: &str = r###"
+# First
+
+- [ ] one
+- [x] two
+
+# Second
+
+> - [ ] three
+
+# Third
+
+- [ ] four
+"###;
+
+    #[derive(Default)]
+    struct TaskCounter {
+        tasks: usize,
+        headings: Vec<String>,
+        stop_after: Option<usize>,
+    }
+
+    impl<'ast> Visit<'ast> for TaskCounter {
+        fn visit_heading(&mut self, heading: &'ast Heading<'ast>) -> ControlFlow<()> {
+            if let Some(Inline::Text(t)) = heading.body.first() {
+                self.headings.push(t.to_string());
+            }
+            visit_heading(self, heading)
+        }
+
+        fn visit_tasklist_marker(&mut self, _marker: char) -> ControlFlow<()> {
+            self.tasks += 1;
+            if Some(self.tasks) == self.stop_after {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    #[test]
+    fn counts_everything() {
+        let doc = Document::parse(TASKS, ParseOptions::all());
+        let mut counter = TaskCounter::default();
+        assert_eq!(counter.visit_document(&doc), ControlFlow::Continue(()));
+        assert_eq!(counter.tasks, 4);
+        assert_eq!(counter.headings, ["First", "Second", "Third"]);
+    }
+
+    #[test]
+    fn stops_early() {
+        let doc = Document::parse(TASKS, ParseOptions::all());
+        let mut counter = TaskCounter {
+            stop_after: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(counter.visit_document(&doc), ControlFlow::Break(()));
+        assert_eq!(counter.tasks, 3);
+        assert_eq!(counter.headings, ["First", "Second"]);
+    }
+}
@@ -0,0 +1,431 @@
+//! In-place traversal of the AST
+//!
+//! A [`VisitMut`] implementation walks the tree through `&mut` references
+//! so that nodes can be edited without rebuilding the whole document as
+//! [`MarkdownFold`](crate::fold::MarkdownFold) does.  As with
+//! [`Visit`](crate::visit::Visit), returning [`ControlFlow::Break`] from any
+//! method stops the walk immediately.
+
+use std::ops::ControlFlow;
+
+use pulldown_cmark::CowStr;
+
+use crate::ast::*;
+
+pub trait VisitMut<'a> {
+    fn visit_document_mut(&mut self, document: &mut Document<'a>) -> ControlFlow<()> {
+        visit_document_mut(self, document)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block<'a>) -> ControlFlow<()> {
+        visit_block_mut(self, block)
+    }
+
+    fn visit_inline_mut(&mut self, inline: &mut Inline<'a>) -> ControlFlow<()> {
+        visit_inline_mut(self, inline)
+    }
+
+    fn visit_rule_mut(&mut self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_soft_break_mut(&mut self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_hard_break_mut(&mut self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_emphasis_mut(&mut self, inlines: &mut Vec<Inline<'a>>) -> ControlFlow<()> {
+        visit_inlines_mut(self, inlines)
+    }
+
+    fn visit_strong_mut(&mut self, inlines: &mut Vec<Inline<'a>>) -> ControlFlow<()> {
+        visit_inlines_mut(self, inlines)
+    }
+
+    fn visit_strikethrough_mut(&mut self, inlines: &mut Vec<Inline<'a>>) -> ControlFlow<()> {
+        visit_inlines_mut(self, inlines)
+    }
+
+    fn visit_text_mut(&mut self, _text: &mut CowStr<'a>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_html_mut(&mut self, _html: &mut CowStr<'a>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_inline_math_mut(&mut self, _inline_math: &mut CowStr<'a>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_display_math_mut(&mut self, _display_math: &mut CowStr<'a>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_code_mut(&mut self, _code: &mut CowStr<'a>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_tasklist_marker_mut(&mut self, _marker: &mut char) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_footnote_reference_mut(
+        &mut self,
+        _footnote_reference: &mut CowStr<'a>,
+    ) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_metadata_block_mut(
+        &mut self,
+        _metadata_block: &mut MetadataBlock<'a>,
+    ) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_heading_mut(&mut self, heading: &mut Heading<'a>) -> ControlFlow<()> {
+        visit_heading_mut(self, heading)
+    }
+
+    fn visit_paragraph_mut(&mut self, paragraph: &mut Paragraph<'a>) -> ControlFlow<()> {
+        visit_paragraph_mut(self, paragraph)
+    }
+
+    fn visit_block_quote_mut(&mut self, block_quote: &mut BlockQuote<'a>) -> ControlFlow<()> {
+        visit_block_quote_mut(self, block_quote)
+    }
+
+    fn visit_code_block_mut(&mut self, code_block: &mut CodeBlock<'a>) -> ControlFlow<()> {
+        visit_code_block_mut(self, code_block)
+    }
+
+    fn visit_html_block_mut(&mut self, _html_block: &mut HtmlBlock<'a>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn visit_footnote_definition_mut(
+        &mut self,
+        footnote_definition: &mut FootnoteDefinition<'a>,
+    ) -> ControlFlow<()> {
+        visit_footnote_definition_mut(self, footnote_definition)
+    }
+
+    fn visit_list_mut(&mut self, list: &mut List<'a>) -> ControlFlow<()> {
+        visit_list_mut(self, list)
+    }
+
+    fn visit_list_item_mut(&mut self, list_item: &mut ListItem<'a>) -> ControlFlow<()> {
+        visit_list_item_mut(self, list_item)
+    }
+
+    fn visit_definition_list_mut(
+        &mut self,
+        definition_list: &mut DefinitionList<'a>,
+    ) -> ControlFlow<()> {
+        visit_definition_list_mut(self, definition_list)
+    }
+
+    fn visit_definition_item_mut(
+        &mut self,
+        definition_item: &mut DefinitionItem<'a>,
+    ) -> ControlFlow<()> {
+        visit_definition_item_mut(self, definition_item)
+    }
+
+    fn visit_definition_definition_mut(
+        &mut self,
+        definition_definition: &mut DefinitionDefinition<'a>,
+    ) -> ControlFlow<()> {
+        visit_definition_definition_mut(self, definition_definition)
+    }
+
+    fn visit_table_mut(&mut self, table: &mut Table<'a>) -> ControlFlow<()> {
+        visit_table_mut(self, table)
+    }
+
+    fn visit_table_head_mut(&mut self, table_head: &mut TableHead<'a>) -> ControlFlow<()> {
+        visit_table_head_mut(self, table_head)
+    }
+
+    fn visit_table_row_mut(&mut self, table_row: &mut TableRow<'a>) -> ControlFlow<()> {
+        visit_table_row_mut(self, table_row)
+    }
+
+    fn visit_table_cell_mut(&mut self, table_cell: &mut TableCell<'a>) -> ControlFlow<()> {
+        visit_table_cell_mut(self, table_cell)
+    }
+
+    fn visit_image_mut(&mut self, image: &mut Image<'a>) -> ControlFlow<()> {
+        visit_image_mut(self, image)
+    }
+
+    fn visit_link_mut(&mut self, link: &mut Link<'a>) -> ControlFlow<()> {
+        visit_link_mut(self, link)
+    }
+}
+
+pub fn visit_inlines_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    inlines: &mut [Inline<'a>],
+) -> ControlFlow<()> {
+    inlines
+        .iter_mut()
+        .try_for_each(|i| visitor.visit_inline_mut(i))
+}
+
+pub fn visit_blocks_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    blocks: &mut [Block<'a>],
+) -> ControlFlow<()> {
+    blocks
+        .iter_mut()
+        .try_for_each(|b| visitor.visit_block_mut(b))
+}
+
+pub fn visit_document_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    document: &mut Document<'a>,
+) -> ControlFlow<()> {
+    visit_blocks_mut(visitor, &mut document.blocks)
+}
+
+pub fn visit_block_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    block: &mut Block<'a>,
+) -> ControlFlow<()> {
+    match block {
+        Block::Metadata(metadata_block) => visitor.visit_metadata_block_mut(metadata_block),
+        Block::Heading(heading) => visitor.visit_heading_mut(heading),
+        Block::Paragraph(paragraph) => visitor.visit_paragraph_mut(paragraph),
+        Block::BlockQuote(block_quote) => visitor.visit_block_quote_mut(block_quote),
+        Block::CodeBlock(code_block) => visitor.visit_code_block_mut(code_block),
+        Block::HtmlBlock(html_block) => visitor.visit_html_block_mut(html_block),
+        Block::FootnoteDefinition(footnote_definition) => {
+            visitor.visit_footnote_definition_mut(footnote_definition)
+        }
+        Block::List(list) => visitor.visit_list_mut(list),
+        Block::DefinitionList(definition_list) => {
+            visitor.visit_definition_list_mut(definition_list)
+        }
+        Block::Table(table) => visitor.visit_table_mut(table),
+        Block::Rule => visitor.visit_rule_mut(),
+    }
+}
+
+pub fn visit_inline_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    inline: &mut Inline<'a>,
+) -> ControlFlow<()> {
+    match inline {
+        Inline::SoftBreak => visitor.visit_soft_break_mut(),
+        Inline::HardBreak => visitor.visit_hard_break_mut(),
+        Inline::TasklistMarker(marker) => visitor.visit_tasklist_marker_mut(marker),
+        Inline::Image(image) => visitor.visit_image_mut(image),
+        Inline::Link(link) => visitor.visit_link_mut(link),
+
+        Inline::Text(text) => visitor.visit_text_mut(text),
+        Inline::Html(html) => visitor.visit_html_mut(html),
+        Inline::InlineMath(inline_math) => visitor.visit_inline_math_mut(inline_math),
+        Inline::DisplayMath(display_math) => visitor.visit_display_math_mut(display_math),
+        Inline::Code(code) => visitor.visit_code_mut(code),
+        Inline::FootnoteReference(footnote_reference) => {
+            visitor.visit_footnote_reference_mut(footnote_reference)
+        }
+
+        Inline::Emphasis(inlines) => visitor.visit_emphasis_mut(inlines),
+        Inline::Strong(inlines) => visitor.visit_strong_mut(inlines),
+        Inline::Strikethrough(inlines) => visitor.visit_strikethrough_mut(inlines),
+        Inline::InlineBlock(b) => visitor.visit_block_mut(b),
+    }
+}
+
+pub fn visit_heading_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    heading: &mut Heading<'a>,
+) -> ControlFlow<()> {
+    visit_inlines_mut(visitor, &mut heading.body)
+}
+
+pub fn visit_paragraph_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    paragraph: &mut Paragraph<'a>,
+) -> ControlFlow<()> {
+    visit_inlines_mut(visitor, &mut paragraph.body)
+}
+
+pub fn visit_link_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    link: &mut Link<'a>,
+) -> ControlFlow<()> {
+    visit_inlines_mut(visitor, &mut link.body)
+}
+
+pub fn visit_image_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    image: &mut Image<'a>,
+) -> ControlFlow<()> {
+    visit_inlines_mut(visitor, &mut image.body)
+}
+
+pub fn visit_block_quote_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    block_quote: &mut BlockQuote<'a>,
+) -> ControlFlow<()> {
+    visit_blocks_mut(visitor, &mut block_quote.body)
+}
+
+pub fn visit_code_block_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    code_block: &mut CodeBlock<'a>,
+) -> ControlFlow<()> {
+    visit_inlines_mut(visitor, &mut code_block.body)
+}
+
+pub fn visit_footnote_definition_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    footnote_definition: &mut FootnoteDefinition<'a>,
+) -> ControlFlow<()> {
+    visit_blocks_mut(visitor, &mut footnote_definition.body)
+}
+
+pub fn visit_list_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    list: &mut List<'a>,
+) -> ControlFlow<()> {
+    list.items
+        .iter_mut()
+        .try_for_each(|li| visitor.visit_list_item_mut(li))
+}
+
+pub fn visit_list_item_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    list_item: &mut ListItem<'a>,
+) -> ControlFlow<()> {
+    match list_item {
+        ListItem::Inline(vec) => visit_inlines_mut(visitor, vec),
+        ListItem::Block(vec) => visit_blocks_mut(visitor, vec),
+    }
+}
+
+pub fn visit_definition_list_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    definition_list: &mut DefinitionList<'a>,
+) -> ControlFlow<()> {
+    definition_list
+        .items
+        .iter_mut()
+        .try_for_each(|di| visitor.visit_definition_item_mut(di))
+}
+
+pub fn visit_definition_item_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    definition_item: &mut DefinitionItem<'a>,
+) -> ControlFlow<()> {
+    visit_inlines_mut(visitor, &mut definition_item.title)?;
+    definition_item
+        .definitions
+        .iter_mut()
+        .try_for_each(|dd| visitor.visit_definition_definition_mut(dd))
+}
+
+pub fn visit_definition_definition_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    definition_definition: &mut DefinitionDefinition<'a>,
+) -> ControlFlow<()> {
+    visit_inlines_mut(visitor, &mut definition_definition.body)
+}
+
+pub fn visit_table_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    table: &mut Table<'a>,
+) -> ControlFlow<()> {
+    visitor.visit_table_head_mut(&mut table.header)?;
+    table
+        .rows
+        .iter_mut()
+        .try_for_each(|tr| visitor.visit_table_row_mut(tr))
+}
+
+pub fn visit_table_head_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    table_head: &mut TableHead<'a>,
+) -> ControlFlow<()> {
+    table_head
+        .cells
+        .iter_mut()
+        .try_for_each(|c| visitor.visit_table_cell_mut(c))
+}
+
+pub fn visit_table_row_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    table_row: &mut TableRow<'a>,
+) -> ControlFlow<()> {
+    table_row
+        .cells
+        .iter_mut()
+        .try_for_each(|c| visitor.visit_table_cell_mut(c))
+}
+
+pub fn visit_table_cell_mut<'a, V: VisitMut<'a> + ?Sized>(
+    visitor: &mut V,
+    table_cell: &mut TableCell<'a>,
+) -> ControlFlow<()> {
+    visit_inlines_mut(visitor, &mut table_cell.body)
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::ControlFlow;
+
+    use insta::assert_snapshot;
+
+    use crate::{Document, ParseOptions, RenderOptions};
+
+    use super::*;
+
+    const TASKS: &str = r###"
+# Tasks
+
+- [ ] one
+- [ ] two
+- [ ] three
+"###;
+
+    struct TickFirst(usize);
+
+    impl<'a> VisitMut<'a> for TickFirst {
+        fn visit_tasklist_marker_mut(&mut self, marker: &mut char) -> ControlFlow<()> {
+            if self.0 == 0 {
+                return ControlFlow::Break(());
+            }
+            self.0 -= 1;
+            *marker = 'x';
+            ControlFlow::Continue(())
+        }
+
+        fn visit_text_mut(&mut self, text: &mut CowStr<'a>) -> ControlFlow<()> {
+            *text = text.to_uppercase().into();
+            ControlFlow::Continue(())
+        }
+    }
+
+    #[test]
+    fn edits_in_place() {
+        let mut doc = Document::parse(TASKS, ParseOptions::all());
+        assert_eq!(
+            TickFirst(2).visit_document_mut(&mut doc),
+            ControlFlow::Break(())
+        );
+        assert_snapshot!(doc.render(RenderOptions::default()).unwrap(), @r###"
+        # TASKS
+
+        * [x] ONE
+        * [x] TWO
+        * [ ] three
+        "###);
+    }
+}
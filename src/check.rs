@@ -0,0 +1,133 @@
+//! Checking entries for mistakes before they are committed
+//!
+//! `jt check` is run by the pre-commit hook which `jt hooks install` sets
+//! up.  The configuration on disk is checked whenever the journal is
+//! loaded, which includes the editor command's substitution variables, so
+//! only a staged configuration needs checking here alongside the entries.
+
+use std::{
+    fmt,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+};
+
+use pulldown_cmark_ast::{section::Sections, visit::Visit, Document};
+use time::Date;
+
+use crate::{config::Configuration, filters::known_marker, markdown::MarkdownFile};
+
+/// The pre-commit hook installed by `jt hooks install`
+pub const PRE_COMMIT_HOOK: &str = "#!/bin/sh
+# Installed by jt hooks install, remove with jt hooks uninstall
+exec jt --path . check --staged
+";
+
+/// Something wrong with an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// The task markers in part of an entry
+#[derive(Default)]
+struct Markers(Vec<char>);
+
+impl Visit<'_> for Markers {
+    fn visit_tasklist_marker(&mut self, marker: char) -> ControlFlow<()> {
+        self.0.push(marker);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Everything wrong with `toml` as a replacement for the journal
+/// configuration `config`, which is at `path`
+pub fn configuration(config: &Configuration, path: &Path, toml: &str) -> Vec<Problem> {
+    match config.with_journal(path, toml) {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![Problem {
+            path: path.to_path_buf(),
+            message: format!("{e:#}"),
+        }],
+    }
+}
+
+/// Everything wrong with the entry at `path`, relative to the journal
+pub fn entry(config: &Configuration, path: &Path, body: &str) -> Vec<Problem> {
+    let mut ret = Vec::new();
+    let mut problem = |message: String| {
+        ret.push(Problem {
+            path: path.to_path_buf(),
+            message,
+        })
+    };
+    if Date::parse(&path.to_string_lossy(), config.log_pattern()).is_err() {
+        problem(format!(
+            "Filename does not match log_pattern `{}`",
+            config.log_glob()
+        ));
+    }
+    let file = match MarkdownFile::parse(path, body) {
+        Ok(file) => file,
+        Err(e) => {
+            problem(format!("{e:#}"));
+            return ret;
+        }
+    };
+    let sections = Sections::from_blocks(file.document().blocks);
+    // Everything beneath a todo section is processed as tasks, so nested
+    // sections needn't be looked at separately
+    let mut pending = sections.sections.iter().collect::<Vec<_>>();
+    while let Some(section) = pending.pop() {
+        if !section.has_class("todo") {
+            pending.extend(&section.children);
+            continue;
+        }
+        let mut markers = Markers::default();
+        let _ = markers.visit_document(&Document {
+            blocks: section.clone().into_blocks(),
+        });
+        for marker in markers.0.into_iter().filter(|m| !known_marker(*m)) {
+            problem(format!(
+                "Unknown task marker `[{marker}]` in the `{}` section",
+                section.title()
+            ));
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn problems() {
+        let config = Configuration::default();
+        let good = "+++\ntitle = \"Fine\"\n+++\n# Actions {.todo}\n\n- [ ] One\n- [x] Two\n";
+        assert!(entry(&config, Path::new("log/2024-12-02.md"), good).is_empty());
+
+        let body = "+++\ntitle = \"Fine\"\n+++\n# Actions {.todo}\n\n- [?] One\n\n## Later\n\n- [q] Two\n\n# Notes\n\n- [?] Not a task list\n";
+        let problems = entry(&config, Path::new("log/2024-12-2.md"), body)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        insta::assert_snapshot!(problems.join("\n"), @r###"
+        log/2024-12-2.md: Filename does not match log_pattern `log/*-*-*.md`
+        log/2024-12-2.md: Unknown task marker `[?]` in the `Actions` section
+        log/2024-12-2.md: Unknown task marker `[q]` in the `Actions` section
+        "###);
+
+        let body = "+++\ntitle = \n+++\n";
+        let problems = entry(&config, Path::new("log/2024-12-02.md"), body);
+        assert!(problems[0]
+            .message
+            .starts_with("Trying to parse frontmatter from: log/2024-12-02.md"));
+    }
+}
@@ -2,26 +2,75 @@
 
 use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use eyre::{bail, Result};
+use time::Date;
+use tracing::warn;
 
 mod helpers;
 use git_testament::git_testament;
 use helpers::*;
 
+use crate::{config::profiles::Profiles, vcs::VcsKind};
+
 git_testament!(pub TESTAMENT);
 
 #[derive(Parser)]
 pub struct Cli {
-    #[clap(short, long, env = "JUNTAKAMI_PATH", default_value_os_t = default_journal_path())]
-    /// The path to the journal
-    path: PathBuf,
+    #[clap(short, long)]
+    /// The path to the journal, or else $JUNTAKAMI_PATH, or else the default
+    /// named journal, or else ~/journal
+    path: Option<PathBuf>,
+    #[clap(short, long, conflicts_with = "path")]
+    /// A journal named in the global configuration, instead of a path
+    journal: Option<String>,
     #[clap(subcommand)]
     cmd: Cmd,
 }
 
 impl Cli {
-    pub fn path(&self) -> &Path {
-        &self.path
+    /// The journal to work on
+    ///
+    /// A named journal or a path given on the commandline wins over
+    /// `JUNTAKAMI_PATH`, which wins over the default named journal.
+    pub fn path(&self) -> Result<PathBuf> {
+        let path = match &self.journal {
+            Some(_) => None,
+            None => self
+                .path
+                .clone()
+                .or_else(|| std::env::var_os("JUNTAKAMI_PATH").map(PathBuf::from)),
+        };
+        if let Some(path) = path {
+            return Ok(path);
+        }
+        let profiles = Profiles::load()?;
+        if let Some(name) = &self.journal {
+            return Ok(profiles.get(name)?.to_path_buf());
+        }
+        if let Some(problem) = profiles.problem() {
+            bail!("{problem}");
+        }
+        match profiles.default_journal() {
+            Some(name) => Ok(profiles.get(name)?.to_path_buf()),
+            None => Ok(default_journal_path()),
+        }
+    }
+
+    /// The journal to initialise, as [`Cli::path`] except that if the
+    /// default named journal can't be found, ~/journal is used instead
+    ///
+    /// Otherwise a mistake in the global configuration would stop a journal
+    /// being set up at all.
+    pub fn init_path(&self) -> Result<PathBuf> {
+        if self.journal.is_some() {
+            return self.path();
+        }
+        self.path().or_else(|e| {
+            let path = default_journal_path();
+            warn!("{e:#}, so initialising {}", path.display());
+            Ok(path)
+        })
     }
 
     pub fn cmd(&self) -> &Cmd {
@@ -34,14 +83,146 @@ pub enum Cmd {
     #[clap(hide = true)]
     DumpTestament,
     Init(InitArgs),
-    /// Show the status of the git tree in the journal
-    Status,
-    /// Show the configuration of the journal
-    ShowConfig,
+    Status(StatusArgs),
+    Journals(JournalsArgs),
+    ShowConfig(ShowConfigArgs),
+    #[clap(subcommand)]
+    /// Read and change the journal's configuration
+    Config(ConfigCmd),
     /// Prepare today's entry
     Prep,
     /// Edit today's entry
     Edit,
+    Diff(DiffArgs),
+    History(HistoryArgs),
+    MergeDriver(MergeDriverArgs),
+    Check(CheckArgs),
+    #[clap(subcommand)]
+    /// Manage the git hooks which keep the journal tidy
+    Hooks(HooksCmd),
+    #[clap(subcommand)]
+    /// Work with the tables in entries
+    Table(TableCmd),
+    #[clap(subcommand)]
+    /// Record time spent on projects in today's entry
+    Clock(ClockCmd),
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Clone, Parser)]
+/// Show the state of the journal: entries, tasks and uncommitted changes
+pub struct StatusArgs {
+    #[clap(long, value_enum, default_value_t)]
+    /// How to write the status out
+    format: OutputFormat,
+}
+
+impl StatusArgs {
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+}
+
+#[derive(Clone, Parser)]
+/// List the journals named in the global configuration, with their status
+pub struct JournalsArgs {
+    #[clap(long, value_enum, default_value_t)]
+    /// How to write the list out
+    format: OutputFormat,
+}
+
+impl JournalsArgs {
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+}
+
+#[derive(Clone, Parser)]
+/// Show the configuration of the journal, with every layer merged
+pub struct ShowConfigArgs {
+    #[clap(long)]
+    /// Note which layer each value came from
+    sources: bool,
+}
+
+impl ShowConfigArgs {
+    pub fn sources(&self) -> bool {
+        self.sources
+    }
+}
+
+#[derive(Clone, Parser)]
+pub enum ConfigCmd {
+    Get(ConfigGetArgs),
+    Set(ConfigSetArgs),
+    Unset(ConfigUnsetArgs),
+    Migrate(ConfigMigrateArgs),
+}
+
+#[derive(Clone, Parser)]
+/// Show the effective value of a setting
+pub struct ConfigGetArgs {
+    /// The setting, such as `juntakami.editor`
+    key: String,
+}
+
+impl ConfigGetArgs {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+#[derive(Clone, Parser)]
+/// Change a setting in the journal's configuration file
+pub struct ConfigSetArgs {
+    /// The setting, such as `render.wrap_width`
+    key: String,
+    /// The new value, as TOML such as `72` or `["vim", "@ENTRY@"]`, or else a string
+    value: String,
+}
+
+impl ConfigSetArgs {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+#[derive(Clone, Parser)]
+/// Remove a setting from the journal's configuration file, so that it's
+/// inherited again
+pub struct ConfigUnsetArgs {
+    /// The setting, such as `render.wrap_width`
+    key: String,
+}
+
+impl ConfigUnsetArgs {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+#[derive(Clone, Parser)]
+/// Rewrite a configuration file from an older jt in the current format
+pub struct ConfigMigrateArgs {
+    #[clap(long)]
+    /// Migrate the global configuration file instead of the journal's
+    global: bool,
+}
+
+impl ConfigMigrateArgs {
+    pub fn global(&self) -> bool {
+        self.global
+    }
 }
 
 #[derive(Clone, Parser)]
@@ -50,10 +231,208 @@ pub struct InitArgs {
     #[clap(short, long)]
     /// Force initialisation even if there's a journal config already
     force: bool,
+    #[clap(long, value_enum, default_value_t)]
+    /// The version control to keep the journal in
+    vcs: VcsKind,
 }
 
 impl InitArgs {
     pub fn force(&self) -> bool {
         self.force
     }
+
+    pub fn vcs(&self) -> VcsKind {
+        self.vcs
+    }
+}
+
+#[derive(Clone, Parser)]
+/// Show how one day's entry differs from another's
+pub struct DiffArgs {
+    #[clap(value_parser = parse_date)]
+    /// The earlier entry, as YYYY-MM-DD, `today` or `yesterday`
+    from: Date,
+    #[clap(value_parser = parse_date)]
+    /// The later entry, as YYYY-MM-DD, `today` or `yesterday`
+    to: Date,
+    #[clap(long)]
+    /// Do this for every journal named in the global configuration
+    all_journals: bool,
+}
+
+impl DiffArgs {
+    pub fn from(&self) -> Date {
+        self.from
+    }
+
+    pub fn to(&self) -> Date {
+        self.to
+    }
+
+    pub fn all_journals(&self) -> bool {
+        self.all_journals
+    }
+}
+
+#[derive(Clone, Parser)]
+/// List the commits which changed an entry
+pub struct HistoryArgs {
+    #[clap(value_parser = parse_date)]
+    /// The entry, as YYYY-MM-DD, `today` or `yesterday`
+    date: Date,
+    #[clap(long, value_name = "REV", conflicts_with = "tasks")]
+    /// Print the entry as it was at a revision instead
+    show: Option<String>,
+    #[clap(long)]
+    /// Show how each task in the entry changed over time instead
+    tasks: bool,
+    #[clap(long, conflicts_with = "show")]
+    /// Do this for every journal named in the global configuration
+    all_journals: bool,
+}
+
+impl HistoryArgs {
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    pub fn show(&self) -> Option<&str> {
+        self.show.as_deref()
+    }
+
+    pub fn tasks(&self) -> bool {
+        self.tasks
+    }
+
+    pub fn all_journals(&self) -> bool {
+        self.all_journals
+    }
+}
+
+#[derive(Clone, Parser)]
+/// Merge two versions of an entry, when run by git as a merge driver
+pub struct MergeDriverArgs {
+    /// The common ancestor of the entries (%O)
+    base: PathBuf,
+    /// Our version of the entry, which the result is written to (%A)
+    ours: PathBuf,
+    /// Their version of the entry (%B)
+    theirs: PathBuf,
+}
+
+impl MergeDriverArgs {
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    pub fn ours(&self) -> &Path {
+        &self.ours
+    }
+
+    pub fn theirs(&self) -> &Path {
+        &self.theirs
+    }
+}
+
+#[derive(Clone, Parser)]
+/// Check entries for broken frontmatter, unknown task markers and misnamed files
+pub struct CheckArgs {
+    #[clap(long, conflicts_with = "paths")]
+    /// Check the entries staged for commit, as the pre-commit hook does
+    staged: bool,
+    /// The entries to check, relative to the journal; all of them by default
+    paths: Vec<PathBuf>,
+}
+
+impl CheckArgs {
+    pub fn staged(&self) -> bool {
+        self.staged
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+#[derive(Clone, Parser)]
+pub enum HooksCmd {
+    Install(HooksInstallArgs),
+    /// Remove the pre-commit hook
+    Uninstall,
+}
+
+#[derive(Clone, Parser)]
+/// Install a pre-commit hook running `jt check`, and the merge driver for entries
+pub struct HooksInstallArgs {
+    #[clap(short, long)]
+    /// Replace any existing pre-commit hook
+    force: bool,
+}
+
+impl HooksInstallArgs {
+    pub fn force(&self) -> bool {
+        self.force
+    }
+}
+
+#[derive(Clone, Parser)]
+pub enum TableCmd {
+    Export(TableExportArgs),
+}
+
+#[derive(Clone, Parser)]
+/// Write out a table from an entry as CSV
+pub struct TableExportArgs {
+    #[clap(value_parser = parse_date)]
+    /// The entry, as YYYY-MM-DD, `today` or `yesterday`
+    date: Date,
+    /// The heading of the section the table is in
+    heading: String,
+}
+
+impl TableExportArgs {
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    pub fn heading(&self) -> &str {
+        &self.heading
+    }
+}
+
+#[derive(Clone, Parser)]
+pub enum ClockCmd {
+    In(ClockInArgs),
+    /// Stop recording time against the current project
+    Out,
+    /// Show what is currently being recorded
+    Status,
+    Report(ClockReportArgs),
+}
+
+#[derive(Clone, Parser)]
+/// Start recording time against a project, stopping any other
+pub struct ClockInArgs {
+    /// The project to record time against
+    project: String,
+}
+
+impl ClockInArgs {
+    pub fn project(&self) -> &str {
+        &self.project
+    }
+}
+
+#[derive(Clone, Parser)]
+/// Show the time spent on each project today
+pub struct ClockReportArgs {
+    #[clap(short, long)]
+    /// Cover the whole week so far, from Monday
+    week: bool,
+}
+
+impl ClockReportArgs {
+    pub fn week(&self) -> bool {
+        self.week
+    }
 }
@@ -2,8 +2,25 @@
 
 use std::path::{Path, PathBuf};
 
+use time::{macros::format_description, Date, Duration, OffsetDateTime};
+
 pub fn default_journal_path() -> PathBuf {
     home::home_dir()
         .unwrap_or(Path::new(std::path::Component::RootDir.as_os_str()).into())
         .join("journal")
 }
+
+/// Parse a date given on the commandline
+pub fn parse_date(value: &str) -> Result<Date, String> {
+    let today = || {
+        OffsetDateTime::now_local()
+            .map(|now| now.date())
+            .map_err(|e| format!("Unable to determine today's date: {e}"))
+    };
+    match value {
+        "today" => today(),
+        "yesterday" => Ok(today()? - Duration::days(1)),
+        _ => Date::parse(value, format_description!("[year]-[month]-[day]"))
+            .map_err(|e| format!("Expected a date as YYYY-MM-DD: {e}")),
+    }
+}
@@ -0,0 +1,329 @@
+//! Time tracking within daily entries
+//!
+//! Clocking in and out records intervals in a table under a heading with
+//! the `clock` class, which is added to the end of the entry when first
+//! needed:
+//!
+//! ```markdown
+//! # Clock { .keep .clock }
+//!
+//! |Project|In|Out|
+//! |-------|--|---|
+//! |juntakami|2024-12-02 09:00 +00:00|2024-12-02 10:30 +00:00|
+//! |support|2024-12-02 10:30 +00:00||
+//! ```
+//!
+//! An interval which is still open when the next entry is prepared is
+//! carried over into it, so the same interval can appear in more than one
+//! entry.  Intervals are identified by their project and start time, so
+//! that they are only counted once when reporting.
+
+use std::collections::{BTreeMap, HashMap};
+
+use eyre::{bail, eyre, Context, Result};
+use pulldown_cmark_ast::{
+    builder::{HeadingBuilder, TableBuilder},
+    section::{Section, Sections},
+    Block, Document, HeadingLevel, Table, TableCell, TableRow,
+};
+use time::{
+    format_description::BorrowedFormatItem, macros::format_description, Duration, OffsetDateTime,
+};
+
+pub const CLOCK_CLASS: &str = "clock";
+
+const TIMESTAMP: &[BorrowedFormatItem<'static>] = format_description!(
+    "[year]-[month]-[day] [hour]:[minute] [offset_hour sign:mandatory]:[offset_minute]"
+);
+
+/// A stretch of time spent on a project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interval {
+    pub project: String,
+    pub start: OffsetDateTime,
+    /// When the interval was closed, if it has been
+    pub end: Option<OffsetDateTime>,
+}
+
+impl Interval {
+    /// How much of the interval falls between `from` and `to`
+    ///
+    /// An open interval is taken to run until `to`.
+    pub fn duration_within(&self, from: OffsetDateTime, to: OffsetDateTime) -> Duration {
+        let start = self.start.max(from);
+        let end = self.end.unwrap_or(to).min(to);
+        (end - start).max(Duration::ZERO)
+    }
+}
+
+/// Where each field lives in a clock table
+struct Columns {
+    project: usize,
+    start: usize,
+    end: usize,
+}
+
+impl Columns {
+    fn of(table: &Table<'_>) -> Result<Self> {
+        let column = |name: &str| {
+            table
+                .column(name)
+                .ok_or_else(|| eyre!("Clock table has no `{name}` column"))
+        };
+        Ok(Self {
+            project: column("Project")?,
+            start: column("In")?,
+            end: column("Out")?,
+        })
+    }
+}
+
+fn timestamp(when: OffsetDateTime) -> Result<String> {
+    when.format(TIMESTAMP)
+        .with_context(|| format!("Formatting clock time {when}"))
+}
+
+fn cell_text(table: &Table<'_>, row: usize, column: usize) -> String {
+    table
+        .cell(row, column)
+        .map(TableCell::text)
+        .unwrap_or_default()
+}
+
+fn rows(table: &Table<'_>) -> Result<Vec<Interval>> {
+    let columns = Columns::of(table)?;
+    (0..table.rows.len())
+        .map(|row| {
+            let parse = |text: String| {
+                OffsetDateTime::parse(&text, TIMESTAMP).with_context(|| {
+                    format!(
+                        "Parsing `{text}` in row {} of the clock table as YYYY-MM-DD HH:MM +HH:MM",
+                        row + 1
+                    )
+                })
+            };
+            let end = cell_text(table, row, columns.end);
+            Ok(Interval {
+                project: cell_text(table, row, columns.project),
+                start: parse(cell_text(table, row, columns.start))?,
+                end: if end.is_empty() {
+                    None
+                } else {
+                    Some(parse(end)?)
+                },
+            })
+        })
+        .collect()
+}
+
+fn clock_table<'d, 'a>(section: &'d Section<'a>) -> Option<&'d Table<'a>> {
+    section.body.iter().find_map(|block| match block {
+        Block::Table(table) => Some(table),
+        _ => None,
+    })
+}
+
+/// Every interval recorded in an entry
+pub fn intervals(doc: &Document<'_>) -> Result<Vec<Interval>> {
+    let sections = Sections::from_blocks(doc.blocks.clone());
+    match sections.find_by_class(CLOCK_CLASS).and_then(clock_table) {
+        Some(table) => rows(table),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Run `f` on the clock table of the entry, adding one if needed
+fn with_clock_table<'a, R>(
+    doc: Document<'a>,
+    f: impl FnOnce(&mut Table<'a>) -> Result<R>,
+) -> Result<(Document<'a>, R)> {
+    let mut sections = Sections::from_blocks(doc.blocks);
+    if sections.find_by_class(CLOCK_CLASS).is_none() {
+        let heading = HeadingBuilder::new(HeadingLevel::H1, "Clock")
+            .class("keep")
+            .class(CLOCK_CLASS)
+            .build();
+        sections.sections.push(Section::new(heading));
+    }
+    let section = sections.find_mut(|s| s.has_class(CLOCK_CLASS)).unwrap();
+    if clock_table(section).is_none() {
+        section.body.push(Block::Table(
+            TableBuilder::new(["Project", "In", "Out"]).build(),
+        ));
+    }
+    let table = section
+        .body
+        .iter_mut()
+        .find_map(|block| match block {
+            Block::Table(table) => Some(table),
+            _ => None,
+        })
+        .unwrap();
+    let ret = f(table)?;
+    Ok((
+        Document {
+            blocks: sections.into_blocks(),
+        },
+        ret,
+    ))
+}
+
+/// Close every open interval in the table, returning them
+fn close_open(table: &mut Table<'_>, now: OffsetDateTime) -> Result<Vec<Interval>> {
+    let columns = Columns::of(table)?;
+    let mut closed = Vec::new();
+    for (row, interval) in rows(table)?.into_iter().enumerate() {
+        if interval.end.is_none() {
+            *table.cell_mut(row, columns.end).unwrap() = TableCell::new(timestamp(now)?);
+            closed.push(Interval {
+                end: Some(now),
+                ..interval
+            });
+        }
+    }
+    Ok(closed)
+}
+
+/// Start an interval on `project`, closing any other open interval
+///
+/// Returns the intervals which were closed.
+pub fn clock_in<'a>(
+    doc: Document<'a>,
+    project: &str,
+    now: OffsetDateTime,
+) -> Result<(Document<'a>, Vec<Interval>)> {
+    with_clock_table(doc, |table| {
+        if rows(table)?
+            .iter()
+            .any(|i| i.end.is_none() && i.project == project)
+        {
+            bail!("Already clocked in to {project}");
+        }
+        let closed = close_open(table, now)?;
+        let columns = Columns::of(table)?;
+        let mut row = TableRow {
+            cells: vec![TableCell::empty(); table.columns()],
+        };
+        row.cells[columns.project] = TableCell::new(project.to_string());
+        row.cells[columns.start] = TableCell::new(timestamp(now)?);
+        table.push_row(row);
+        Ok(closed)
+    })
+}
+
+/// Close the open interval, returning it
+pub fn clock_out(doc: Document<'_>, now: OffsetDateTime) -> Result<(Document<'_>, Interval)> {
+    let (doc, mut closed) = with_clock_table(doc, |table| close_open(table, now))?;
+    match closed.pop() {
+        Some(interval) => Ok((doc, interval)),
+        None => bail!("Not clocked in to anything"),
+    }
+}
+
+/// The time spent on each project between `from` and `to`
+///
+/// Intervals which appear more than once, because they were carried over
+/// between entries, are only counted once, preferring a closed copy.
+pub fn report(
+    intervals: impl IntoIterator<Item = Interval>,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> BTreeMap<String, Duration> {
+    let mut unique: HashMap<(String, OffsetDateTime), Interval> = HashMap::new();
+    for interval in intervals {
+        let key = (interval.project.clone(), interval.start);
+        match unique.get(&key) {
+            Some(existing) if existing.end.is_some() => {}
+            _ => {
+                unique.insert(key, interval);
+            }
+        }
+    }
+    let mut ret = BTreeMap::new();
+    for interval in unique.into_values() {
+        let duration = interval.duration_within(from, to);
+        if duration > Duration::ZERO {
+            *ret.entry(interval.project).or_insert(Duration::ZERO) += duration;
+        }
+    }
+    ret
+}
+
+/// A duration as hours and minutes, e.g. `1:05`
+pub fn hours(duration: Duration) -> String {
+    let minutes = duration.whole_minutes();
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod test {
+    use insta::assert_snapshot;
+    use pulldown_cmark_ast::{ParseOptions, RenderOptions};
+    use time::macros::datetime;
+
+    use super::*;
+
+    const ENTRY: &str = r###"
+# Actions {.keep .todo}
+
+- [ ] Track some time
+"###;
+
+    #[test]
+    fn clocking() {
+        let doc = Document::parse(ENTRY, ParseOptions::all());
+        let (doc, closed) = clock_in(doc, "juntakami", datetime!(2024-12-02 09:00 UTC)).unwrap();
+        assert!(closed.is_empty());
+        let (doc, closed) = clock_in(doc, "support", datetime!(2024-12-02 10:30 UTC)).unwrap();
+        assert_eq!(closed[0].project, "juntakami");
+        assert!(clock_in(doc.clone(), "support", datetime!(2024-12-02 10:45 UTC)).is_err());
+        let (doc, closed) = clock_out(doc, datetime!(2024-12-02 11:15 UTC)).unwrap();
+        assert_eq!(closed.project, "support");
+        assert!(clock_out(doc.clone(), datetime!(2024-12-02 11:20 UTC)).is_err());
+
+        assert_snapshot!(doc.render(RenderOptions::default()).unwrap(), @r###"
+        # Actions { .keep .todo }
+
+        * [ ] Track some time
+
+        # Clock { .keep .clock }
+
+        |Project|In|Out|
+        |-------|--|---|
+        |juntakami|2024-12-02 09:00 +00:00|2024-12-02 10:30 +00:00|
+        |support|2024-12-02 10:30 +00:00|2024-12-02 11:15 +00:00|
+        "###);
+    }
+
+    #[test]
+    fn reporting() {
+        let day = |start, end| Interval {
+            project: "juntakami".into(),
+            start,
+            end,
+        };
+        let intervals = [
+            // Carried over from the day before, and closed the next morning
+            day(datetime!(2024-12-01 22:00 UTC), None),
+            day(
+                datetime!(2024-12-01 22:00 UTC),
+                Some(datetime!(2024-12-02 01:00 UTC)),
+            ),
+            Interval {
+                project: "support".into(),
+                start: datetime!(2024-12-02 09:00 UTC),
+                end: None,
+            },
+        ];
+        let totals = report(
+            intervals,
+            datetime!(2024-12-02 00:00 UTC),
+            datetime!(2024-12-02 10:30 UTC),
+        );
+        let totals = totals
+            .iter()
+            .map(|(project, duration)| format!("{project} {}", hours(*duration)))
+            .collect::<Vec<_>>();
+        assert_eq!(totals, ["juntakami 1:00", "support 1:30"]);
+    }
+}
@@ -1,31 +1,77 @@
 //! Configuration for journals
+//!
+//! The configuration is layered: the built-in defaults, then the global
+//! configuration file, then the journal's own `juntakami.conf`, then the
+//! environment.  See [`layers`] for how they are combined.  The global
+//! configuration can also name journals, see [`profiles`].
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use eyre::{bail, Context, Result};
+use eyre::{bail, eyre, Context, Result};
+use pulldown_cmark_ast::RenderOptions;
 use serde::{Deserialize, Serialize};
 use time::format_description::OwnedFormatItem;
+use toml_edit::{DocumentMut, Item};
 
-#[derive(Default, Serialize, Deserialize)]
+use crate::vcs::VcsKind;
+
+pub mod layers;
+pub mod migrate;
+pub mod profiles;
+
+use layers::{Layer, Layers};
+use migrate::CURRENT_VERSION;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 struct RawConfiguration {
+    /// The version of the format, see [`migrate`]
+    version: i64,
     juntakami: RawDefaults,
     log_meta: RawLogMeta,
+    render: RawRender,
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 struct RawDefaults {
     log_pattern: String,
-    list_char: char,
     editor: Vec<String>,
+    vcs: VcsKind,
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 struct RawLogMeta {
     title: String,
     created: String,
     author: String,
 }
 
+/// The markdown style entries are written back out in
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct RawRender {
+    /// Bullets for each level of nested list
+    list_chars: Vec<char>,
+    emphasis: char,
+    strong: String,
+    fence: char,
+    ordered_numbering: OrderedNumbering,
+    ordered_delimiter: char,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wrap_width: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OrderedNumbering {
+    /// 1. 2. 3.
+    Increment,
+    /// 1. 1. 1.
+    Repeat,
+}
+
 pub const CONFIG_FILENAME: &str = "juntakami.conf";
 pub const JOURNAL_BASE: &str = "@JOURNAL@";
 pub const JOURNAL_ENTRY: &str = "@ENTRY@";
@@ -34,11 +80,36 @@ impl Default for RawDefaults {
     fn default() -> Self {
         Self {
             log_pattern: "log/[year]-[month]-[day].md".into(),
-            list_char: '-',
             editor: ["code", JOURNAL_BASE, JOURNAL_ENTRY]
                 .into_iter()
                 .map(String::from)
                 .collect(),
+            vcs: VcsKind::default(),
+        }
+    }
+}
+
+impl Default for RawConfiguration {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            juntakami: RawDefaults::default(),
+            log_meta: RawLogMeta::default(),
+            render: RawRender::default(),
+        }
+    }
+}
+
+impl Default for RawRender {
+    fn default() -> Self {
+        Self {
+            list_chars: vec!['-'],
+            emphasis: '_',
+            strong: "**".into(),
+            fence: '`',
+            ordered_numbering: OrderedNumbering::Increment,
+            ordered_delimiter: '.',
+            wrap_width: None,
         }
     }
 }
@@ -66,15 +137,22 @@ struct ParsedLogMeta {
 pub struct Configuration {
     raw: RawConfiguration,
     parsed: ParsedConfiguration,
+    layers: Layers,
+    /// Just what the journal's own configuration file says
+    journal: toml::Table,
+    /// What the journal's configuration was layered with, so that another
+    /// version of it can be checked the same way
+    global: Option<PathBuf>,
+    env: Vec<(String, String)>,
 }
 
 impl ParsedLogMeta {
-    fn parse(path: &Path, raw: &RawLogMeta) -> Result<Self> {
+    fn parse(layers: &Layers, raw: &RawLogMeta) -> Result<Self> {
         let title = time::format_description::parse_owned::<2>(&raw.title).with_context(|| {
             format!(
                 "Trying to parse log_meta.title `{}` from {}",
                 raw.title,
-                path.display()
+                layers.describe("log_meta.title")
             )
         })?;
         let created =
@@ -82,7 +160,7 @@ impl ParsedLogMeta {
                 format!(
                     "Trying to parse log_meta.created `{}` from {}",
                     raw.created,
-                    path.display()
+                    layers.describe("log_meta.created")
                 )
             })?;
         Ok(Self { title, created })
@@ -90,16 +168,16 @@ impl ParsedLogMeta {
 }
 
 impl ParsedConfiguration {
-    fn parse(path: &Path, raw: &RawConfiguration) -> Result<Self> {
+    fn parse(layers: &Layers, raw: &RawConfiguration) -> Result<Self> {
         let log_pattern = time::format_description::parse_owned::<2>(&raw.juntakami.log_pattern)
             .with_context(|| {
                 format!(
                     "Trying to parse log_pattern `{}` from {}",
                     raw.juntakami.log_pattern,
-                    path.display()
+                    layers.describe("juntakami.log_pattern")
                 )
             })?;
-        let log_meta = ParsedLogMeta::parse(path, &raw.log_meta)?;
+        let log_meta = ParsedLogMeta::parse(layers, &raw.log_meta)?;
         Ok(Self {
             log_pattern,
             log_meta,
@@ -107,11 +185,19 @@ impl ParsedConfiguration {
     }
 }
 
+/// The built-in defaults, as the bottom layer of configuration
+fn defaults() -> toml::Table {
+    match toml::Value::try_from(RawConfiguration::default()) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => unreachable!("Default configuration always serialises to a table"),
+    }
+}
+
 impl Default for Configuration {
     fn default() -> Self {
-        let raw = RawConfiguration::default();
-        let parsed = ParsedConfiguration::parse(Path::new(""), &raw).unwrap();
-        Self { raw, parsed }
+        let mut journal = toml::Table::new();
+        journal.insert("version".into(), CURRENT_VERSION.into());
+        Self::from_layers(Layers::new(defaults()), journal).unwrap()
     }
 }
 
@@ -121,9 +207,53 @@ impl Configuration {
         &self.parsed.log_pattern
     }
 
-    /// The character to use for unordered lists
-    pub fn list_char(&self) -> char {
-        self.raw.juntakami.list_char
+    /// A glob matching every log filename, e.g. `log/*-*-*.md`
+    pub fn log_glob(&self) -> String {
+        let mut ret = String::new();
+        let mut depth = 0;
+        for c in self.raw.juntakami.log_pattern.chars() {
+            match c {
+                '[' => {
+                    if depth == 0 {
+                        ret.push('*');
+                    }
+                    depth += 1;
+                }
+                ']' if depth > 0 => depth -= 1,
+                _ if depth == 0 => ret.push(c),
+                _ => {}
+            }
+        }
+        ret
+    }
+
+    /// Which version control the journal is kept in
+    pub fn vcs(&self) -> VcsKind {
+        self.raw.juntakami.vcs
+    }
+
+    pub fn set_vcs(&mut self, vcs: VcsKind) {
+        self.raw.juntakami.vcs = vcs;
+        self.journal
+            .entry("juntakami")
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .unwrap()
+            .insert("vcs".into(), toml::Value::try_from(vcs).unwrap());
+    }
+
+    /// How to write markdown back out
+    pub fn render_options(&self) -> RenderOptions<'_> {
+        let render = &self.raw.render;
+        RenderOptions {
+            list_tokens: render.list_chars.clone(),
+            ordered_list_token: render.ordered_delimiter,
+            increment_ordered_list_bullets: render.ordered_numbering == OrderedNumbering::Increment,
+            emphasis_token: render.emphasis,
+            strong_token: &render.strong,
+            code_block_token: render.fence,
+            wrap_width: render.wrap_width,
+        }
     }
 
     /// The title to give to new log entries
@@ -141,49 +271,377 @@ impl Configuration {
         &self.raw.log_meta.author
     }
 
-    /// Get the raw config text
+    /// Get the effective config text, with every layer merged
     pub fn raw_config(&self) -> Result<String> {
         toml::to_string(&self.raw).context("Serialising configuration")
     }
 
+    /// The effective config, with each value commented with where it came from
+    pub fn annotated_config(&self) -> String {
+        let mut ret = String::new();
+        for file in self.layers.files() {
+            ret.push_str(&format!("# Read {}\n", file.display()));
+        }
+        ret.push_str(&self.layers.annotated());
+        ret
+    }
+
     /// The editor to run for editing journal entries
     pub fn editor(&self) -> &[impl AsRef<str>] {
         &self.raw.juntakami.editor
     }
 
-    /// Write to disk
+    /// Write the journal's own configuration to disk
+    ///
+    /// Anything left to the defaults or set globally isn't written.
     pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
         let path = path.as_ref();
-        let toml = self.raw_config()?;
+        let toml = toml::to_string(&self.journal).context("Serialising configuration")?;
         std::fs::write(path, &toml)
             .with_context(|| format!("Attempting to save configuration to {}", path.display()))
     }
 
-    /// Read from disk
+    /// Read the journal configuration at `path` from disk, along with the
+    /// global configuration and the environment
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::load_with(path, layers::global_path().as_deref(), std::env::vars())
+    }
+
+    /// As [`Configuration::load`], with the global configuration at
+    /// `global`, if it exists, and `env` in place of the environment
+    pub fn load_with(
+        path: impl AsRef<Path>,
+        global: Option<&Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("Attempting to read configuration from {}", path.display()))?;
+        Self::load_str(path, &toml, global, env)
+    }
+
+    /// As [`Configuration::load_with`], with `toml` as the content of the
+    /// journal configuration
+    fn load_str(
+        path: &Path,
+        toml: &str,
+        global: Option<&Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self> {
+        let mut layers = Layers::new(defaults());
+        if let Some(global) = global {
+            if std::fs::exists(global).with_context(|| {
+                format!("Attempting to detect existence of {}", global.display())
+            })? {
+                layers.add_file(Layer::Global, global)?;
+            }
+        }
+        let journal = layers.add_str(Layer::Journal, path, toml)?;
+        let env = env
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(layers::ENV_PREFIX))
+            .collect::<Vec<_>>();
+        layers.add_env(env.iter().cloned());
+        for key in profiles::KEYS {
+            layers.remove(key);
+        }
+        let mut config = Self::from_layers(layers, journal)?;
+        config.global = global.map(Path::to_path_buf);
+        config.env = env;
+        Ok(config)
+    }
+
+    /// The configuration there would be with `toml` in place of the
+    /// journal's configuration, which is at `path`
+    pub fn with_journal(&self, path: &Path, toml: &str) -> Result<Self> {
+        Self::load_str(path, toml, self.global.as_deref(), self.env.clone())
+    }
+
+    /// The effective value of a setting, by its dotted key
+    pub fn get(&self, key: &str) -> Option<&toml::Value> {
+        let mut parts = key.split('.');
+        let mut value = self.layers.merged().get(parts.next()?)?;
+        for part in parts {
+            value = value.as_table()?.get(part)?;
+        }
+        Some(value)
+    }
+
+    /// Bring the configuration file at `path` up to date, keeping its
+    /// comments and layout, and returning a description of each step taken
+    pub fn migrate(path: impl AsRef<Path>) -> Result<Vec<&'static str>> {
         let path = path.as_ref();
         let toml = std::fs::read_to_string(path)
             .with_context(|| format!("Attempting to read configuration from {}", path.display()))?;
-        let raw: RawConfiguration = toml::from_str(&toml)
+        let mut doc: DocumentMut = toml
+            .parse()
             .with_context(|| format!("Parsing contents of {}", path.display()))?;
-        let parsed = ParsedConfiguration::parse(path, &raw)?;
+        let steps = migrate::migrate(&mut doc, path)?;
+        if !steps.is_empty() {
+            std::fs::write(path, doc.to_string()).with_context(|| {
+                format!("Attempting to save configuration to {}", path.display())
+            })?;
+        }
+        Ok(steps)
+    }
+
+    /// Change the journal configuration at `path`, keeping its comments
+    /// and layout
+    ///
+    /// The result is checked as [`Configuration::load`] would check it, and
+    /// the file is only written if it passes.
+    pub fn edit(
+        path: impl AsRef<Path>,
+        f: impl FnOnce(&mut DocumentMut) -> Result<()>,
+    ) -> Result<()> {
+        Self::edit_with(path, layers::global_path().as_deref(), std::env::vars(), f)
+    }
+
+    /// As [`Configuration::edit`], checked as [`Configuration::load_with`]
+    /// would check it
+    fn edit_with(
+        path: impl AsRef<Path>,
+        global: Option<&Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+        f: impl FnOnce(&mut DocumentMut) -> Result<()>,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("Attempting to read configuration from {}", path.display()))?;
+        let mut doc: DocumentMut = toml
+            .parse()
+            .with_context(|| format!("Parsing contents of {}", path.display()))?;
+        f(&mut doc)?;
+        let toml = doc.to_string();
+        Self::load_str(path, &toml, global, env)
+            .context("The configuration would no longer be valid")?;
+        std::fs::write(path, &toml)
+            .with_context(|| format!("Attempting to save configuration to {}", path.display()))
+    }
+
+    fn from_layers(layers: Layers, journal: toml::Table) -> Result<Self> {
+        let files = layers
+            .files()
+            .map(|f| f.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let raw: RawConfiguration = toml::Value::Table(layers.merged().clone())
+            .try_into()
+            .with_context(|| format!("Reading configuration from {files}"))?;
+        let parsed = ParsedConfiguration::parse(&layers, &raw)?;
         // Some sanity checks...
+        let editor_source = layers.describe("juntakami.editor");
         if raw.juntakami.editor.is_empty() {
-            bail!("Editor is empty in {}", path.display());
+            bail!("Editor is empty in {editor_source}");
         }
         if raw.juntakami.editor.iter().all(|v| v != JOURNAL_ENTRY) {
-            bail!(
-                "Editor command does not include `{JOURNAL_ENTRY}` anywhere, in {}",
-                path.display()
-            );
+            bail!("Editor command does not include `{JOURNAL_ENTRY}` anywhere, in {editor_source}");
         }
         if raw.juntakami.editor[0].starts_with('@') {
             bail!(
-                "Editor command cannot start with substitution variable `{}`, in {}",
+                "Editor command cannot start with substitution variable `{}`, in {editor_source}",
                 raw.juntakami.editor[0],
-                path.display()
             );
         }
-        Ok(Self { raw, parsed })
+        // Only whole arguments are substituted, so anything else which looks
+        // like a variable is a mistake which would reach the editor as is
+        let known = [JOURNAL_BASE, JOURNAL_ENTRY];
+        for arg in &raw.juntakami.editor[1..] {
+            let misused = |var: &&str| !(var == arg && known.contains(var));
+            if let Some(var) = substitution_variables(arg).find(misused) {
+                if known.contains(&var) {
+                    bail!(
+                        "Editor argument `{arg}` must be `{var}` alone for it to be substituted, in {editor_source}"
+                    );
+                }
+                bail!(
+                    "Editor argument `{arg}` has unknown substitution variable `{var}`, only `{JOURNAL_BASE}` and `{JOURNAL_ENTRY}` are known, in {editor_source}"
+                );
+            }
+        }
+        let config = Self {
+            raw,
+            parsed,
+            layers,
+            journal,
+            global: None,
+            env: Vec::new(),
+        };
+        config
+            .render_options()
+            .validate()
+            .with_context(|| format!("Checking render settings from {files}"))?;
+        Ok(config)
+    }
+}
+
+/// Everything in `arg` which looks like a substitution variable, such as `@ENTRY@`
+fn substitution_variables(arg: &str) -> impl Iterator<Item = &str> {
+    let mut rest = arg;
+    std::iter::from_fn(move || loop {
+        let start = rest.find('@')?;
+        let len = rest[start + 1..].find('@')? + 2;
+        let candidate = &rest[start..start + len];
+        let name = &candidate[1..len - 1];
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            rest = &rest[start + len..];
+            return Some(candidate);
+        }
+        // The closing `@` may yet open a variable
+        rest = &rest[start + len - 1..];
+    })
+}
+
+/// Set a dotted key in a configuration document
+///
+/// The value is read as TOML if it can be, such as `72` or
+/// `["vim", "@ENTRY@"]`, and as a string otherwise.
+pub fn set_key(doc: &mut DocumentMut, key: &str, value: &str) -> Result<()> {
+    let mut value = match format!("value = {value}").parse::<DocumentMut>() {
+        Ok(parsed) => match parsed.get("value").and_then(Item::as_value) {
+            Some(parsed) => parsed.clone(),
+            None => value.into(),
+        },
+        Err(_) => value.into(),
+    };
+    let (tables, last) = split_key(key)?;
+    let mut table = doc.as_table_mut();
+    for part in tables {
+        table = table
+            .entry(part)
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .ok_or_else(|| eyre!("`{part}` in `{key}` is not a table"))?;
+    }
+    if let Some(old) = table.get(last).and_then(Item::as_value) {
+        // Keep any comment which was alongside the old value
+        *value.decor_mut() = old.decor().clone();
+    }
+    table.insert(last, Item::Value(value));
+    Ok(())
+}
+
+/// Remove a dotted key from a configuration document, and any table left
+/// empty by doing so, returning whether it was there
+pub fn unset_key(doc: &mut DocumentMut, key: &str) -> Result<bool> {
+    fn unset(table: &mut toml_edit::Table, tables: &[&str], last: &str) -> bool {
+        let Some((first, rest)) = tables.split_first() else {
+            return table.remove(last).is_some();
+        };
+        let Some(inner) = table.get_mut(first).and_then(Item::as_table_mut) else {
+            return false;
+        };
+        let removed = unset(inner, rest, last);
+        if inner.is_empty() {
+            table.remove(first);
+        }
+        removed
+    }
+    let (tables, last) = split_key(key)?;
+    Ok(unset(doc.as_table_mut(), &tables, last))
+}
+
+fn split_key(key: &str) -> Result<(Vec<&str>, &str)> {
+    let mut parts = key.split('.').collect::<Vec<_>>();
+    if parts.iter().any(|part| part.is_empty()) {
+        bail!("`{key}` is not a valid key, expected something like `juntakami.editor`");
+    }
+    let last = parts.pop().unwrap();
+    Ok((parts, last))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn partial_journal_config() {
+        let journal: toml::Table = toml::from_str("[log_meta]\nauthor = \"Someone\"\n").unwrap();
+        let mut layers = Layers::new(defaults());
+        layers.add(Layer::Journal, journal.clone());
+        let mut config = Configuration::from_layers(layers, journal).unwrap();
+        assert_eq!(config.author(), "Someone");
+        assert_eq!(config.log_glob(), "log/*-*-*.md");
+
+        config.set_vcs(VcsKind::None);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILENAME);
+        config.save(&path).unwrap();
+        insta::assert_snapshot!(std::fs::read_to_string(&path).unwrap(), @r###"
+        [juntakami]
+        vcs = "none"
+
+        [log_meta]
+        author = "Someone"
+        "###);
+    }
+
+    #[test]
+    fn editing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILENAME);
+        std::fs::write(
+            &path,
+            "# My journal\n[juntakami]\nvcs = \"none\" # No git here\n\n[log_meta]\nauthor = \"Someone\"\n",
+        )
+        .unwrap();
+        Configuration::edit_with(&path, None, [], |doc| {
+            set_key(doc, "juntakami.vcs", "git")?;
+            set_key(doc, "render.wrap_width", "72")?;
+            set_key(doc, "juntakami.editor", r#"["vim", "@ENTRY@"]"#)?;
+            unset_key(doc, "log_meta.author")?;
+            Ok(())
+        })
+        .unwrap();
+        insta::assert_snapshot!(std::fs::read_to_string(&path).unwrap(), @r###"
+        # My journal
+        [juntakami]
+        vcs = "git" # No git here
+        editor = ["vim", "@ENTRY@"]
+
+        [render]
+        wrap_width = 72
+        "###);
+        let config = Configuration::load_with(&path, None, []).unwrap();
+        assert_eq!(
+            config.get("render.wrap_width"),
+            Some(&toml::Value::Integer(72))
+        );
+
+        let err = Configuration::edit_with(&path, None, [], |doc| {
+            set_key(doc, "juntakami.editor", r#"["vim"]"#)
+        })
+        .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            format!(
+                "The configuration would no longer be valid: Editor command does not include `@ENTRY@` anywhere, in {}",
+                path.display()
+            )
+        );
+        assert!(set_key(&mut DocumentMut::new(), "juntakami..vcs", "git").is_err());
+    }
+
+    #[test]
+    fn editor_variables() {
+        let editor = |args: &str| {
+            let toml = format!("version = 2\n[juntakami]\neditor = {args}\n");
+            let mut layers = Layers::new(defaults());
+            let journal = layers
+                .add_str(Layer::Journal, Path::new("journal/juntakami.conf"), &toml)
+                .unwrap();
+            Configuration::from_layers(layers, journal).map(|_| ())
+        };
+        assert!(editor(r#"["code", "@JOURNAL@", "@ENTRY@", "me@example.com"]"#).is_ok());
+        assert!(editor(r#"["vim", "--cmd", "cd @JOURNAL@", "@ENTRY@"]"#).is_err());
+        insta::assert_snapshot!(editor(r#"["vim", "@JOURNAL_BASE@", "@ENTRY@"]"#).unwrap_err(), @r###"
+        Editor argument `@JOURNAL_BASE@` has unknown substitution variable `@JOURNAL_BASE@`, only `@JOURNAL@` and `@ENTRY@` are known, in journal/juntakami.conf
+        "###);
+        insta::assert_snapshot!(editor(r#"["vim", "+e @ENTRY@", "@ENTRY@"]"#).unwrap_err(), @r###"
+        Editor argument `+e @ENTRY@` must be `@ENTRY@` alone for it to be substituted, in journal/juntakami.conf
+        "###);
+        insta::assert_snapshot!(editor(r#"["vim", "@@ENTRY@", "@ENTRY@"]"#).unwrap_err(), @r###"
+        Editor argument `@@ENTRY@` must be `@ENTRY@` alone for it to be substituted, in journal/juntakami.conf
+        "###);
     }
 }
@@ -0,0 +1,259 @@
+//! Stacking configuration from several places
+//!
+//! Each layer is a TOML table which is merged over the ones beneath it, so
+//! a layer need only mention the values it changes.  Tables are merged key
+//! by key, while anything else, arrays included, is replaced outright.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, Result};
+use toml::{Table, Value};
+use toml_edit::DocumentMut;
+use tracing::warn;
+
+use super::migrate;
+
+/// Environment variables named `JUNTAKAMI_<SECTION>__<KEY>` set values
+pub const ENV_PREFIX: &str = "JUNTAKAMI_";
+
+/// Where configuration comes from, least important first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Default,
+    Global,
+    Journal,
+    Environment,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Layer::Default => "default",
+            Layer::Global => "global",
+            Layer::Journal => "journal",
+            Layer::Environment => "environment",
+        })
+    }
+}
+
+/// The global configuration file, `$XDG_CONFIG_HOME/juntakami/config.toml`
+pub fn global_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home::home_dir()?.join(".config"),
+    };
+    Some(base.join("juntakami").join("config.toml"))
+}
+
+pub struct Layers {
+    merged: Table,
+    /// The layer each value in `merged` came from, by dotted key
+    sources: BTreeMap<String, Layer>,
+    files: BTreeMap<Layer, PathBuf>,
+}
+
+impl Layers {
+    /// Start from the built-in defaults
+    pub fn new(defaults: Table) -> Self {
+        let mut ret = Self {
+            merged: Table::new(),
+            sources: BTreeMap::new(),
+            files: BTreeMap::new(),
+        };
+        ret.add(Layer::Default, defaults);
+        ret
+    }
+
+    /// Merge `table` over everything so far
+    pub fn add(&mut self, layer: Layer, table: Table) {
+        fn merge(
+            into: &mut Table,
+            from: Table,
+            prefix: &str,
+            layer: Layer,
+            sources: &mut BTreeMap<String, Layer>,
+        ) {
+            for (key, value) in from {
+                let dotted = format!("{prefix}{key}");
+                match value {
+                    Value::Table(from) => {
+                        let entry = into
+                            .entry(&key)
+                            .or_insert_with(|| Value::Table(Table::new()));
+                        if !entry.is_table() {
+                            *entry = Value::Table(Table::new());
+                        }
+                        let Value::Table(into) = entry else {
+                            unreachable!()
+                        };
+                        merge(into, from, &format!("{dotted}."), layer, sources);
+                    }
+                    value => {
+                        into.insert(key, value);
+                        sources.insert(dotted, layer);
+                    }
+                }
+            }
+        }
+        merge(&mut self.merged, table, "", layer, &mut self.sources);
+    }
+
+    /// Merge the TOML file at `path` over everything so far, returning
+    /// what was in it
+    pub fn add_file(&mut self, layer: Layer, path: &Path) -> Result<Table> {
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("Attempting to read configuration from {}", path.display()))?;
+        self.add_str(layer, path, &toml)
+    }
+
+    /// As [`Layers::add_file`], with the content of the file already read
+    ///
+    /// Files from older versions are upgraded as they're read.
+    pub fn add_str(&mut self, layer: Layer, path: &Path, toml: &str) -> Result<Table> {
+        let mut doc: DocumentMut = toml
+            .parse()
+            .with_context(|| format!("Parsing contents of {}", path.display()))?;
+        if !migrate::migrate(&mut doc, path)?.is_empty() {
+            let command = match layer {
+                Layer::Global => "jt config migrate --global",
+                _ => "jt config migrate",
+            };
+            warn!(
+                "{} was written by an older jt, run `{command}` to update it",
+                path.display()
+            );
+        }
+        let table: Table = toml::from_str(&doc.to_string())
+            .with_context(|| format!("Parsing contents of {}", path.display()))?;
+        self.add(layer, table.clone());
+        self.files.insert(layer, path.to_path_buf());
+        Ok(table)
+    }
+
+    /// Merge values from environment variables over everything so far
+    ///
+    /// Values are read as TOML if they can be, such as `72` or
+    /// `["vim", "@ENTRY@"]`, and as strings otherwise.
+    pub fn add_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) {
+        let mut table = Table::new();
+        for (name, value) in vars {
+            let Some((section, key)) = name
+                .strip_prefix(ENV_PREFIX)
+                .and_then(|rest| rest.split_once("__"))
+            else {
+                continue;
+            };
+            let value = match toml::from_str::<Table>(&format!("value = {value}")) {
+                Ok(mut parsed) => parsed.remove("value").unwrap(),
+                Err(_) => Value::String(value),
+            };
+            table
+                .entry(section.to_lowercase())
+                .or_insert_with(|| Value::Table(Table::new()))
+                .as_table_mut()
+                .unwrap()
+                .insert(key.to_lowercase(), value);
+        }
+        self.add(Layer::Environment, table);
+    }
+
+    /// Forget a top level key, and everything beneath it, from every layer
+    pub fn remove(&mut self, key: &str) {
+        self.merged.remove(key);
+        let prefix = format!("{key}.");
+        self.sources
+            .retain(|dotted, _| dotted != key && !dotted.starts_with(&prefix));
+    }
+
+    pub fn merged(&self) -> &Table {
+        &self.merged
+    }
+
+    /// The layer a value came from, by its dotted key
+    pub fn source(&self, key: &str) -> Layer {
+        self.sources.get(key).copied().unwrap_or(Layer::Default)
+    }
+
+    /// Where a value came from, for error messages
+    pub fn describe(&self, key: &str) -> String {
+        let layer = self.source(key);
+        match (layer, self.files.get(&layer)) {
+            (_, Some(path)) => path.display().to_string(),
+            (Layer::Environment, None) => "the environment".into(),
+            _ => "the built-in defaults".into(),
+        }
+    }
+
+    /// The files which were read, in order
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.values().map(PathBuf::as_path)
+    }
+
+    /// Every value as TOML, each commented with the layer it came from
+    pub fn annotated(&self) -> String {
+        fn walk(table: &Table, prefix: &str, layers: &Layers, ret: &mut String) {
+            for (key, value) in table {
+                if !value.is_table() {
+                    let layer = layers.source(&format!("{prefix}{key}"));
+                    ret.push_str(&format!("{key} = {value} # {layer}\n"));
+                }
+            }
+            for (key, value) in table {
+                if let Value::Table(inner) = value {
+                    let dotted = format!("{prefix}{key}");
+                    ret.push_str(&format!("\n[{dotted}]\n"));
+                    walk(inner, &format!("{dotted}."), layers, ret);
+                }
+            }
+        }
+        let mut ret = String::new();
+        walk(&self.merged, "", self, &mut ret);
+        ret.trim_start().to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn layering() {
+        let table = |toml: &str| toml::from_str::<Table>(toml).unwrap();
+        let mut layers = Layers::new(table(
+            "[juntakami]\nlog_pattern = \"[year].md\"\neditor = [\"code\", \"@ENTRY@\"]\n[log_meta]\nauthor = \"Someone\"\n",
+        ));
+        layers.add(
+            Layer::Global,
+            table("[juntakami]\neditor = [\"vim\", \"@ENTRY@\"]\n"),
+        );
+        layers.add(Layer::Journal, table("[render]\nwrap_width = 72\n"));
+        layers.add_env([
+            ("JUNTAKAMI_PATH".into(), "/elsewhere".into()),
+            ("JUNTAKAMI_LOG_META__AUTHOR".into(), "Someone Else".into()),
+            ("JUNTAKAMI_RENDER__WRAP_WIDTH".into(), "80".into()),
+        ]);
+        assert_eq!(layers.source("juntakami.log_pattern"), Layer::Default);
+        assert_eq!(
+            layers.describe("juntakami.log_pattern"),
+            "the built-in defaults"
+        );
+        assert_eq!(layers.describe("log_meta.author"), "the environment");
+        layers.add(Layer::Global, table("[journals.work]\npath = \"/work\"\n"));
+        layers.remove("journals");
+        insta::assert_snapshot!(layers.annotated(), @r###"
+        [juntakami]
+        editor = ["vim", "@ENTRY@"] # global
+        log_pattern = "[year].md" # default
+
+        [log_meta]
+        author = "Someone Else" # environment
+
+        [render]
+        wrap_width = 80 # environment
+        "###);
+    }
+}
@@ -0,0 +1,139 @@
+//! Upgrading configuration files written by older versions
+//!
+//! Every configuration file records the `version` of the format it was
+//! written in, with files from before versioning being version 1.  Older
+//! files are upgraded in memory whenever they're loaded, one version at a
+//! time, and `jt config migrate` writes the upgraded file back out.
+
+use std::path::Path;
+
+use eyre::{bail, Result};
+use toml_edit::{DocumentMut, Item};
+
+/// The version of the configuration format which this jt writes
+pub const CURRENT_VERSION: i64 = 2;
+
+/// A step from one version to the next
+struct Migration {
+    /// The version this step upgrades from
+    from: i64,
+    description: &'static str,
+    /// Returns whether anything needed changing
+    apply: fn(&mut DocumentMut) -> bool,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "moved juntakami.list_char to render.list_chars",
+    apply: list_char_to_render,
+}];
+
+fn list_char_to_render(doc: &mut DocumentMut) -> bool {
+    let Some(list_char) = doc
+        .get_mut("juntakami")
+        .and_then(Item::as_table_like_mut)
+        .and_then(|table| table.remove("list_char"))
+    else {
+        return false;
+    };
+    let render = doc
+        .entry("render")
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut();
+    if let Some(render) = render {
+        // An empty list used to mean falling back to list_char
+        let unset = render
+            .get("list_chars")
+            .and_then(Item::as_array)
+            .is_none_or(|chars| chars.is_empty());
+        if unset {
+            let mut chars = toml_edit::Array::new();
+            let mut decor = toml_edit::Decor::default();
+            if let Some(c) = list_char.as_value() {
+                chars.push(c.clone().decorated("", ""));
+                // Keep any comment which was alongside the old setting
+                decor = c.decor().clone();
+            }
+            let mut value = toml_edit::Value::Array(chars);
+            *value.decor_mut() = decor;
+            render.insert("list_chars", Item::Value(value));
+        }
+    }
+    true
+}
+
+/// The version a configuration file was written in
+pub fn version(doc: &DocumentMut, path: &Path) -> Result<i64> {
+    match doc.get("version") {
+        None => Ok(1),
+        Some(item) => match item.as_integer() {
+            Some(version) => Ok(version),
+            None => bail!("`version` in {} is not a number", path.display()),
+        },
+    }
+}
+
+/// Upgrade a configuration file to the current version, returning a
+/// description of each step which changed it
+///
+/// A file which needed no changes is left alone, `version` and all, so
+/// that one written by hand without a `version` isn't reported as old.
+pub fn migrate(doc: &mut DocumentMut, path: &Path) -> Result<Vec<&'static str>> {
+    let mut version = version(doc, path)?;
+    if version > CURRENT_VERSION {
+        bail!(
+            "{} is for version {version} of the configuration, but this jt only understands up to version {CURRENT_VERSION}; please upgrade jt",
+            path.display()
+        );
+    }
+    let mut ret = Vec::new();
+    for migration in MIGRATIONS {
+        if migration.from == version {
+            if (migration.apply)(doc) {
+                ret.push(migration.description);
+            }
+            version += 1;
+        }
+    }
+    if !ret.is_empty() {
+        doc.insert("version", toml_edit::value(version));
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn migrating() {
+        let path = Path::new("juntakami.conf");
+        let mut doc = "# My journal\n[juntakami]\nlist_char = '*' # Stars\nvcs = \"git\"\n[render]\nlist_chars = []\n"
+            .parse::<DocumentMut>()
+            .unwrap();
+        assert_eq!(version(&doc, path).unwrap(), 1);
+        let steps = migrate(&mut doc, path).unwrap();
+        assert_eq!(steps, ["moved juntakami.list_char to render.list_chars"]);
+        insta::assert_snapshot!(doc.to_string(), @r###"
+        version = 2
+        # My journal
+        [juntakami]
+        vcs = "git"
+        [render]
+        list_chars = ['*'] # Stars
+        "###);
+        assert!(migrate(&mut doc, path).unwrap().is_empty());
+
+        // Written by hand in the current format, without a version
+        let current = "[juntakami]\nvcs = \"git\"\n[render]\nlist_chars = ['*']\n";
+        let mut doc = current.parse::<DocumentMut>().unwrap();
+        assert!(migrate(&mut doc, path).unwrap().is_empty());
+        assert_eq!(doc.to_string(), current);
+
+        let mut doc = "version = 99\n".parse::<DocumentMut>().unwrap();
+        assert!(migrate(&mut doc, path)
+            .unwrap_err()
+            .to_string()
+            .contains("please upgrade jt"));
+    }
+}
@@ -0,0 +1,186 @@
+//! Named journals, set up in the global configuration
+//!
+//! ```toml
+//! version = 2
+//! default_journal = "work"
+//!
+//! [journals.work]
+//! path = "~/work/journal"
+//!
+//! [journals.personal]
+//! path = "~/journal"
+//! ```
+//!
+//! A leading `~` in a path is the home directory, and any other relative
+//! path is relative to the global configuration file.  These settings
+//! choose a journal rather than configuring one, so they are left out of
+//! each journal's configuration.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use eyre::{bail, Context, Result};
+use serde::Deserialize;
+
+use super::layers::global_path;
+
+/// The top level keys which belong to named journals
+pub const KEYS: &[&str] = &["default_journal", "journals"];
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RawProfiles {
+    default_journal: Option<String>,
+    journals: BTreeMap<String, RawProfile>,
+}
+
+#[derive(Deserialize)]
+struct RawProfile {
+    path: PathBuf,
+}
+
+/// The named journals, and which of them is used by default
+#[derive(Debug, Default)]
+pub struct Profiles {
+    /// Where they were read from, for error messages
+    source: Option<PathBuf>,
+    default: Option<String>,
+    journals: BTreeMap<String, PathBuf>,
+}
+
+impl Profiles {
+    /// Read the named journals from the global configuration, if there is any
+    pub fn load() -> Result<Self> {
+        let Some(path) = global_path() else {
+            return Ok(Self::default());
+        };
+        if !std::fs::exists(&path)
+            .with_context(|| format!("Attempting to detect existence of {}", path.display()))?
+        {
+            return Ok(Self::default());
+        }
+        let toml = std::fs::read_to_string(&path)
+            .with_context(|| format!("Attempting to read configuration from {}", path.display()))?;
+        Self::load_str(&path, &toml)
+    }
+
+    /// As [`Profiles::load`], with `toml` as the content of the global
+    /// configuration at `path`
+    pub fn load_str(path: &Path, toml: &str) -> Result<Self> {
+        let raw: RawProfiles = toml::from_str(toml)
+            .with_context(|| format!("Reading named journals from {}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let journals = raw
+            .journals
+            .into_iter()
+            .map(|(name, profile)| {
+                let path = match profile.path.strip_prefix("~") {
+                    Ok(rest) => match home::home_dir() {
+                        Some(home) => home.join(rest),
+                        None => profile.path,
+                    },
+                    Err(_) => dir.join(profile.path),
+                };
+                (name, path)
+            })
+            .collect::<BTreeMap<_, _>>();
+        Ok(Self {
+            source: Some(path.to_path_buf()),
+            default: raw.default_journal,
+            journals,
+        })
+    }
+
+    /// The path to the journal called `name`
+    pub fn get(&self, name: &str) -> Result<&Path> {
+        if let Some(path) = self.journals.get(name) {
+            return Ok(path);
+        }
+        let source = match &self.source {
+            Some(source) => source.display().to_string(),
+            None => "the global configuration".into(),
+        };
+        if self.journals.is_empty() {
+            bail!("There is no journal named `{name}`, as {source} names none");
+        }
+        let known = self
+            .journals
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        bail!("There is no journal named `{name}` in {source}, only {known}");
+    }
+
+    /// The name of the journal to use when none is given
+    ///
+    /// This needn't be one of the journals, see [`Profiles::problem`].
+    pub fn default_journal(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    /// What's wrong with the default journal, if anything
+    ///
+    /// This is only reported when it matters, so that a mistake here
+    /// doesn't get in the way of everything else.
+    pub fn problem(&self) -> Option<String> {
+        let default = self.default.as_deref()?;
+        if self.journals.contains_key(default) {
+            return None;
+        }
+        let source = match &self.source {
+            Some(source) => source.display().to_string(),
+            None => "the global configuration".into(),
+        };
+        Some(format!(
+            "default_journal `{default}` in {source} is not one of the journals there"
+        ))
+    }
+
+    /// Every named journal, by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.journals
+            .iter()
+            .map(|(name, path)| (name.as_str(), path.as_path()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn named_journals() {
+        let global = Path::new("/home/someone/.config/juntakami/config.toml");
+        let profiles = Profiles::load_str(
+            global,
+            "default_journal = \"work\"\n[journals.work]\npath = \"/srv/work\"\n[journals.notes]\npath = \"notes\"\n[render]\nwrap_width = 72\n",
+        )
+        .unwrap();
+        assert_eq!(profiles.default_journal(), Some("work"));
+        assert_eq!(profiles.get("work").unwrap(), Path::new("/srv/work"));
+        assert_eq!(
+            profiles.iter().collect::<Vec<_>>(),
+            [
+                ("notes", Path::new("/home/someone/.config/juntakami/notes")),
+                ("work", Path::new("/srv/work")),
+            ]
+        );
+        assert_eq!(
+            profiles.get("home").unwrap_err().to_string(),
+            "There is no journal named `home` in /home/someone/.config/juntakami/config.toml, only notes, work"
+        );
+
+        assert_eq!(profiles.problem(), None);
+
+        // A dangling default is kept, to be reported when it's needed
+        let profiles = Profiles::load_str(global, "default_journal = \"home\"\n").unwrap();
+        assert_eq!(profiles.default_journal(), Some("home"));
+        assert_eq!(
+            profiles.problem().unwrap(),
+            "default_journal `home` in /home/someone/.config/juntakami/config.toml is not one of the journals there"
+        );
+    }
+}
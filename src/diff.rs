@@ -0,0 +1,240 @@
+//! Describing the differences between journal entries
+//!
+//! The structural diff from [`pulldown_cmark_ast::diff`] is turned into
+//! a short human summary (used when preparing a new entry) and into a
+//! fuller listing for `jt diff`.
+
+use std::collections::HashMap;
+
+use pulldown_cmark_ast::{
+    diff::{Change, Diff, DiffNode},
+    section::{Section, Sections},
+    Block, Document, Inline, List, ListItem, Paragraph, RenderError, RenderOptions,
+};
+
+use crate::filters::progress;
+
+/// A one-line description of what changed, e.g. "3 tasks advanced, Notes section cleared"
+pub fn summarise(old: &Document, new: &Document, diff: &Diff<'_>) -> Vec<String> {
+    let (mut advanced, mut changed, mut vanished, mut added) = (0, 0, 0, 0);
+    for change in diff.iter() {
+        match change {
+            Change::Changed {
+                old: DiffNode::ListItem(old),
+                new: DiffNode::ListItem(new),
+                ..
+            } => {
+                if let (Some(before), Some(after)) = (task_marker(old), task_marker(new)) {
+                    // Carrying a task over ([.] to [-]) or reopening one
+                    // is a change, but not progress
+                    if progress(after) > progress(before) {
+                        advanced += 1;
+                    } else if before != after {
+                        changed += 1;
+                    }
+                }
+            }
+            Change::Removed {
+                node: DiffNode::ListItem(item),
+                ..
+            } if task_marker(item).is_some() => vanished += 1,
+            Change::Added {
+                node: DiffNode::ListItem(item),
+                ..
+            } if task_marker(item).is_some() => added += 1,
+            _ => {}
+        }
+    }
+
+    let mut ret = Vec::new();
+    let mut count = |n: usize, what: &str| {
+        if n > 0 {
+            let plural = if n == 1 { "task" } else { "tasks" };
+            ret.push(format!("{n} {plural} {what}"));
+        }
+    };
+    count(advanced, "advanced");
+    count(changed, "changed");
+    count(vanished, "vanished");
+    count(added, "added");
+
+    ret.extend(
+        cleared_sections(old, new)
+            .into_iter()
+            .map(|title| format!("{title} section cleared")),
+    );
+    ret
+}
+
+fn task_marker(item: &ListItem) -> Option<char> {
+    task_body(item).map(|(marker, _)| marker)
+}
+
+/// The marker of a task list item and the inlines which follow it
+fn task_body<'i, 'a>(item: &'i ListItem<'a>) -> Option<(char, &'i [Inline<'a>])> {
+    let body = match item {
+        ListItem::Inline(body) => body,
+        ListItem::Block(blocks) => match blocks.first() {
+            Some(Block::Paragraph(p)) => &p.body,
+            _ => return None,
+        },
+    };
+    match body.split_first() {
+        Some((Inline::TasklistMarker(marker), rest)) => Some((*marker, rest)),
+        _ => None,
+    }
+}
+
+/// The marker and plain text of a task list item, e.g. `('x', "Write the diff")`
+pub fn task(item: &ListItem) -> Option<(char, String)> {
+    let (marker, body) = task_body(item)?;
+    let paragraph = Document {
+        blocks: vec![Block::Paragraph(Paragraph {
+            body: body.to_vec(),
+        })],
+    };
+    Some((marker, paragraph.to_plain_text().trim().to_string()))
+}
+
+/// Titles of the sections which had content in `old` but have none in `new`
+fn cleared_sections(old: &Document, new: &Document) -> Vec<String> {
+    fn walk<'s>(
+        sections: &'s [Section],
+        path: &mut Vec<String>,
+        f: &mut dyn FnMut(&[String], &'s Section),
+    ) {
+        for section in sections {
+            path.push(section.title());
+            f(path, section);
+            walk(&section.children, path, f);
+            path.pop();
+        }
+    }
+
+    let old = Sections::from_blocks(old.blocks.clone());
+    let new = Sections::from_blocks(new.blocks.clone());
+    let mut emptied = HashMap::new();
+    walk(&new.sections, &mut Vec::new(), &mut |path, section| {
+        emptied.insert(path.to_vec(), section.body.is_empty());
+    });
+    let mut ret = Vec::new();
+    walk(&old.sections, &mut Vec::new(), &mut |path, section| {
+        if !section.body.is_empty() && emptied.get(path) == Some(&true) {
+            ret.push(section.title());
+        }
+    });
+    ret
+}
+
+/// A listing of every change, grouped by the section it happened in
+pub fn describe(diff: &Diff<'_>, options: &RenderOptions<'_>) -> Result<String, RenderError> {
+    let render = |node: &DiffNode<'_>| {
+        let block = match node {
+            DiffNode::Block(block) => (*block).clone(),
+            DiffNode::ListItem(item) => Block::List(List {
+                start: None,
+                items: vec![(*item).clone()],
+            }),
+        };
+        Document {
+            blocks: vec![block],
+        }
+        .render(options.clone())
+    };
+    let mut out = String::new();
+    let mut prefixed = |prefix: &str, text: String| {
+        for (n, line) in text.lines().enumerate() {
+            let prefix = if n == 0 { prefix } else { " " };
+            out.push_str(&format!("{prefix} {line}\n"));
+        }
+    };
+
+    let mut context = None;
+    for change in diff.iter() {
+        if context != Some(change.context()) {
+            context = Some(change.context());
+            let heading = if change.context().is_empty() {
+                "(top of entry)".to_string()
+            } else {
+                change.context().join(" > ")
+            };
+            prefixed("#", heading);
+        }
+        match change {
+            Change::Added { node, .. } => prefixed("+", render(node)?),
+            Change::Removed { node, .. } => prefixed("-", render(node)?),
+            Change::Changed { old, new, .. } => {
+                prefixed("~", render(old)?);
+                prefixed(">", render(new)?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use pulldown_cmark_ast::ParseOptions;
+
+    use super::*;
+
+    const BEFORE: &str = r###"
+# Actions {.todo}
+
+- [ ] Write the diff
+- [ ] Test the diff
+- [.] Something in progress
+- [.] Something carried over
+- [ ] Forget about this
+
+# Notes {.drop}
+
+Some notes
+"###;
+
+    const AFTER: &str = r###"
+# Actions {.todo}
+
+- [x] Write the diff
+- [x] Test the diff
+- [.] Something in progress
+- [-] Something carried over
+
+# Notes {.drop}
+"###;
+
+    #[test]
+    fn summary() {
+        let before = Document::parse(BEFORE, ParseOptions::all());
+        let after = Document::parse(AFTER, ParseOptions::all());
+        let diff = before.diff(&after);
+        assert_eq!(
+            summarise(&before, &after, &diff),
+            [
+                "2 tasks advanced",
+                "1 task changed",
+                "1 task vanished",
+                "Notes section cleared"
+            ]
+        );
+    }
+
+    #[test]
+    fn description() {
+        let before = Document::parse(BEFORE, ParseOptions::all());
+        let after = Document::parse(AFTER, ParseOptions::all());
+        let diff = before.diff(&after);
+        insta::assert_snapshot!(describe(&diff, &RenderOptions::default()).unwrap(), @r###"
+        # Actions
+        ~ * [ ] Write the diff
+        > * [x] Write the diff
+        ~ * [ ] Test the diff
+        > * [x] Test the diff
+        ~ * [.] Something carried over
+        > * [-] Something carried over
+        - * [ ] Forget about this
+        # Notes
+        - Some notes
+        "###);
+    }
+}
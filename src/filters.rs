@@ -1,139 +1,84 @@
 //! Filters for markdown trees
 
-use std::cmp::Ordering;
-
 use pulldown_cmark_ast::{
     fold::{self, fold_list, fold_list_item, MarkdownFold},
-    Block, BlockQuote, CowStr, Document, FootnoteDefinition, HeadingLevel, Inline, List, ListItem,
+    section::{Section, Sections},
+    Block, BlockQuote, Document, FootnoteDefinition, Inline, List, ListItem,
 };
 
-pub struct KeepDrop {
-    stack: Vec<KdMode>,
-}
+use crate::clock::CLOCK_CLASS;
 
-struct KdMode {
+pub struct KeepDrop {
     keeping: bool,
-    cur_keeping: bool,
-    cur_level: HeadingLevel,
-}
-
-impl KdMode {
-    fn new(keeping: bool) -> Self {
-        KdMode {
-            keeping,
-            cur_keeping: keeping,
-            cur_level: HeadingLevel::H1,
-        }
-    }
 }
 
 impl KeepDrop {
     pub fn new(keeping: bool) -> KeepDrop {
-        KeepDrop {
-            stack: vec![KdMode::new(keeping)],
-        }
+        KeepDrop { keeping }
     }
 
-    fn top(&mut self) -> &mut KdMode {
-        self.stack.last_mut().unwrap()
-    }
-
-    fn fold_blocklist(&mut self, blocks: Vec<Block>) -> Vec<Block> {
-        const KEEP: CowStr<'static> = CowStr::Borrowed("keep");
-        const DROP: CowStr<'static> = CowStr::Borrowed("drop");
+    fn fold_blocklist<'a>(&mut self, blocks: Vec<Block<'a>>, keeping: bool) -> Vec<Block<'a>> {
+        let sections = Sections::from_blocks(blocks);
         let mut ret = Vec::new();
-        for block in blocks {
-            let currently_keeping = self.top().cur_keeping;
-            if let Block::Heading(heading) = block {
-                let cur_level = self.top().cur_level;
-                let heading_keeps = heading.classes.contains(&KEEP);
-                let heading_drops = heading.classes.contains(&DROP);
-                match heading.level.cmp(&cur_level) {
-                    Ordering::Greater => {
-                        // Deeper, so if we're currently keeping we should check
-                        // if this tells us to drop we should push a new drop context
-                        if currently_keeping && heading_drops {
-                            self.stack.push(KdMode {
-                                keeping: false,
-                                cur_keeping: false,
-                                cur_level: heading.level,
-                            });
-                        } else {
-                            // Wasn't a drop, so include this if we're keeping
-                            if currently_keeping {
-                                ret.push(Block::Heading(heading));
-                            }
-                        }
-                    }
-                    Ordering::Equal => {
-                        // Same depth, flip back to "default" and then check for keep/drop
-                        self.top().cur_keeping =
-                            self.top().keeping && !heading_drops || heading_keeps;
-                        if self.top().cur_keeping {
-                            ret.push(Block::Heading(heading))
-                        }
-                    }
-                    Ordering::Less => {
-                        // Less deep, so we need to pop a level
-                        self.stack.pop();
-                    }
-                }
-            } else if currently_keeping {
-                ret.push(self.fold_block(block));
+        if keeping {
+            ret.extend(fold::fold_blocks(self, sections.preamble));
+        }
+        for section in sections.sections {
+            // Top level sections follow the default unless told otherwise
+            if section.has_class("keep") || keeping && !section.has_class("drop") {
+                self.keep_section(section, &mut ret);
             }
         }
         ret
     }
 
-    fn fold_blocklist_push(&mut self, blocks: Vec<Block>) -> Vec<Block> {
-        eprintln!("{blocks:?}");
-        self.stack.push(KdMode {
-            keeping: true,
-            cur_keeping: true,
-            cur_level: HeadingLevel::H1,
-        });
-        let ret = self.fold_blocklist(blocks);
-        self.stack.pop();
-        ret
+    fn keep_section<'a>(&mut self, section: Section<'a>, ret: &mut Vec<Block<'a>>) {
+        ret.push(Block::Heading(section.heading));
+        ret.extend(fold::fold_blocks(self, section.body));
+        for child in section.children {
+            // Within a kept section, only explicit drops are dropped
+            if !child.has_class("drop") {
+                self.keep_section(child, ret);
+            }
+        }
     }
 }
 
-impl fold::MarkdownFold for KeepDrop {
+impl<'a> fold::MarkdownFold<'a> for KeepDrop {
     // We only need to fold wherever there a block lists
 
-    fn fold_document(&mut self, document: Document) -> Document {
+    fn fold_document(&mut self, document: Document<'a>) -> Document<'a> {
         Document {
-            blocks: self.fold_blocklist(document.blocks),
+            blocks: self.fold_blocklist(document.blocks, self.keeping),
         }
     }
 
-    fn fold_block_quote(&mut self, block_quote: BlockQuote) -> BlockQuote {
+    fn fold_block_quote(&mut self, block_quote: BlockQuote<'a>) -> BlockQuote<'a> {
         BlockQuote {
             kind: block_quote.kind,
-            body: self.fold_blocklist_push(block_quote.body),
+            body: self.fold_blocklist(block_quote.body, true),
         }
     }
 
     fn fold_footnote_definition(
         &mut self,
-        footnote_definition: FootnoteDefinition,
-    ) -> FootnoteDefinition {
+        footnote_definition: FootnoteDefinition<'a>,
+    ) -> FootnoteDefinition<'a> {
         FootnoteDefinition {
             label: footnote_definition.label,
-            body: self.fold_blocklist_push(footnote_definition.body),
+            body: self.fold_blocklist(footnote_definition.body, true),
         }
     }
 
-    fn fold_list_item(&mut self, list_item: ListItem) -> ListItem {
+    fn fold_list_item(&mut self, list_item: ListItem<'a>) -> ListItem<'a> {
         match list_item {
             ListItem::Inline(vec) => fold_list_item(self, ListItem::Inline(vec)),
-            ListItem::Block(vec) => ListItem::Block(self.fold_blocklist_push(vec)),
+            ListItem::Block(vec) => ListItem::Block(self.fold_blocklist(vec, true)),
         }
     }
 }
 
 pub struct TodoFilter {
-    level: HeadingLevel,
     processing: bool,
 }
 
@@ -167,8 +112,30 @@ impl ItemKind {
         }
     }
 
-    fn implicit_space(self) -> bool {
-        matches!(self, ItemKind::Unticked | ItemKind::Complete)
+    /// How far along a task is, from not started to finished with
+    fn progress(self) -> u8 {
+        match self {
+            ItemKind::PassThru | ItemKind::Unticked | ItemKind::WasPartial => 0,
+            ItemKind::Pausing | ItemKind::Paused => 1,
+            ItemKind::Partial => 2,
+            ItemKind::Dropping | ItemKind::Dropped => 3,
+            ItemKind::Complete | ItemKind::WasComplete => 4,
+        }
+    }
+
+    fn marker(self) -> char {
+        match self {
+            ItemKind::PassThru => unreachable!(),
+            ItemKind::Unticked => ' ',
+            ItemKind::Partial => '.',
+            ItemKind::WasPartial => '-',
+            ItemKind::Complete => 'x',
+            ItemKind::WasComplete => 'F',
+            ItemKind::Dropping => 'd',
+            ItemKind::Dropped => 'D',
+            ItemKind::Pausing => 'p',
+            ItemKind::Paused => 'P',
+        }
     }
 }
 
@@ -178,7 +145,7 @@ impl From<char> for ItemKind {
             ' ' => Self::Unticked,
             '.' => Self::Partial,
             '-' => Self::WasPartial,
-            'x' => Self::Complete,
+            'x' | 'X' => Self::Complete,
             'F' => Self::WasComplete,
             'd' => Self::Dropping,
             'D' => Self::Dropped,
@@ -189,75 +156,29 @@ impl From<char> for ItemKind {
     }
 }
 
-impl TodoFilter {
-    const OPEN_SQUARE: CowStr<'static> = CowStr::Borrowed("[");
-    const CLOSE_SQUARE: CowStr<'static> = CowStr::Borrowed("]");
+/// Whether the todo processing knows what to do with a task marker
+pub fn known_marker(marker: char) -> bool {
+    !matches!(ItemKind::from(marker), ItemKind::PassThru)
+}
 
+/// How far along a task is, so that the more advanced of two can be kept
+pub fn progress(marker: char) -> u8 {
+    ItemKind::from(marker).progress()
+}
+
+impl TodoFilter {
     pub fn new() -> TodoFilter {
-        TodoFilter {
-            level: HeadingLevel::H1,
-            processing: false,
-        }
+        TodoFilter { processing: false }
     }
 
-    fn snaffle(&mut self, bits: &mut Vec<Inline>) -> ItemKind {
-        if bits.is_empty() {
-            return ItemKind::PassThru;
-        }
-
-        let kind = match bits.remove(0) {
-            Inline::TasklistMarker(ticked) => {
-                if ticked {
-                    ItemKind::Complete
-                } else {
-                    ItemKind::Unticked
-                }
-            }
-            Inline::Text(t) if t == Self::OPEN_SQUARE => {
-                // OK, we had an open square, if there is a close square
-                // we look deeper
-                if let Some(Inline::Text(t)) = bits.get(1).cloned() {
-                    if t == Self::CLOSE_SQUARE {
-                        // Okay it's a close, finally we want text in the first position which
-                        // is a single character
-                        if let Some(Inline::Text(c)) = bits.first().cloned() {
-                            if c.len() != 1 {
-                                // Not one character
-                                bits.insert(0, Inline::Text(Self::OPEN_SQUARE));
-                                ItemKind::PassThru
-                            } else {
-                                let kind = ItemKind::from(c.chars().next().unwrap());
-                                if matches!(kind, ItemKind::PassThru) {
-                                    bits.insert(0, Inline::Text(Self::OPEN_SQUARE));
-                                } else {
-                                    // Remove c
-                                    bits.remove(0);
-                                    // remove ]
-                                    bits.remove(0);
-                                }
-                                kind
-                            }
-                        } else {
-                            // Not text
-                            bits.insert(0, Inline::Text(Self::OPEN_SQUARE));
-                            ItemKind::PassThru
-                        }
-                    } else {
-                        // For whatever reason, the open isn't matched right
-                        bits.insert(0, Inline::Text(Self::OPEN_SQUARE));
-                        ItemKind::PassThru
-                    }
-                } else {
-                    // Not even text
-                    bits.insert(0, Inline::Text(Self::OPEN_SQUARE));
-                    ItemKind::PassThru
-                }
-            }
-            other => {
-                bits.insert(0, other);
-                ItemKind::PassThru
-            }
+    fn snaffle<'a>(&mut self, bits: &mut Vec<Inline<'a>>) -> ItemKind {
+        let kind = match bits.first() {
+            Some(Inline::TasklistMarker(marker)) => ItemKind::from(*marker),
+            _ => ItemKind::PassThru,
         };
+        if !matches!(kind, ItemKind::PassThru) {
+            bits.remove(0);
+        }
 
         // Now fold the rest of the list
         let new = bits.drain(..).map(|i| self.fold_inline(i)).collect();
@@ -266,7 +187,7 @@ impl TodoFilter {
         kind
     }
 
-    fn snaffle_block(&mut self, bits: &mut Vec<Block>) -> ItemKind {
+    fn snaffle_block<'a>(&mut self, bits: &mut Vec<Block<'a>>) -> ItemKind {
         if bits.is_empty() {
             return ItemKind::PassThru;
         }
@@ -283,53 +204,18 @@ impl TodoFilter {
         ret
     }
 
-    fn insert_kind(bits: &mut Vec<Inline>, old_kind: ItemKind) {
-        let kind = old_kind.cycle();
-        match (old_kind.implicit_space(), kind.implicit_space()) {
-            (true, false) => {
-                // We lose an implicit space, shove one in
-                bits.insert(0, Inline::Text(CowStr::Borrowed(" ")));
-            }
-            (false, true) => {
-                // We gain an implicit space, try and remove the space from bits[0]
-                if let Some(Inline::Text(t)) = bits.get_mut(0) {
-                    if &t[0..=0] == " " {
-                        *t = CowStr::Boxed(t[1..].into());
-                    }
-                }
-            }
-            (true, true) | (false, false) => {
-                // Nothing to do, nothing changed
-            }
-        }
-        match kind {
-            ItemKind::Partial | ItemKind::Dropping | ItemKind::Pausing | ItemKind::PassThru => {
-                unreachable!()
-            }
-
-            ItemKind::Unticked => bits.insert(0, Inline::TasklistMarker(false)),
-            ItemKind::Complete => bits.insert(0, Inline::TasklistMarker(true)),
-            ItemKind::WasPartial => Self::insert_char(bits, '-'),
-            ItemKind::WasComplete => Self::insert_char(bits, 'F'),
-            ItemKind::Dropped => Self::insert_char(bits, 'D'),
-            ItemKind::Paused => Self::insert_char(bits, 'P'),
-        }
+    fn insert_kind<'a>(bits: &mut Vec<Inline<'a>>, old_kind: ItemKind) {
+        bits.insert(0, Inline::TasklistMarker(old_kind.cycle().marker()));
     }
 
-    fn insert_char(bits: &mut Vec<Inline>, ch: char) {
-        bits.insert(0, Inline::Text(Self::CLOSE_SQUARE));
-        bits.insert(0, Inline::Text(CowStr::from(format!("{ch}"))));
-        bits.insert(0, Inline::Text(Self::OPEN_SQUARE));
-    }
-
-    fn insert_kind_block(block: &mut Block, old_kind: ItemKind) {
+    fn insert_kind_block<'a>(block: &mut Block<'a>, old_kind: ItemKind) {
         let Block::Paragraph(p) = block else {
             unreachable!()
         };
         Self::insert_kind(&mut p.body, old_kind);
     }
 
-    fn adjust_item(&mut self, mut item: ListItem) -> Option<ListItem> {
+    fn adjust_item<'a>(&mut self, mut item: ListItem<'a>) -> Option<ListItem<'a>> {
         // Step one is to try and find out what this item even is.
         let kind = match &mut item {
             ListItem::Inline(vec) => self.snaffle(vec),
@@ -356,8 +242,8 @@ impl Default for TodoFilter {
     }
 }
 
-impl MarkdownFold for TodoFilter {
-    fn fold_list(&mut self, list: List) -> List {
+impl<'a> MarkdownFold<'a> for TodoFilter {
+    fn fold_list(&mut self, list: List<'a>) -> List<'a> {
         // We're folding the whole list because we might decide to not bother
         // with an item
         if self.processing {
@@ -374,34 +260,71 @@ impl MarkdownFold for TodoFilter {
         }
     }
 
-    fn fold_document(&mut self, document: Document) -> Document {
-        // Since we only care about the document level headers, this will do nicely
-        let mut blocks = Vec::new();
-        const TODO_CLASS: CowStr<'static> = CowStr::Borrowed("todo");
-
-        for block in document.blocks {
-            match block {
-                Block::Heading(h) => {
-                    if h.level <= self.level {
-                        self.level = h.level;
-                        self.processing = h.classes.contains(&TODO_CLASS);
-                    } else {
-                        // Deeper than 1, but switch to todo handling
-                        if h.classes.contains(&TODO_CLASS) && !self.processing {
-                            self.level = h.level;
-                            self.processing = true;
-                        }
-                    }
-                    blocks.push(Block::Heading(h));
-                }
-                _ => blocks.push(self.fold_block(block)),
-            }
+    fn fold_document(&mut self, document: Document<'a>) -> Document<'a> {
+        let sections = Sections::from_blocks(document.blocks);
+        self.processing = false;
+        let mut blocks = fold::fold_blocks(self, sections.preamble);
+        for section in sections.sections {
+            self.fold_section(section, false, &mut blocks);
         }
-
         Document { blocks }
     }
 }
 
+impl TodoFilter {
+    fn fold_section<'a>(
+        &mut self,
+        section: Section<'a>,
+        processing: bool,
+        blocks: &mut Vec<Block<'a>>,
+    ) {
+        // Once inside a todo section, everything beneath it is processed
+        let processing = processing || section.has_class("todo");
+        blocks.push(Block::Heading(section.heading));
+        self.processing = processing;
+        blocks.extend(fold::fold_blocks(self, section.body));
+        for child in section.children {
+            self.fold_section(child, processing, blocks);
+        }
+    }
+}
+
+/// Carries open clock intervals forward, dropping the closed ones
+///
+/// If nothing is left open, the clock section goes entirely.
+pub struct ClockFilter;
+
+impl<'a> MarkdownFold<'a> for ClockFilter {
+    fn fold_document(&mut self, document: Document<'a>) -> Document<'a> {
+        let mut sections = Sections::from_blocks(document.blocks);
+        let Some(path) = sections.path_of(|s| s.has_class(CLOCK_CLASS)) else {
+            return Document {
+                blocks: sections.into_blocks(),
+            };
+        };
+        let section = sections.get_mut(&path).unwrap();
+        let mut open = false;
+        for block in &mut section.body {
+            let Block::Table(table) = block else {
+                continue;
+            };
+            let Some(out) = table.column("Out") else {
+                continue;
+            };
+            table
+                .rows
+                .retain(|row| row.get(out).is_none_or(|cell| cell.text().is_empty()));
+            open |= !table.rows.is_empty();
+        }
+        if !open && section.children.is_empty() {
+            sections.remove(&path);
+        }
+        Document {
+            blocks: sections.into_blocks(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::path::Path;
@@ -412,7 +335,7 @@ mod test {
 
     use crate::{config::Configuration, filters::TodoFilter, markdown::MarkdownFile};
 
-    use super::KeepDrop;
+    use super::{ClockFilter, KeepDrop};
 
     const KEEP_DROP: &str = r###"
 
@@ -456,7 +379,9 @@ Maybe see this?
     fn keep_drop(#[case] keep: bool) {
         let md = Document::parse(KEEP_DROP, ParseOptions::all());
         let filtered = KeepDrop::new(keep).fold_document(md);
-        let rmd = filtered.render(pulldown_cmark_ast::RenderOptions::default());
+        let rmd = filtered
+            .render(pulldown_cmark_ast::RenderOptions::default())
+            .unwrap();
         assert_snapshot!(format!("keep_drop_{:?}", keep), rmd);
     }
 
@@ -507,7 +432,45 @@ Maybe see this?
     #[test]
     fn todo_processing() {
         let mut md = MarkdownFile::parse(Path::new(""), TODO).unwrap();
-        md.filter_markdown(TodoFilter::new(), &Configuration::default());
+        md.filter_markdown(TodoFilter::new(), &Configuration::default())
+            .unwrap();
         assert_snapshot!(md.markdown());
     }
+
+    const CLOCK: &str = r###"
+# Notes {.keep}
+
+# Clock {.keep .clock}
+
+|Project|In|Out|
+|-------|--|---|
+|juntakami|2024-12-02 09:00 +00:00|2024-12-02 10:30 +00:00|
+|support|2024-12-02 10:30 +00:00||
+"###;
+
+    #[test]
+    fn clock_carry_over() {
+        let md = Document::parse(CLOCK, ParseOptions::all());
+        let filtered = ClockFilter.fold_document(md);
+        let rmd = filtered
+            .render(pulldown_cmark_ast::RenderOptions::default())
+            .unwrap();
+        assert_snapshot!(rmd, @r###"
+        # Notes { .keep }
+
+        # Clock { .keep .clock }
+
+        |Project|In|Out|
+        |-------|--|---|
+        |support|2024-12-02 10:30 +00:00||
+        "###);
+
+        let closed = CLOCK.replace("+00:00||", "+00:00|2024-12-02 11:00 +00:00|");
+        let md = Document::parse(&closed, ParseOptions::all());
+        let filtered = ClockFilter.fold_document(md);
+        let rmd = filtered
+            .render(pulldown_cmark_ast::RenderOptions::default())
+            .unwrap();
+        assert_snapshot!(rmd, @"# Notes { .keep }");
+    }
 }
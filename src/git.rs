@@ -3,51 +3,48 @@
 use std::{
     ffi::OsStr,
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Output},
 };
 
-use eyre::{bail, Context, Result};
-use tracing::warn;
+use eyre::{bail, eyre, Context, Result};
+use time::OffsetDateTime;
+
+use crate::vcs::{FileStatus, Revision, Upstream, Vcs};
+
+const GITATTRIBUTES: &str = ".gitattributes";
 
 pub struct Git {
-    available: bool,
     base: PathBuf,
 }
 
 impl Git {
     pub fn new(base: impl AsRef<Path>) -> Self {
         let base = base.as_ref().to_path_buf();
-        let available = match Command::new("git")
-            .arg("--version")
-            .output()
-            .map(|o| o.status.success())
-        {
-            Ok(b) => b,
-            Err(e) => {
-                warn!("Unable to run git: {e}");
-                false
-            }
-        };
-        if !available {
-            warn!("All git operations will be stubbed");
-        }
-        Self { base, available }
+        Self { base }
     }
 
-    pub fn _git<I, S>(&self, args: I) -> Result<String>
+    fn output<I, S>(&self, args: I) -> Result<Output>
     where
         I: IntoIterator<Item = S>,
         S: AsRef<OsStr>,
     {
-        if !self.available {
-            return Ok(String::new());
-        }
         let mut cmd = Command::new("git");
         cmd.args(args);
         cmd.current_dir(&self.base);
-        let out = cmd
-            .output()
-            .with_context(|| format!("Running git with {:?}", cmd.get_args()))?;
+        cmd.output().with_context(|| {
+            format!(
+                "Running git with {:?}; if git isn't installed, set `vcs = \"none\"` in the journal configuration",
+                cmd.get_args()
+            )
+        })
+    }
+
+    pub fn _git<I, S>(&self, args: I) -> Result<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let out = self.output(args)?;
         if !out.status.success() {
             bail!(
                 "Failure running git: {}",
@@ -57,27 +54,209 @@ impl Git {
         Ok(String::from_utf8_lossy(&out.stdout).into_owned())
     }
 
-    pub fn init(&self) -> Result<()> {
+    /// Run git just to find out whether something is true
+    fn succeeds<I, S>(&self, args: I) -> Result<bool>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        Ok(self.output(args)?.status.success())
+    }
+
+    /// `rev:./path`, naming a file relative to the journal
+    fn object(revision: &str, path: &Path) -> String {
+        format!("{revision}:./{}", path.display())
+    }
+}
+
+impl Vcs for Git {
+    fn init(&self) -> Result<()> {
         self._git(["init"]).map(|_| ())
     }
 
-    pub fn add(&self, p: impl AsRef<Path>) -> Result<()> {
-        let p = p.as_ref().as_os_str();
-        self._git([OsStr::new("add"), p]).map(|_| ())
+    fn add(&self, p: &Path) -> Result<()> {
+        self._git([OsStr::new("add"), p.as_os_str()]).map(|_| ())
     }
 
-    pub fn status(&self) -> Result<Vec<(char, char, PathBuf)>> {
-        let all_stats = self._git(["status", "--porcelain=v1"])?;
-        Ok(all_stats
+    fn status(&self) -> Result<Vec<FileStatus>> {
+        Ok(parse_status(&self._git([
+            "status",
+            "--porcelain=v1",
+            "-z",
+        ])?))
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        self._git(["commit", "--quiet", "-m", message]).map(|_| ())
+    }
+
+    fn log(&self, path: Option<&Path>) -> Result<Vec<Revision>> {
+        // A fresh repository has no history, rather than an error
+        if !self.succeeds(["rev-parse", "--verify", "--quiet", "HEAD"])? {
+            return Ok(Vec::new());
+        }
+        let mut args = vec![OsStr::new("log"), OsStr::new("--format=%H%x00%ct%x00%s")];
+        if let Some(path) = path {
+            args.extend([OsStr::new("--"), path.as_os_str()]);
+        }
+        self._git(args)?
             .lines()
-            .map(|s| {
-                let mut c = s.chars();
-                let c1 = c.next().unwrap();
-                let c2 = c.next().unwrap();
-                c.next();
-                let s = c.collect::<String>();
-                (c1, c2, s.into())
+            .map(|line| {
+                let mut fields = line.splitn(3, '\0');
+                let (Some(id), Some(time), Some(summary)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    bail!("Unexpected output from git log: {line}");
+                };
+                let time = time
+                    .parse()
+                    .ok()
+                    .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+                    .ok_or_else(|| eyre!("Bad commit time in git log: {time}"))?;
+                Ok(Revision {
+                    id: id.to_string(),
+                    time,
+                    summary: summary.to_string(),
+                })
             })
-            .collect())
+            .collect()
+    }
+
+    fn show(&self, revision: &str, path: &Path) -> Result<Option<String>> {
+        let commit = format!("{revision}^{{commit}}");
+        if !self.succeeds(["rev-parse", "--verify", "--quiet", commit.as_str()])? {
+            bail!("Unknown revision {revision}");
+        }
+        let object = Self::object(revision, path);
+        if !self.succeeds(["cat-file", "-e", object.as_str()])? {
+            return Ok(None);
+        }
+        self._git(["show", object.as_str()]).map(Some)
+    }
+
+    fn staged(&self, path: &Path) -> Result<Option<String>> {
+        let object = format!(":./{}", path.display());
+        if !self.succeeds(["cat-file", "-e", object.as_str()])? {
+            return Ok(None);
+        }
+        self._git(["show", object.as_str()]).map(Some)
+    }
+
+    fn upstream(&self) -> Result<Option<Upstream>> {
+        let out = self.output([
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ])?;
+        if !out.status.success() {
+            return Ok(None);
+        }
+        let name = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        let counts = self._git(["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])?;
+        let mut fields = counts.split_whitespace().map(str::parse);
+        match (fields.next(), fields.next()) {
+            (Some(Ok(ahead)), Some(Ok(behind))) => Ok(Some(Upstream {
+                name,
+                ahead,
+                behind,
+            })),
+            _ => bail!("Unexpected output from git rev-list: {counts}"),
+        }
+    }
+
+    fn set_merge_driver(&self, pattern: &str, command: &str) -> Result<()> {
+        self._git([
+            "config",
+            "merge.juntakami.name",
+            "juntakami journal entries",
+        ])?;
+        self._git(["config", "merge.juntakami.driver", command])?;
+        let attributes = self.base.join(GITATTRIBUTES);
+        let mut content = match std::fs::read_to_string(&attributes) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", attributes.display())),
+        };
+        let line = format!("{pattern} merge=juntakami");
+        if !content.lines().any(|l| l.trim() == line) {
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push_str(&line);
+            content.push('\n');
+            std::fs::write(&attributes, content)
+                .with_context(|| format!("Writing {}", attributes.display()))?;
+        }
+        self.add(Path::new(GITATTRIBUTES))
+    }
+
+    fn hooks_dir(&self) -> Result<Option<PathBuf>> {
+        let dir = self._git(["rev-parse", "--git-path", "hooks"])?;
+        Ok(Some(self.base.join(dir.trim())))
+    }
+
+    fn merge_file(&self, ours: &Path, base: &Path, theirs: &Path) -> Result<bool> {
+        let out = self.output([
+            OsStr::new("merge-file"),
+            ours.as_os_str(),
+            base.as_os_str(),
+            theirs.as_os_str(),
+        ])?;
+        match out.status.code() {
+            Some(0) => Ok(false),
+            Some(n) if n > 0 => Ok(true),
+            _ => bail!(
+                "Failure running git merge-file: {}",
+                String::from_utf8_lossy(&out.stderr)
+            ),
+        }
+    }
+}
+
+/// Read `git status --porcelain=v1 -z`
+///
+/// Renamed and copied files are followed by the path they came from, which
+/// is skipped.
+fn parse_status(output: &str) -> Vec<FileStatus> {
+    let mut ret = Vec::new();
+    let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+    while let Some(entry) = entries.next() {
+        let mut c = entry.chars();
+        let (Some(index), Some(worktree)) = (c.next(), c.next()) else {
+            continue;
+        };
+        c.next();
+        if matches!(index, 'R' | 'C') {
+            entries.next();
+        }
+        ret.push(FileStatus {
+            index,
+            worktree,
+            path: c.as_str().into(),
+        });
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn statuses() {
+        let output = "M  log/2024-12-02.md\0R  log/2024-12-04.md\0log/2024-12-4.md\0?? notes with spaces.md\0";
+        let statuses = parse_status(output);
+        assert_eq!(
+            statuses
+                .iter()
+                .map(|s| (s.index, s.worktree, s.path.to_str().unwrap()))
+                .collect::<Vec<_>>(),
+            [
+                ('M', ' ', "log/2024-12-02.md"),
+                ('R', ' ', "log/2024-12-04.md"),
+                ('?', '?', "notes with spaces.md"),
+            ]
+        );
     }
 }
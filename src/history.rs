@@ -0,0 +1,189 @@
+//! The history of an entry, as recorded in version control
+//!
+//! Every committed version of an entry can be parsed, so the tasks in it
+//! can be followed from revision to revision.  Tasks are matched up by
+//! their text, so a task which is reworded looks like one task vanishing
+//! and another appearing.
+
+use std::{fmt, ops::ControlFlow, path::Path};
+
+use eyre::{Context, Result};
+use pulldown_cmark_ast::{visit::Visit, Document, ListItem};
+use time::{format_description::BorrowedFormatItem, macros::format_description, UtcOffset};
+
+use crate::{
+    diff::task,
+    markdown::MarkdownFile,
+    vcs::{Revision, Vcs},
+};
+
+const TIMESTAMP: &[BorrowedFormatItem<'static>] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]");
+
+/// A revision as one line of a listing, e.g. `2024-12-02 09:00 1a2b3c4 Daily commit`
+pub fn describe(revision: &Revision) -> Result<String> {
+    let time = revision
+        .time
+        .format(TIMESTAMP)
+        .with_context(|| format!("Formatting the time of {}", revision.id))?;
+    Ok(format!(
+        "{time} {} {}",
+        short(&revision.id),
+        revision.summary
+    ))
+}
+
+/// The abbreviated form of a revision id
+fn short(id: &str) -> &str {
+    id.get(..7).unwrap_or(id)
+}
+
+/// An entry as it was at a revision
+pub struct Version {
+    pub revision: Revision,
+    /// The entry, if it existed at that revision
+    pub entry: Option<Document<'static>>,
+}
+
+/// Every committed version of the entry at `path`, oldest first
+///
+/// Revision times are given in `offset`.
+pub fn versions(vcs: &dyn Vcs, path: &Path, offset: UtcOffset) -> Result<Vec<Version>> {
+    let mut ret = Vec::new();
+    for revision in vcs.log(Some(path))?.into_iter().rev() {
+        let entry = match vcs.show(&revision.id, path)? {
+            Some(body) => Some(
+                MarkdownFile::parse(path, &body)
+                    .with_context(|| format!("Reading the entry as of {}", revision.id))?
+                    .document()
+                    .into_static(),
+            ),
+            None => None,
+        };
+        ret.push(Version {
+            revision: Revision {
+                time: revision.time.to_offset(offset),
+                ..revision
+            },
+            entry,
+        });
+    }
+    Ok(ret)
+}
+
+/// How one task changed over the history of an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskHistory {
+    pub task: String,
+    /// Each revision at which the task's marker changed, and the new
+    /// marker, or `None` if the task was removed
+    pub changes: Vec<(Revision, Option<char>)>,
+}
+
+/// The tasks in an entry, in order, with their markers
+#[derive(Default)]
+struct Tasks(Vec<(String, char)>);
+
+impl<'ast> Visit<'ast> for Tasks {
+    fn visit_list_item(&mut self, list_item: &'ast ListItem<'ast>) -> ControlFlow<()> {
+        if let Some((marker, text)) = task(list_item) {
+            if !self.0.iter().any(|(seen, _)| *seen == text) {
+                self.0.push((text, marker));
+            }
+        }
+        pulldown_cmark_ast::visit::visit_list_item(self, list_item)
+    }
+}
+
+/// Replay the changes to each task across the versions of an entry
+///
+/// Tasks are listed in the order they first appeared.
+pub fn replay(versions: &[Version]) -> Vec<TaskHistory> {
+    let mut ret: Vec<TaskHistory> = Vec::new();
+    for version in versions {
+        let mut tasks = Tasks::default();
+        if let Some(entry) = &version.entry {
+            let _ = tasks.visit_document(entry);
+        }
+        let state = |text: &str| {
+            tasks
+                .0
+                .iter()
+                .find(|(task, _)| task == text)
+                .map(|(_, marker)| *marker)
+        };
+        for history in &mut ret {
+            let now = state(&history.task);
+            if history.changes.last().map(|(_, marker)| *marker) != Some(now) {
+                history.changes.push((version.revision.clone(), now));
+            }
+        }
+        for (text, marker) in &tasks.0 {
+            if !ret.iter().any(|history| history.task == *text) {
+                ret.push(TaskHistory {
+                    task: text.clone(),
+                    changes: vec![(version.revision.clone(), Some(*marker))],
+                });
+            }
+        }
+    }
+    ret
+}
+
+impl fmt::Display for TaskHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.task)?;
+        for (revision, marker) in &self.changes {
+            let time = revision.time.format(TIMESTAMP).map_err(|_| fmt::Error)?;
+            match marker {
+                Some(marker) => writeln!(f, "  {time} {} [{marker}]", short(&revision.id))?,
+                None => writeln!(f, "  {time} {} removed", short(&revision.id))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::vcs::fake::FakeVcs;
+
+    use super::*;
+
+    #[test]
+    fn task_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let vcs = FakeVcs::new(dir.path());
+        vcs.init().unwrap();
+        let path = Path::new("2024-12-02.md");
+        let commit = |body: &str| {
+            std::fs::write(dir.path().join(path), format!("+++\n+++\n{body}")).unwrap();
+            vcs.add(path).unwrap();
+            vcs.commit("Update").unwrap();
+        };
+        commit("# Actions\n\n- [ ] Write history\n- [ ] Forget this\n");
+        commit("# Actions\n\n- [.] Write history\n- [ ] Forget this\n");
+        commit("# Actions\n\n- [x] Write history\n- [ ] Test history\n");
+
+        let versions = versions(&vcs, path, UtcOffset::UTC).unwrap();
+        assert_eq!(
+            describe(&versions[0].revision).unwrap(),
+            "1970-01-01 00:01 r1 Update"
+        );
+        let replayed = replay(&versions)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<String>();
+        insta::assert_snapshot!(replayed, @r###"
+        Write history
+          1970-01-01 00:01 r1 [ ]
+          1970-01-01 00:02 r2 [.]
+          1970-01-01 00:03 r3 [x]
+        Forget this
+          1970-01-01 00:01 r1 [ ]
+          1970-01-01 00:03 r3 removed
+        Test history
+          1970-01-01 00:03 r3 [ ]
+        "###);
+    }
+}
@@ -6,16 +6,25 @@ use std::{
     process::Command,
 };
 
-use eyre::{bail, Context, Result};
-use time::{Date, OffsetDateTime};
+use eyre::{bail, eyre, Context, Result};
+use pulldown_cmark_ast::{section::Sections, Block};
+use time::{macros::format_description, Date, Duration, OffsetDateTime};
 use tracing::{info, warn};
 
 use crate::{
-    cli::InitArgs,
-    config::{Configuration, CONFIG_FILENAME, JOURNAL_BASE, JOURNAL_ENTRY},
-    filters::{KeepDrop, TodoFilter},
-    git::Git,
+    check::{self, Problem, PRE_COMMIT_HOOK},
+    cli::{InitArgs, OutputFormat},
+    clock::{self, hours},
+    config::{
+        self, profiles::Profiles, Configuration, CONFIG_FILENAME, JOURNAL_BASE, JOURNAL_ENTRY,
+    },
+    diff::{describe, summarise},
+    filters::{ClockFilter, KeepDrop, TodoFilter},
+    history,
     markdown::MarkdownFile,
+    merge,
+    status::{self, JournalStatus, NamedStatus, TaskCounts},
+    vcs::{self, Revision, Vcs},
 };
 
 pub struct NascentJournal {
@@ -24,9 +33,10 @@ pub struct NascentJournal {
 
 pub struct Journal {
     base: PathBuf,
-    git: Git,
+    vcs: Box<dyn Vcs>,
     config: Configuration,
 }
+
 impl NascentJournal {
     /// Construct a Journal
     pub fn new(path: impl AsRef<Path>) -> Self {
@@ -37,13 +47,46 @@ impl NascentJournal {
 
     /// Acquire the journal config
     pub fn load(self) -> Result<Journal> {
+        self.load_with(config::layers::global_path().as_deref(), std::env::vars())
+    }
+
+    /// As [`NascentJournal::load`], with the global configuration at
+    /// `global`, if it exists, and `env` in place of the environment
+    fn load_with(
+        self,
+        global: Option<&Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Journal> {
         let Self { base } = self;
-        let git = Git::new(&base);
-        let config = Configuration::load(base.join(CONFIG_FILENAME))?;
-        Ok(Journal { base, git, config })
+        let config = Configuration::load_with(base.join(CONFIG_FILENAME), global, env)?;
+        let vcs = vcs::open(config.vcs(), &base);
+        Ok(Journal { base, vcs, config })
+    }
+
+    pub fn config_set(&self, key: &str, value: &str) -> Result<()> {
+        Configuration::edit(self.base.join(CONFIG_FILENAME), |doc| {
+            config::set_key(doc, key, value)
+        })
+    }
+
+    pub fn config_unset(&self, key: &str) -> Result<()> {
+        Configuration::edit(self.base.join(CONFIG_FILENAME), |doc| {
+            if !config::unset_key(doc, key)? {
+                warn!("`{key}` is not set in the journal configuration");
+            }
+            Ok(())
+        })
+    }
+
+    pub fn config_migrate(&self) -> Result<()> {
+        migrate_config(&self.base.join(CONFIG_FILENAME))
     }
 
     pub fn initialise(&self, args: &InitArgs) -> Result<()> {
+        self.initialise_with(args, vcs::open(args.vcs(), &self.base).as_ref())
+    }
+
+    fn initialise_with(&self, args: &InitArgs, vcs: &dyn Vcs) -> Result<()> {
         if std::fs::metadata(self.base.join(CONFIG_FILENAME)).is_ok() && !args.force() {
             bail!(
                 "Unable to initialise. Journal already present at {}",
@@ -53,27 +96,63 @@ impl NascentJournal {
         info!("Initialising journal at {}", self.base.display());
         std::fs::create_dir_all(&self.base)
             .with_context(|| format!("Creating journal at: {}", self.base.display()))?;
-        let config = Configuration::default();
+        let mut config = Configuration::default();
+        config.set_vcs(args.vcs());
         config.save(self.base.join(CONFIG_FILENAME))?;
-        let git = Git::new(&self.base);
-        git.init()?;
-        git.add(CONFIG_FILENAME)?;
+        vcs.init()?;
+        vcs.add(Path::new(CONFIG_FILENAME))?;
+        vcs.set_merge_driver(&config.log_glob(), merge::DRIVER_COMMAND)?;
         Ok(())
     }
 }
 
 impl Journal {
-    pub fn show_status(&self) -> Result<()> {
-        let stats = self.git.status()?;
-        for stat in stats {
-            println!("{:?}", stat);
+    pub fn show_status(&self, format: OutputFormat) -> Result<()> {
+        let status = self.status(Self::today()?)?;
+        match format {
+            OutputFormat::Text => print!("{status}"),
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&status).context("Serialising status")?
+            ),
         }
         Ok(())
     }
 
-    pub fn show_config(&self) -> Result<()> {
-        let raw = self.config.raw_config()?;
-        println!("{}", raw);
+    fn status(&self, today: Date) -> Result<JournalStatus> {
+        let recent = self.find_recent(today)?;
+        let tasks = match &recent {
+            Some((_, log_entry)) => TaskCounts::of(&MarkdownFile::load(log_entry)?.document()),
+            None => TaskCounts::default(),
+        };
+        let last_entry = recent.map(|(date, _)| date);
+        Ok(JournalStatus {
+            today,
+            today_exists: last_entry == Some(today),
+            last_entry,
+            days_since_last_entry: last_entry.map(|date| (today - date).whole_days()),
+            tasks,
+            changes: status::changes(self.vcs.status()?),
+            upstream: self.vcs.upstream()?,
+        })
+    }
+
+    pub fn config_get(&self, key: &str) -> Result<()> {
+        match self.config.get(key) {
+            Some(toml::Value::String(value)) => println!("{value}"),
+            Some(toml::Value::Table(table)) => print!("{table}"),
+            Some(value) => println!("{value}"),
+            None => bail!("`{key}` is not set"),
+        }
+        Ok(())
+    }
+
+    pub fn show_config(&self, sources: bool) -> Result<()> {
+        if sources {
+            print!("{}", self.config.annotated_config());
+        } else {
+            println!("{}", self.config.raw_config()?);
+        }
         Ok(())
     }
 
@@ -100,9 +179,15 @@ impl Journal {
         let Some(tomorrow) = Self::today()?.next_day() else {
             bail!("Unable to determine tomorrow's date")
         };
+        self.find_recent(tomorrow)?
+            .map(|(_, log_entry)| MarkdownFile::load(log_entry))
+            .transpose()
+    }
 
+    /// The most recent entry on or before `from`
+    fn find_recent(&self, from: Date) -> Result<Option<(Date, PathBuf)>> {
         let mut tries = 100; // Maximum of 100 days backwards to try
-        let mut to_check = tomorrow;
+        let mut to_check = from;
         loop {
             tries -= 1;
             if tries == 0 {
@@ -112,7 +197,7 @@ impl Journal {
             if std::fs::exists(&log_entry).with_context(|| {
                 format!("Attempting to detect existence of {}", log_entry.display())
             })? {
-                break Some(MarkdownFile::load(log_entry)).transpose();
+                break Ok(Some((to_check, log_entry)));
             } else {
                 let Some(next_check) = to_check.previous_day() else {
                     bail!("Unable to compute day prior to {}", to_check);
@@ -146,8 +231,16 @@ impl Journal {
         loaded.set_created(&new_created);
         loaded.set_author(self.config.author());
 
-        loaded.filter_markdown(KeepDrop::new(loaded.keep_drop()), &self.config);
-        loaded.filter_markdown(TodoFilter::new(), &self.config);
+        // The entry is about to be rewritten, so keep our own copy of it
+        let before = loaded.document().into_static();
+        loaded.filter_markdown(KeepDrop::new(loaded.keep_drop()), &self.config)?;
+        loaded.filter_markdown(TodoFilter::new(), &self.config)?;
+        loaded.filter_markdown(ClockFilter, &self.config)?;
+        let after = loaded.document();
+        let summary = summarise(&before, &after, &before.diff(&after));
+        if !summary.is_empty() {
+            info!("Carried forward with {}", summary.join(", "));
+        }
 
         std::fs::create_dir_all(new_filename.parent().unwrap()).with_context(|| {
             format!("Creating directories to lead to {}", new_filename.display())
@@ -156,6 +249,326 @@ impl Journal {
         loaded.write_raw(Some(new_filename))
     }
 
+    pub fn diff(&self, from: Date, to: Date) -> Result<()> {
+        let old_file = MarkdownFile::load(self.log_filename(from)?)?;
+        let new_file = MarkdownFile::load(self.log_filename(to)?)?;
+        let (old, new) = (old_file.document(), new_file.document());
+        let diff = old.diff(&new);
+        if diff.is_empty() {
+            println!("No differences between {from} and {to}");
+            return Ok(());
+        }
+        for line in summarise(&old, &new, &diff) {
+            println!("{line}");
+        }
+        print!("{}", describe(&diff, &self.config.render_options())?);
+        Ok(())
+    }
+
+    /// List the commits to an entry, or show it at one of them
+    pub fn history(&self, date: Date, show: Option<&str>, tasks: bool) -> Result<()> {
+        let filename = self.log_filename(date)?;
+        let path = filename.strip_prefix(&self.base).unwrap_or(&filename);
+        if let Some(revision) = show {
+            let Some(body) = self.vcs.show(revision, path)? else {
+                bail!("The entry for {date} did not exist as of {revision}");
+            };
+            print!("{body}");
+            return Ok(());
+        }
+        let offset = Self::now()?.offset();
+        if tasks {
+            let versions = history::versions(&*self.vcs, path, offset)?;
+            for task in history::replay(&versions) {
+                print!("{task}");
+            }
+            return Ok(());
+        }
+        let revisions = self.vcs.log(Some(path))?;
+        if revisions.is_empty() {
+            println!("The entry for {date} has never been committed");
+        }
+        for revision in revisions {
+            let revision = Revision {
+                time: revision.time.to_offset(offset),
+                ..revision
+            };
+            println!("{}", history::describe(&revision)?);
+        }
+        Ok(())
+    }
+
+    /// Check entries for problems, failing if there are any
+    pub fn check(&self, staged: bool, paths: &[PathBuf]) -> Result<()> {
+        let (checked, problems) = self.problems(staged, paths)?;
+        for problem in &problems {
+            println!("{problem}");
+        }
+        if !problems.is_empty() {
+            bail!(
+                "Found {} problem(s) in {checked} entries, see above",
+                problems.len()
+            );
+        }
+        info!("Checked {checked} entries");
+        Ok(())
+    }
+
+    /// The problems in the entries staged for commit, if `staged`, or else
+    /// those in `paths`, or in every entry, along with how many were checked
+    fn problems(&self, staged: bool, paths: &[PathBuf]) -> Result<(usize, Vec<Problem>)> {
+        let extension = PathBuf::from(self.config.log_glob());
+        let extension = extension.extension();
+        let entries = if staged {
+            self.vcs
+                .status()?
+                .into_iter()
+                .filter(|status| !matches!(status.index, ' ' | '?' | 'D'))
+                .map(|status| status.path)
+                .filter(|path| path.extension() == extension)
+                .collect()
+        } else if paths.is_empty() {
+            let mut entries = Vec::new();
+            find_files(&self.base, Path::new(""), &mut entries)?;
+            entries.retain(|path| path.extension() == extension);
+            entries.sort();
+            entries
+        } else {
+            paths.to_vec()
+        };
+        let mut problems = Vec::new();
+        if staged {
+            if let Some(config) = self.vcs.staged(Path::new(CONFIG_FILENAME))? {
+                problems.extend(check::configuration(
+                    &self.config,
+                    Path::new(CONFIG_FILENAME),
+                    &config,
+                ));
+            }
+        }
+        for path in &entries {
+            // What's about to be committed, which needn't be what's on disk
+            let body = if staged {
+                self.vcs
+                    .staged(path)?
+                    .ok_or_else(|| eyre!("{} is not staged", path.display()))?
+            } else {
+                let full = self.base.join(path);
+                std::fs::read_to_string(&full)
+                    .with_context(|| format!("Reading {} to check it", full.display()))?
+            };
+            problems.extend(check::entry(&self.config, path, &body));
+        }
+        Ok((entries.len(), problems))
+    }
+
+    /// Install the pre-commit hook which runs `jt check`, and the merge driver
+    pub fn install_hooks(&self, force: bool) -> Result<()> {
+        let hook = self.pre_commit_hook()?;
+        if let Some(existing) = read_if_exists(&hook)? {
+            if existing != PRE_COMMIT_HOOK && !force {
+                bail!(
+                    "There is already a pre-commit hook at {}, use --force to replace it",
+                    hook.display()
+                );
+            }
+        }
+        std::fs::create_dir_all(hook.parent().unwrap())
+            .with_context(|| format!("Creating directories to lead to {}", hook.display()))?;
+        std::fs::write(&hook, PRE_COMMIT_HOOK)
+            .with_context(|| format!("Writing {}", hook.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))
+                .with_context(|| format!("Making {} executable", hook.display()))?;
+        }
+        info!("Installed {}", hook.display());
+        self.vcs
+            .set_merge_driver(&self.config.log_glob(), merge::DRIVER_COMMAND)
+    }
+
+    /// Remove the pre-commit hook, if it's the one `jt hooks install` wrote
+    pub fn uninstall_hooks(&self) -> Result<()> {
+        let hook = self.pre_commit_hook()?;
+        match read_if_exists(&hook)? {
+            None => info!("There is no pre-commit hook to remove"),
+            Some(existing) if existing != PRE_COMMIT_HOOK => bail!(
+                "The pre-commit hook at {} was not installed by jt, leaving it alone",
+                hook.display()
+            ),
+            Some(_) => {
+                std::fs::remove_file(&hook)
+                    .with_context(|| format!("Removing {}", hook.display()))?;
+                info!("Removed {}", hook.display());
+            }
+        }
+        Ok(())
+    }
+
+    fn pre_commit_hook(&self) -> Result<PathBuf> {
+        let Some(dir) = self.vcs.hooks_dir()? else {
+            bail!("The journal has no version control to install hooks into");
+        };
+        Ok(dir.join("pre-commit"))
+    }
+
+    /// Merge their version of an entry into ours, for git
+    ///
+    /// If the entries can't be merged structurally, ours is left with
+    /// conflict markers in it instead.
+    pub fn merge_driver(&self, base: &Path, ours: &Path, theirs: &Path) -> Result<()> {
+        // If both sides added the entry, there is no common ancestor
+        let base_file = match std::fs::read_to_string(base)
+            .with_context(|| format!("Reading {}", base.display()))?
+        {
+            body if body.trim().is_empty() => MarkdownFile::parse(base, "+++\n+++\n")?,
+            body => MarkdownFile::parse(base, &body)?,
+        };
+        let mut ours_file = MarkdownFile::load(ours)?;
+        let theirs_file = MarkdownFile::load(theirs)?;
+        let merged = merge::frontmatter(
+            base_file.frontmatter(),
+            ours_file.frontmatter(),
+            theirs_file.frontmatter(),
+        )
+        .and_then(|frontmatter| {
+            let document = merge::document(
+                &base_file.document(),
+                &ours_file.document(),
+                &theirs_file.document(),
+            )?;
+            Ok((frontmatter, document.into_static()))
+        });
+        match merged {
+            Ok((frontmatter, document)) => {
+                ours_file.set_frontmatter(frontmatter);
+                ours_file.set_document(&document, &self.config)?;
+                ours_file.write_raw(None::<&Path>)
+            }
+            Err(conflict) => {
+                self.vcs.merge_file(ours, base, theirs)?;
+                bail!("Unable to merge entries: {conflict}");
+            }
+        }
+    }
+
+    /// Print the first table in the named section of an entry, as CSV
+    pub fn export_table(&self, date: Date, heading: &str) -> Result<()> {
+        let entry = MarkdownFile::load(self.log_filename(date)?)?;
+        let sections = Sections::from_blocks(entry.document().blocks);
+        let Some(section) = sections.find_by_title(heading) else {
+            bail!("No section `{heading}` in the entry for {date}");
+        };
+        let Some(table) = section.body.iter().find_map(|block| match block {
+            Block::Table(table) => Some(table),
+            _ => None,
+        }) else {
+            bail!("No table directly within `{heading}` in the entry for {date}");
+        };
+        print!("{}", table.to_csv());
+        Ok(())
+    }
+
+    /// Today's entry, which clocking in and out is recorded in
+    fn todays_entry(&self) -> Result<MarkdownFile> {
+        let filename = self.log_filename(Self::today()?)?;
+        if !std::fs::exists(&filename)
+            .with_context(|| format!("Attempting to detect existence of {}", filename.display()))?
+        {
+            bail!("There is no entry for today yet, run `jt prep` first");
+        }
+        MarkdownFile::load(filename)
+    }
+
+    pub fn clock_in(&self, project: &str) -> Result<()> {
+        let now = Self::now()?;
+        let mut entry = self.todays_entry()?;
+        let (doc, closed) = clock::clock_in(entry.document(), project, now)?;
+        let doc = doc.into_static();
+        entry.set_document(&doc, &self.config)?;
+        entry.write_raw(None::<&Path>)?;
+        for interval in closed {
+            let spent = interval.duration_within(interval.start, now);
+            println!("Clocked out of {} after {}", interval.project, hours(spent));
+        }
+        println!("Clocked in to {project}");
+        Ok(())
+    }
+
+    pub fn clock_out(&self) -> Result<()> {
+        let now = Self::now()?;
+        let mut entry = self.todays_entry()?;
+        let (doc, interval) = clock::clock_out(entry.document(), now)?;
+        let doc = doc.into_static();
+        entry.set_document(&doc, &self.config)?;
+        entry.write_raw(None::<&Path>)?;
+        let spent = interval.duration_within(interval.start, now);
+        println!("Clocked out of {} after {}", interval.project, hours(spent));
+        Ok(())
+    }
+
+    pub fn clock_status(&self) -> Result<()> {
+        let now = Self::now()?;
+        let intervals = match self.load_recent()? {
+            Some(entry) => clock::intervals(&entry.document())?,
+            None => Vec::new(),
+        };
+        match intervals.iter().find(|i| i.end.is_none()) {
+            Some(interval) => println!(
+                "Clocked in to {} since {} ({})",
+                interval.project,
+                interval
+                    .start
+                    .format(format_description!("[hour]:[minute]"))
+                    .context("Formatting clock in time")?,
+                hours(interval.duration_within(interval.start, now))
+            ),
+            None => println!("Not clocked in"),
+        }
+        Ok(())
+    }
+
+    /// Total the time spent on each project today, or this week
+    pub fn clock_report(&self, week: bool) -> Result<()> {
+        let now = Self::now()?;
+        let mut from = now.date();
+        if week {
+            from -= Duration::days(from.weekday().number_days_from_monday().into());
+        }
+        let mut intervals = Vec::new();
+        for day in 0..=(now.date() - from).whole_days() {
+            let filename = self.log_filename(from + Duration::days(day))?;
+            if std::fs::exists(&filename).with_context(|| {
+                format!("Attempting to detect existence of {}", filename.display())
+            })? {
+                let entry = MarkdownFile::load(filename)?;
+                intervals.extend(clock::intervals(&entry.document())?);
+            }
+        }
+        let start = from.midnight().assume_offset(now.offset());
+        let totals = clock::report(intervals, start, now);
+        if totals.is_empty() {
+            println!("No time recorded since {from}");
+            return Ok(());
+        }
+        let width = totals
+            .keys()
+            .map(|p| p.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(5);
+        for (project, spent) in &totals {
+            println!("{project:width$}  {}", hours(*spent));
+        }
+        println!(
+            "{:width$}  {}",
+            "Total",
+            hours(totals.values().copied().sum())
+        );
+        Ok(())
+    }
+
     pub fn edit(&self) -> Result<()> {
         let editor = self.config.editor();
         let mut cmd = Command::new(editor[0].as_ref());
@@ -182,3 +595,428 @@ impl Journal {
         Ok(())
     }
 }
+
+/// List the named journals, with the status of each
+pub fn show_journals(format: OutputFormat) -> Result<()> {
+    let profiles = Profiles::load()?;
+    if let Some(problem) = profiles.problem() {
+        warn!("{problem}");
+    }
+    let journals = journals(
+        &profiles,
+        Journal::today()?,
+        config::layers::global_path().as_deref(),
+        &std::env::vars().collect::<Vec<_>>(),
+    );
+    match format {
+        OutputFormat::Text => {
+            if journals.is_empty() {
+                info!("No journals are named in the global configuration");
+            }
+            for (n, journal) in journals.iter().enumerate() {
+                if n > 0 {
+                    println!();
+                }
+                print!("{journal}");
+            }
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&journals).context("Serialising journals")?
+        ),
+    }
+    Ok(())
+}
+
+/// Bring the global configuration up to date
+///
+/// This doesn't involve any journal, so it works however broken the
+/// global configuration's choice of journal is.
+pub fn migrate_global_config() -> Result<()> {
+    let path = config::layers::global_path()
+        .ok_or_else(|| eyre!("Unable to find the global configuration"))?;
+    migrate_config(&path)
+}
+
+fn migrate_config(path: &Path) -> Result<()> {
+    let steps = Configuration::migrate(path)?;
+    if steps.is_empty() {
+        info!("{} is already up to date", path.display());
+    }
+    for step in steps {
+        info!("Migrating {}: {step}", path.display());
+    }
+    Ok(())
+}
+
+/// Run `f` on each of the named journals in turn, under a line naming it
+///
+/// A journal which can't be loaded, or where `f` fails, is reported and
+/// skipped, so that the others are still covered.
+pub fn for_each_journal(profiles: &Profiles, f: impl FnMut(&Journal) -> Result<()>) -> Result<()> {
+    let env = std::env::vars().collect::<Vec<_>>();
+    for_each_journal_with(profiles, config::layers::global_path().as_deref(), &env, f)
+}
+
+/// As [`for_each_journal`], with the global configuration at `global`, if
+/// it exists, and `env` in place of the environment
+fn for_each_journal_with(
+    profiles: &Profiles,
+    global: Option<&Path>,
+    env: &[(String, String)],
+    mut f: impl FnMut(&Journal) -> Result<()>,
+) -> Result<()> {
+    if profiles.iter().next().is_none() {
+        info!("No journals are named in the global configuration");
+    }
+    let mut failed = 0;
+    for (n, (name, path)) in profiles.iter().enumerate() {
+        if n > 0 {
+            println!();
+        }
+        println!("{name}: {}", path.display());
+        if let Err(e) = NascentJournal::new(path)
+            .load_with(global, env.to_vec())
+            .and_then(|journal| f(&journal))
+        {
+            warn!("{name}: {e:#}");
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!("Failed for {failed} of the journals, see above");
+    }
+    Ok(())
+}
+
+fn journals(
+    profiles: &Profiles,
+    today: Date,
+    global: Option<&Path>,
+    env: &[(String, String)],
+) -> Vec<NamedStatus> {
+    profiles
+        .iter()
+        .map(|(name, path)| {
+            let status = NascentJournal::new(path)
+                .load_with(global, env.to_vec())
+                .and_then(|journal| journal.status(today));
+            let (status, error) = match status {
+                Ok(status) => (Some(status), None),
+                Err(e) => (None, Some(format!("{e:#}"))),
+            };
+            NamedStatus {
+                name: name.into(),
+                path: path.into(),
+                default: profiles.default_journal() == Some(name),
+                status,
+                error,
+            }
+        })
+        .collect()
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Reading {}", path.display())),
+    }
+}
+
+/// Every file beneath `dir` in the journal, skipping hidden ones
+fn find_files(base: &Path, dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    let full = base.join(dir);
+    let entries =
+        std::fs::read_dir(&full).with_context(|| format!("Listing {}", full.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Listing {}", full.display()))?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = dir.join(entry.file_name());
+        if entry
+            .file_type()
+            .with_context(|| format!("Examining {}", path.display()))?
+            .is_dir()
+        {
+            find_files(base, &path, found)?;
+        } else {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use crate::vcs::fake::FakeVcs;
+
+    use super::*;
+
+    #[test]
+    fn initialise() {
+        let dir = tempfile::tempdir().unwrap();
+        let vcs = FakeVcs::new(dir.path());
+        let journal = NascentJournal::new(dir.path());
+        let args = InitArgs::parse_from(["init"]);
+        journal.initialise_with(&args, &vcs).unwrap();
+        assert!(vcs.initialised());
+        let status = vcs.status().unwrap();
+        assert_eq!(status[0].path, Path::new(CONFIG_FILENAME));
+        assert_eq!(
+            vcs.merge_driver(),
+            Some(("log/*-*-*.md".into(), merge::DRIVER_COMMAND.into()))
+        );
+
+        // A second attempt needs forcing
+        assert!(journal.initialise_with(&args, &vcs).is_err());
+        let args = InitArgs::parse_from(["init", "--force", "--vcs", "none"]);
+        journal.initialise_with(&args, &vcs).unwrap();
+        let journal = NascentJournal::new(dir.path()).load_with(None, []).unwrap();
+        assert_eq!(journal.config.vcs(), vcs::VcsKind::None);
+    }
+
+    #[test]
+    fn status() {
+        let dir = tempfile::tempdir().unwrap();
+        let vcs = FakeVcs::new(dir.path());
+        vcs.init().unwrap();
+        let journal = Journal {
+            base: dir.path().into(),
+            vcs: Box::new(vcs),
+            config: Configuration::default(),
+        };
+        let today = time::macros::date!(2024 - 12 - 04);
+        let status = journal.status(today).unwrap();
+        assert!(!status.today_exists);
+        assert_eq!(status.last_entry, None);
+
+        let entry = journal.log_filename(today - Duration::days(2)).unwrap();
+        std::fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        std::fs::write(
+            &entry,
+            "+++\n+++\n# Actions\n\n- [ ] One\n- [ ] Two\n- [.] Three\n- [P] Four\n- [x] Five\n",
+        )
+        .unwrap();
+        journal
+            .vcs
+            .add(entry.strip_prefix(dir.path()).unwrap())
+            .unwrap();
+        let status = journal.status(today).unwrap();
+        insta::assert_snapshot!(status.to_string(), @r###"
+        Today's entry (2024-12-04): missing, run `jt prep`
+        Last entry: 2024-12-02 (2 days ago)
+        Tasks: 2 open, 1 partial, 1 paused
+        Changes:
+          added: log/2024-12-02.md
+        Upstream: none configured
+        "###);
+        insta::assert_snapshot!(serde_json::to_string_pretty(&status).unwrap(), @r###"
+        {
+          "today": "2024-12-04",
+          "today_exists": false,
+          "last_entry": "2024-12-02",
+          "days_since_last_entry": 2,
+          "tasks": {
+            "open": 2,
+            "partial": 1,
+            "paused": 1
+          },
+          "changes": {
+            "added": [
+              "log/2024-12-02.md"
+            ]
+          },
+          "upstream": null
+        }
+        "###);
+    }
+
+    #[test]
+    fn checking() {
+        let dir = tempfile::tempdir().unwrap();
+        let vcs = FakeVcs::new(dir.path());
+        vcs.init().unwrap();
+        let journal = Journal {
+            base: dir.path().into(),
+            vcs: Box::new(vcs),
+            config: Configuration::default(),
+        };
+        std::fs::create_dir_all(dir.path().join("log")).unwrap();
+        for (name, body) in [
+            (
+                "log/2024-12-02.md",
+                "+++\n+++\n# Actions {.todo}\n\n- [x] Fine\n",
+            ),
+            (
+                "log/2024-12-03.md",
+                "+++\n+++\n# Actions {.todo}\n\n- [?] Odd\n",
+            ),
+            ("log/notes.md", "+++\n+++\n"),
+        ] {
+            std::fs::write(dir.path().join(name), body).unwrap();
+        }
+        journal.vcs.add(Path::new("log/2024-12-03.md")).unwrap();
+
+        let (checked, problems) = journal.problems(true, &[]).unwrap();
+        assert_eq!((checked, problems.len()), (1, 1));
+        // Fixing the working copy doesn't fix what's staged, and vice versa
+        let fixed = "+++\n+++\n# Actions {.todo}\n\n- [x] Odd\n";
+        std::fs::write(dir.path().join("log/2024-12-03.md"), fixed).unwrap();
+        let (_, problems) = journal.problems(true, &[]).unwrap();
+        assert_eq!(problems.len(), 1);
+        journal.vcs.add(Path::new("log/2024-12-03.md")).unwrap();
+        let (_, problems) = journal.problems(true, &[]).unwrap();
+        assert!(problems.is_empty());
+        // A staged configuration is checked, since the one on disk was checked
+        // when the journal was loaded
+        std::fs::write(
+            dir.path().join(CONFIG_FILENAME),
+            "version = 2\n[juntakami]\neditor = [\"vim\", \"@FILE@\", \"@ENTRY@\"]\n",
+        )
+        .unwrap();
+        journal.vcs.add(Path::new(CONFIG_FILENAME)).unwrap();
+        let (checked, problems) = journal.problems(true, &[]).unwrap();
+        assert_eq!(checked, 1);
+        let problems = problems.iter().map(ToString::to_string).collect::<Vec<_>>();
+        insta::assert_snapshot!(problems.join("\n"), @r###"
+        juntakami.conf: Editor argument `@FILE@` has unknown substitution variable `@FILE@`, only `@JOURNAL@` and `@ENTRY@` are known, in juntakami.conf
+        "###);
+        std::fs::write(dir.path().join(CONFIG_FILENAME), "version = 2\n").unwrap();
+        journal.vcs.add(Path::new(CONFIG_FILENAME)).unwrap();
+        let (_, problems) = journal.problems(true, &[]).unwrap();
+        assert!(problems.is_empty());
+        std::fs::write(
+            dir.path().join("log/2024-12-03.md"),
+            "+++\n+++\n# Actions {.todo}\n\n- [?] Odd\n",
+        )
+        .unwrap();
+        let (checked, problems) = journal.problems(false, &[]).unwrap();
+        assert_eq!(checked, 3);
+        let problems = problems.iter().map(ToString::to_string).collect::<Vec<_>>();
+        insta::assert_snapshot!(problems.join("\n"), @r###"
+        log/2024-12-03.md: Unknown task marker `[?]` in the `Actions` section
+        log/notes.md: Filename does not match log_pattern `log/*-*-*.md`
+        "###);
+
+        journal.install_hooks(false).unwrap();
+        let hook = journal.pre_commit_hook().unwrap();
+        assert_eq!(std::fs::read_to_string(&hook).unwrap(), PRE_COMMIT_HOOK);
+        journal.uninstall_hooks().unwrap();
+        assert!(!hook.exists());
+        std::fs::write(&hook, "#!/bin/sh\n").unwrap();
+        assert!(journal.install_hooks(false).is_err());
+        assert!(journal.uninstall_hooks().is_err());
+    }
+
+    #[test]
+    fn named_journals() {
+        let dir = tempfile::tempdir().unwrap();
+        let args = InitArgs::parse_from(["init", "--vcs", "none"]);
+        NascentJournal::new(dir.path().join("home"))
+            .initialise(&args)
+            .unwrap();
+        let profiles = Profiles::load_str(
+            &dir.path().join("config.toml"),
+            "default_journal = \"home\"\n[journals.home]\npath = \"home\"\n[journals.gone]\npath = \"gone\"\n",
+        )
+        .unwrap();
+        let today = time::macros::date!(2024 - 12 - 04);
+        let listed = journals(&profiles, today, None, &[]);
+        let [gone, home] = listed.as_slice() else {
+            panic!("Expected two journals");
+        };
+        assert_eq!((gone.name.as_str(), gone.default), ("gone", false));
+        assert!(gone.status.is_none());
+        assert!(gone
+            .error
+            .as_ref()
+            .unwrap()
+            .starts_with("Attempting to read configuration from"));
+        assert_eq!((home.name.as_str(), home.default), ("home", true));
+        assert_eq!(home.path, dir.path().join("home"));
+        assert_eq!(home.status.as_ref().unwrap().last_entry, None);
+        assert!(home.error.is_none());
+
+        let mut visited = Vec::new();
+        let err = for_each_journal_with(&profiles, None, &[], |journal| {
+            visited.push(journal.base.clone());
+            Ok(())
+        })
+        .unwrap_err();
+        assert_eq!(visited, [dir.path().join("home")]);
+        assert_eq!(err.to_string(), "Failed for 1 of the journals, see above");
+        // A default which isn't one of the journals doesn't stop them being
+        // listed, it just isn't marked
+        let profiles = Profiles::load_str(
+            &dir.path().join("config.toml"),
+            "default_journal = \"work\"\n[journals.home]\npath = \"home\"\n",
+        )
+        .unwrap();
+        assert!(profiles.problem().is_some());
+        let listed = journals(&profiles, today, None, &[]);
+        let [home] = listed.as_slice() else {
+            panic!("Expected one journal");
+        };
+        assert_eq!((home.name.as_str(), home.default), ("home", false));
+        assert!(home.error.is_none());
+    }
+
+    #[test]
+    fn merging() {
+        let dir = tempfile::tempdir().unwrap();
+        let vcs = FakeVcs::new(dir.path());
+        vcs.init().unwrap();
+        let journal = Journal {
+            base: dir.path().into(),
+            vcs: Box::new(vcs),
+            config: Configuration::default(),
+        };
+        let [base, ours, theirs] = ["base", "ours", "theirs"].map(|name| dir.path().join(name));
+        let write = |path: &Path, heading: &str, notes: &str| {
+            std::fs::write(path, format!("+++\n+++\n# {heading}\n\n{notes}\n")).unwrap();
+        };
+        write(&base, "Notes {.drop}", "Some notes");
+        write(&ours, "Notes {.drop}", "Some notes\n\nMine");
+        write(&theirs, "Notes {.drop}", "Some notes\n\nTheirs");
+        journal.merge_driver(&base, &ours, &theirs).unwrap();
+        insta::assert_snapshot!(std::fs::read_to_string(&ours).unwrap(), @r###"
+        +++
+
+        +++
+        # Notes { .drop }
+
+        Some notes
+
+        Theirs
+
+        Mine
+        "###);
+
+        write(&ours, "Notes {.keep}", "Some notes");
+        write(&theirs, "Notes", "Some notes");
+        let err = journal.merge_driver(&base, &ours, &theirs).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unable to merge entries: The heading `Notes` was changed on both sides"
+        );
+        insta::assert_snapshot!(std::fs::read_to_string(&ours).unwrap(), @r###"
+        <<<<<<< ours
+        +++
+        +++
+        # Notes {.keep}
+
+        Some notes
+        =======
+        +++
+        +++
+        # Notes
+
+        Some notes
+        >>>>>>> theirs
+        "###);
+    }
+}
@@ -1,19 +1,27 @@
 use std::{ffi::OsStr, path::Path};
 
 use clap::Parser;
-use cli::Cmd;
+use cli::{ClockCmd, Cmd, ConfigCmd, HooksCmd, TableCmd};
+use config::profiles::Profiles;
 use eyre::Result;
 use git_testament::GitModification;
-use journal::NascentJournal;
+use journal::{for_each_journal, migrate_global_config, show_journals, NascentJournal};
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::EnvFilter;
 
+mod check;
 mod cli;
+mod clock;
 pub mod config;
+mod diff;
 pub mod filters;
 mod git;
+mod history;
 mod journal;
 pub mod markdown;
+mod merge;
+mod status;
+pub mod vcs;
 
 fn main() -> Result<()> {
     color_eyre::install()?;
@@ -26,15 +34,54 @@ fn main() -> Result<()> {
         )
         .init();
     let args = cli::Cli::parse();
-    let journal = NascentJournal::new(args.path());
 
+    // These don't need the journal picked out by the path, so don't
+    // resolve it in case the global configuration is what needs fixing
     match args.cmd() {
         Cmd::DumpTestament => {
             dump_testament();
             return Ok(());
         }
-        Cmd::Init(args) => {
-            journal.initialise(args)?;
+        Cmd::Init(init) => {
+            NascentJournal::new(args.init_path()?).initialise(init)?;
+            return Ok(());
+        }
+        Cmd::Config(ConfigCmd::Migrate(args)) if args.global() => {
+            migrate_global_config()?;
+            return Ok(());
+        }
+        Cmd::Journals(args) => {
+            show_journals(args.format())?;
+            return Ok(());
+        }
+        Cmd::Diff(args) if args.all_journals() => {
+            for_each_journal(&Profiles::load()?, |journal| {
+                journal.diff(args.from(), args.to())
+            })?;
+            return Ok(());
+        }
+        Cmd::History(args) if args.all_journals() => {
+            for_each_journal(&Profiles::load()?, |journal| {
+                journal.history(args.date(), None, args.tasks())
+            })?;
+            return Ok(());
+        }
+        _ => {}
+    }
+    let journal = NascentJournal::new(args.path()?);
+
+    match args.cmd() {
+        // These don't need the configuration to be valid, so it can be fixed
+        Cmd::Config(ConfigCmd::Set(args)) => {
+            journal.config_set(args.key(), args.value())?;
+            return Ok(());
+        }
+        Cmd::Config(ConfigCmd::Unset(args)) => {
+            journal.config_unset(args.key())?;
+            return Ok(());
+        }
+        Cmd::Config(ConfigCmd::Migrate(_)) => {
+            journal.config_migrate()?;
             return Ok(());
         }
         _ => {}
@@ -44,10 +91,24 @@ fn main() -> Result<()> {
     match args.cmd() {
         Cmd::DumpTestament => {}
         Cmd::Init(_) => {}
-        Cmd::Status => journal.show_status()?,
-        Cmd::ShowConfig => journal.show_config()?,
+        Cmd::Journals(_) => {}
+        Cmd::Config(ConfigCmd::Set(_) | ConfigCmd::Unset(_) | ConfigCmd::Migrate(_)) => {}
+        Cmd::Config(ConfigCmd::Get(args)) => journal.config_get(args.key())?,
+        Cmd::Status(args) => journal.show_status(args.format())?,
+        Cmd::ShowConfig(args) => journal.show_config(args.sources())?,
         Cmd::Prep => journal.prep()?,
         Cmd::Edit => journal.edit()?,
+        Cmd::Diff(args) => journal.diff(args.from(), args.to())?,
+        Cmd::History(args) => journal.history(args.date(), args.show(), args.tasks())?,
+        Cmd::MergeDriver(args) => journal.merge_driver(args.base(), args.ours(), args.theirs())?,
+        Cmd::Check(args) => journal.check(args.staged(), args.paths())?,
+        Cmd::Hooks(HooksCmd::Install(args)) => journal.install_hooks(args.force())?,
+        Cmd::Hooks(HooksCmd::Uninstall) => journal.uninstall_hooks()?,
+        Cmd::Table(TableCmd::Export(args)) => journal.export_table(args.date(), args.heading())?,
+        Cmd::Clock(ClockCmd::In(args)) => journal.clock_in(args.project())?,
+        Cmd::Clock(ClockCmd::Out) => journal.clock_out()?,
+        Cmd::Clock(ClockCmd::Status) => journal.clock_status()?,
+        Cmd::Clock(ClockCmd::Report(args)) => journal.clock_report(args.week())?,
     }
 
     Ok(())
@@ -13,7 +13,7 @@ use std::path::{Path, PathBuf};
 
 use eyre::{bail, Context, Result};
 use once_cell::sync::Lazy;
-use pulldown_cmark_ast::{fold::MarkdownFold, Document, ParseOptions, RenderOptions};
+use pulldown_cmark_ast::{fold::MarkdownFold, Document, ParseOptions};
 use regex::Regex;
 use toml_edit::Item;
 
@@ -33,8 +33,6 @@ static TOML_RE: Lazy<Regex> = Lazy::new(|| {
     .unwrap()
 });
 
-static LIST_TIDY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"([\-\*] +)\\\[\\?(.)\\\] ").unwrap());
-
 impl MarkdownFile {
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         fn _load(path: &Path) -> Result<MarkdownFile> {
@@ -77,6 +75,14 @@ impl MarkdownFile {
         &self.origin
     }
 
+    pub fn frontmatter(&self) -> &toml_edit::DocumentMut {
+        &self.frontmatter
+    }
+
+    pub fn set_frontmatter(&mut self, frontmatter: toml_edit::DocumentMut) {
+        self.frontmatter = frontmatter;
+    }
+
     pub fn markdown(&self) -> &str {
         &self.markdown
     }
@@ -131,12 +137,29 @@ impl MarkdownFile {
             .unwrap_or(false)
     }
 
-    pub fn filter_markdown(&mut self, mut filter: impl MarkdownFold, config: &Configuration) {
-        let doc = Document::parse(&self.markdown, parse_opts());
+    pub fn document(&self) -> Document<'_> {
+        Document::parse(&self.markdown, parse_opts())
+    }
+
+    /// Replace the markdown with a rendering of `doc`
+    pub fn set_document(&mut self, doc: &Document<'_>, config: &Configuration) -> Result<()> {
+        self.markdown = doc
+            .render(config.render_options())
+            .with_context(|| format!("Rendering {}", self.origin.display()))?;
+        Ok(())
+    }
+
+    pub fn filter_markdown(
+        &mut self,
+        mut filter: impl for<'a> MarkdownFold<'a>,
+        config: &Configuration,
+    ) -> Result<()> {
+        let doc = self.document();
         let filtered = filter.fold_document(doc);
-        self.markdown = LIST_TIDY_RE
-            .replace_all(&filtered.render(render_opts(config)), "$1[$2] ")
-            .into_owned();
+        self.markdown = filtered
+            .render(config.render_options())
+            .with_context(|| format!("Rendering {}", self.origin.display()))?;
+        Ok(())
     }
 }
 
@@ -144,18 +167,6 @@ fn parse_opts() -> ParseOptions {
     ParseOptions::all().intersection(ParseOptions::ENABLE_SMART_PUNCTUATION.complement())
 }
 
-fn render_opts(config: &Configuration) -> RenderOptions<'_> {
-    RenderOptions {
-        list_token: config.list_char(),
-        strong_token: "**",
-        emphasis_token: '_',
-        ordered_list_token: '.',
-        code_block_token: '`',
-        increment_ordered_list_bullets: true,
-        ..Default::default()
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
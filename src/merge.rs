@@ -0,0 +1,448 @@
+//! Three-way merging of journal entries
+//!
+//! When the same entry has been edited in two places, git hands the
+//! common ancestor and both versions to `jt merge-driver`.  Rather than
+//! merging lines, the entries are merged structurally:
+//!
+//! * Frontmatter is merged key by key.
+//! * Sections are matched up by their titles, and blocks within them by
+//!   their content, so notes added on both sides are all kept.
+//! * Lists are matched up in order, and their items by the text of the
+//!   task, so tasks added on either side are all kept.  Where both sides
+//!   changed a task, the more advanced marker wins.
+//!
+//! Anything removed on one side is removed, unless the other side changed
+//! it.  Only when both sides changed something in different ways, such as
+//! the same frontmatter key or the same heading, is there a conflict, or
+//! when a changed sequence has two things which would be matched up the
+//! same way, such as two sections with one title, as they can't be told
+//! apart.
+
+use std::fmt;
+
+use pulldown_cmark_ast::{
+    section::{Section, Sections},
+    Block, Document, List, ListItem,
+};
+use toml_edit::DocumentMut;
+
+use crate::{diff::task, filters::progress};
+
+/// The merge driver as git should run it, from the top of the journal
+pub const DRIVER_COMMAND: &str = "jt --path . merge-driver %O %A %B";
+
+/// Something which couldn't be merged, usually because it was changed
+/// differently on each side
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict(String);
+
+impl Conflict {
+    fn both_sides(what: impl fmt::Display) -> Self {
+        Self(format!("{what} was changed on both sides"))
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+/// Take whichever side changed, or either if they agree
+fn pick<T: PartialEq + Clone>(
+    base: Option<&T>,
+    ours: &T,
+    theirs: &T,
+    what: impl FnOnce() -> String,
+) -> Result<T, Conflict> {
+    if ours == theirs || base == Some(theirs) {
+        Ok(ours.clone())
+    } else if base == Some(ours) {
+        Ok(theirs.clone())
+    } else {
+        Err(Conflict::both_sides(what()))
+    }
+}
+
+/// Merge three sequences whose elements are matched up by `key`
+///
+/// The result is in our order, with anything only they added placed after
+/// whatever preceded it on their side.  Elements present on both sides are
+/// combined with `both`.  If only one side changed, that side is taken as
+/// it is; otherwise two elements of a sequence with the same key, named by
+/// `what`, are a conflict, since there's no telling which is which.
+fn merge_keyed<T: PartialEq + Clone, K: PartialEq>(
+    base: &[T],
+    ours: &[T],
+    theirs: &[T],
+    key: impl Fn(&T) -> K,
+    what: impl Fn(&T) -> String,
+    both: impl Fn(Option<&T>, &T, &T) -> Result<T, Conflict>,
+) -> Result<Vec<T>, Conflict> {
+    if ours == theirs || base == theirs {
+        return Ok(ours.to_vec());
+    }
+    if base == ours {
+        return Ok(theirs.to_vec());
+    }
+    for items in [base, ours, theirs] {
+        for (i, item) in items.iter().enumerate() {
+            let k = key(item);
+            if items[..i].iter().any(|earlier| key(earlier) == k) {
+                return Err(Conflict(format!(
+                    "{} appears more than once, so can't be merged",
+                    what(item)
+                )));
+            }
+        }
+    }
+    let find = |items: &'_ [T], k: &K| items.iter().position(|item| key(item) == *k);
+    let mut ret = Vec::new();
+    let mut keys = Vec::new();
+    for item in ours {
+        let k = key(item);
+        let old = find(base, &k).map(|i| &base[i]);
+        match find(theirs, &k) {
+            Some(i) => ret.push(both(old, item, &theirs[i])?),
+            // Removed by them, unless we changed it
+            None if old == Some(item) => continue,
+            None => ret.push(item.clone()),
+        }
+        keys.push(k);
+    }
+    let mut at = 0;
+    for item in theirs {
+        let k = key(item);
+        if let Some(i) = keys.iter().position(|seen| *seen == k) {
+            at = i + 1;
+            continue;
+        }
+        if find(base, &k).map(|i| &base[i]) == Some(item) {
+            // Removed by us, and unchanged by them
+            continue;
+        }
+        ret.insert(at, item.clone());
+        keys.insert(at, k);
+        at += 1;
+    }
+    Ok(ret)
+}
+
+/// Merge the frontmatter of an entry, key by key
+pub fn frontmatter(
+    base: &DocumentMut,
+    ours: &DocumentMut,
+    theirs: &DocumentMut,
+) -> Result<DocumentMut, Conflict> {
+    // Compare values without their comments and whitespace
+    let repr = |doc: &DocumentMut, key: &str| {
+        doc.get(key).map(|item| match item.as_value() {
+            Some(value) => {
+                let mut value = value.clone();
+                value.decor_mut().clear();
+                value.to_string()
+            }
+            None => item.to_string(),
+        })
+    };
+    let mut ret = ours.clone();
+    let keys = ours
+        .iter()
+        .chain(theirs.iter())
+        .map(|(key, _)| key.to_string())
+        .collect::<Vec<_>>();
+    for key in keys {
+        let (old, mine, other) = (repr(base, &key), repr(ours, &key), repr(theirs, &key));
+        if mine == other || old == other {
+            continue;
+        }
+        if old != mine {
+            return Err(Conflict::both_sides(format!("Frontmatter key `{key}`")));
+        }
+        match theirs.get(&key) {
+            Some(item) => {
+                ret.insert(&key, item.clone());
+            }
+            None => {
+                ret.remove(&key);
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Merge the markdown of an entry
+pub fn document<'a>(
+    base: &Document<'a>,
+    ours: &Document<'a>,
+    theirs: &Document<'a>,
+) -> Result<Document<'a>, Conflict> {
+    let [base, ours, theirs] =
+        [base, ours, theirs].map(|doc| Sections::from_blocks(doc.blocks.clone()));
+    let merged = Sections {
+        preamble: blocks(&base.preamble, &ours.preamble, &theirs.preamble)?,
+        sections: sections(&base.sections, &ours.sections, &theirs.sections)?,
+    };
+    Ok(Document {
+        blocks: merged.into_blocks(),
+    })
+}
+
+fn sections<'a>(
+    base: &[Section<'a>],
+    ours: &[Section<'a>],
+    theirs: &[Section<'a>],
+) -> Result<Vec<Section<'a>>, Conflict> {
+    let what = |section: &Section| format!("The section `{}`", section.title());
+    merge_keyed(
+        base,
+        ours,
+        theirs,
+        Section::title,
+        what,
+        |old, mine, other| {
+            let heading = pick(
+                old.map(|s| &s.heading),
+                &mine.heading,
+                &other.heading,
+                || format!("The heading `{}`", mine.title()),
+            )?;
+            let empty = Section::new(heading.clone());
+            let old = old.unwrap_or(&empty);
+            Ok(Section {
+                heading,
+                body: blocks(&old.body, &mine.body, &other.body)?,
+                children: sections(&old.children, &mine.children, &other.children)?,
+            })
+        },
+    )
+}
+
+/// What blocks are matched up by: lists by where they come, anything
+/// else by its content
+#[derive(PartialEq)]
+enum BlockKey<'a> {
+    List(usize),
+    Other(Block<'a>),
+}
+
+fn blocks<'a>(
+    base: &[Block<'a>],
+    ours: &[Block<'a>],
+    theirs: &[Block<'a>],
+) -> Result<Vec<Block<'a>>, Conflict> {
+    // Number the lists in each sequence so they can be matched up
+    let numbered = |blocks: &[Block<'a>]| {
+        let mut lists = 0;
+        blocks
+            .iter()
+            .map(|block| {
+                let n = lists;
+                if matches!(block, Block::List(_)) {
+                    lists += 1;
+                }
+                (n, block.clone())
+            })
+            .collect::<Vec<_>>()
+    };
+    let [base, ours, theirs] = [base, ours, theirs].map(numbered);
+    let merged = merge_keyed(
+        &base,
+        &ours,
+        &theirs,
+        |(n, block)| match block {
+            Block::List(_) => BlockKey::List(*n),
+            _ => BlockKey::Other(block.clone()),
+        },
+        |_| "A block".into(),
+        |old, (n, mine), (_, other)| match (mine, other) {
+            (Block::List(mine), Block::List(other)) => {
+                let old = match old {
+                    Some((_, Block::List(old))) => old.items.as_slice(),
+                    _ => &[],
+                };
+                Ok((
+                    *n,
+                    Block::List(List {
+                        start: mine.start,
+                        items: items(old, &mine.items, &other.items)?,
+                    }),
+                ))
+            }
+            _ => Ok((*n, mine.clone())),
+        },
+    )?;
+    Ok(merged.into_iter().map(|(_, block)| block).collect())
+}
+
+/// What list items are matched up by: tasks by their text, anything else
+/// by its content
+#[derive(PartialEq)]
+enum ItemKey<'a> {
+    Task(String),
+    Other(ListItem<'a>),
+}
+
+fn items<'a>(
+    base: &[ListItem<'a>],
+    ours: &[ListItem<'a>],
+    theirs: &[ListItem<'a>],
+) -> Result<Vec<ListItem<'a>>, Conflict> {
+    merge_keyed(
+        base,
+        ours,
+        theirs,
+        |item| match task(item) {
+            Some((_, text)) => ItemKey::Task(text),
+            None => ItemKey::Other(item.clone()),
+        },
+        |item| match task(item) {
+            Some((_, text)) => format!("The task `{text}`"),
+            None => "A list item".into(),
+        },
+        |old, mine, other| {
+            let (Some((before, text)), Some((after, _))) = (task(mine), task(other)) else {
+                return Ok(mine.clone());
+            };
+            if progress(before) != progress(after) {
+                let ahead = if progress(before) > progress(after) {
+                    mine
+                } else {
+                    other
+                };
+                return Ok(ahead.clone());
+            }
+            pick(old, mine, other, || format!("The task `{text}`"))
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use pulldown_cmark_ast::{ParseOptions, RenderOptions};
+
+    use super::*;
+
+    const BASE: &str = r###"
+# Actions {.todo}
+
+- [ ] Write the merge driver
+- [ ] Test the merge driver
+- [ ] Forget about this
+
+# Notes {.drop}
+
+Some notes
+"###;
+
+    const OURS: &str = r###"
+# Actions {.todo}
+
+- [.] Write the merge driver
+- [ ] Test the merge driver
+- [ ] Forget about this
+- [ ] Something from this machine
+
+# Notes {.drop}
+
+Some notes
+
+Notes from this machine
+"###;
+
+    const THEIRS: &str = r###"
+# Actions {.todo}
+
+- [x] Write the merge driver
+- [.] Test the merge driver
+- [ ] Something from that machine
+
+# Notes {.drop}
+
+Some notes
+
+Notes from that machine
+"###;
+
+    #[test]
+    fn merging_documents() {
+        let parse = |text| Document::parse(text, ParseOptions::all());
+        let merged = document(&parse(BASE), &parse(OURS), &parse(THEIRS)).unwrap();
+        insta::assert_snapshot!(merged.render(RenderOptions::default()).unwrap(), @r###"
+        # Actions { .todo }
+
+        * [x] Write the merge driver
+        * [.] Test the merge driver
+        * [ ] Something from that machine
+        * [ ] Something from this machine
+
+        # Notes { .drop }
+
+        Some notes
+
+        Notes from that machine
+
+        Notes from this machine
+        "###);
+
+        let renamed = BASE.replace("Forget about this", "Remember this");
+        let other = BASE.replace("Forget about this", "Remember that");
+        let merged = document(&parse(BASE), &parse(&renamed), &parse(&other)).unwrap();
+        insta::assert_snapshot!(merged.render(RenderOptions::default()).unwrap(), @r###"
+        # Actions { .todo }
+
+        * [ ] Write the merge driver
+        * [ ] Test the merge driver
+        * [ ] Remember that
+        * [ ] Remember this
+
+        # Notes { .drop }
+
+        Some notes
+        "###);
+
+        let ours = BASE.replace("# Notes {.drop}", "# Notes {.keep}");
+        let theirs = BASE.replace("# Notes {.drop}", "# Notes");
+        let conflict = document(&parse(BASE), &parse(&ours), &parse(&theirs)).unwrap_err();
+        assert_eq!(
+            conflict.to_string(),
+            "The heading `Notes` was changed on both sides"
+        );
+
+        // Which of two `Notes` sections is which can't be told, unless only
+        // one side changed anything
+        let base = format!("{BASE}\n# Notes\n\nMore notes\n");
+        let ours = base.replace("More notes", "More of our notes");
+        let theirs = base.replace("Some notes", "Some of their notes");
+        let merged = document(&parse(&base), &parse(&ours), &parse(&base)).unwrap();
+        assert_eq!(merged, parse(&ours));
+        let conflict = document(&parse(&base), &parse(&ours), &parse(&theirs)).unwrap_err();
+        assert_eq!(
+            conflict.to_string(),
+            "The section `Notes` appears more than once, so can't be merged"
+        );
+    }
+
+    #[test]
+    fn merging_frontmatter() {
+        let parse = |text: &str| text.parse::<DocumentMut>().unwrap();
+        let base = parse("title = \"Entry\"\nkeep = false\nauthor = \"Someone\"\n");
+        let ours = parse("title = \"Entry\" # A comment\nkeep = true\nauthor = \"Someone\"\n");
+        let theirs = parse("title = \"Entry\"\nkeep = false\nmood = \"happy\"\n");
+        let merged = frontmatter(&base, &ours, &theirs).unwrap();
+        insta::assert_snapshot!(merged.to_string(), @r###"
+        title = "Entry" # A comment
+        keep = true
+        mood = "happy"
+        "###);
+
+        let theirs = parse("title = \"Entry\"\nkeep = 1\n");
+        let conflict = frontmatter(&base, &ours, &theirs).unwrap_err();
+        assert_eq!(
+            conflict.to_string(),
+            "Frontmatter key `keep` was changed on both sides"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! The state of a journal at a glance, for `jt status` and `jt journals`
+
+use std::{collections::BTreeMap, fmt, ops::ControlFlow, path::PathBuf};
+
+use pulldown_cmark_ast::{visit::Visit, Document};
+use serde::Serialize;
+use time::Date;
+
+use crate::vcs::{FileStatus, Upstream};
+
+#[derive(Debug, Serialize)]
+pub struct JournalStatus {
+    pub today: Date,
+    pub today_exists: bool,
+    /// The most recent entry, which may be today's
+    pub last_entry: Option<Date>,
+    pub days_since_last_entry: Option<i64>,
+    /// The tasks in the most recent entry
+    pub tasks: TaskCounts,
+    /// Files which differ from what's committed
+    pub changes: BTreeMap<ChangeKind, Vec<PathBuf>>,
+    pub upstream: Option<Upstream>,
+}
+
+/// The status of one of the named journals
+#[derive(Debug, Serialize)]
+pub struct NamedStatus {
+    pub name: String,
+    pub path: PathBuf,
+    /// Whether this is the journal used when none is given
+    pub default: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<JournalStatus>,
+    /// Why the status couldn't be found, such as the journal not existing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TaskCounts {
+    /// `[ ]`
+    pub open: usize,
+    /// `[.]`
+    pub partial: usize,
+    /// `[p]` and `[P]`
+    pub paused: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Conflicted,
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Untracked,
+}
+
+impl ChangeKind {
+    pub fn of(status: &FileStatus) -> Self {
+        match (status.index, status.worktree) {
+            ('?', '?') => ChangeKind::Untracked,
+            ('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => ChangeKind::Conflicted,
+            ('R', _) => ChangeKind::Renamed,
+            ('D', _) | (_, 'D') => ChangeKind::Deleted,
+            ('A', _) => ChangeKind::Added,
+            _ => ChangeKind::Modified,
+        }
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChangeKind::Conflicted => "conflicted",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Added => "added",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::Renamed => "renamed",
+            ChangeKind::Untracked => "untracked",
+        })
+    }
+}
+
+impl TaskCounts {
+    pub fn of(doc: &Document<'_>) -> Self {
+        let mut counts = TaskCounts::default();
+        let _ = counts.visit_document(doc);
+        counts
+    }
+}
+
+impl Visit<'_> for TaskCounts {
+    fn visit_tasklist_marker(&mut self, marker: char) -> ControlFlow<()> {
+        match marker {
+            ' ' => self.open += 1,
+            '.' => self.partial += 1,
+            'p' | 'P' => self.paused += 1,
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl fmt::Display for NamedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let default = if self.default { " (default)" } else { "" };
+        writeln!(f, "{}{default}: {}", self.name, self.path.display())?;
+        if let Some(status) = &self.status {
+            for line in status.to_string().lines() {
+                writeln!(f, "  {line}")?;
+            }
+        }
+        if let Some(error) = &self.error {
+            writeln!(f, "  Unavailable: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Group changed files by the kind of change
+pub fn changes(statuses: Vec<FileStatus>) -> BTreeMap<ChangeKind, Vec<PathBuf>> {
+    let mut ret: BTreeMap<ChangeKind, Vec<PathBuf>> = BTreeMap::new();
+    for status in statuses {
+        ret.entry(ChangeKind::of(&status))
+            .or_default()
+            .push(status.path);
+    }
+    ret
+}
+
+impl fmt::Display for JournalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let present = if self.today_exists {
+            "present"
+        } else {
+            "missing, run `jt prep`"
+        };
+        writeln!(f, "Today's entry ({}): {present}", self.today)?;
+        match (self.last_entry, self.days_since_last_entry) {
+            (Some(last), Some(0)) => writeln!(f, "Last entry: {last} (today)")?,
+            (Some(last), Some(1)) => writeln!(f, "Last entry: {last} (1 day ago)")?,
+            (Some(last), Some(days)) => writeln!(f, "Last entry: {last} ({days} days ago)")?,
+            _ => writeln!(f, "Last entry: none")?,
+        }
+        if self.last_entry.is_some() {
+            let TaskCounts {
+                open,
+                partial,
+                paused,
+            } = self.tasks;
+            writeln!(f, "Tasks: {open} open, {partial} partial, {paused} paused")?;
+        }
+        if self.changes.is_empty() {
+            writeln!(f, "Changes: none")?;
+        } else {
+            writeln!(f, "Changes:")?;
+            for (kind, paths) in &self.changes {
+                for path in paths {
+                    writeln!(f, "  {kind}: {}", path.display())?;
+                }
+            }
+        }
+        match &self.upstream {
+            Some(Upstream {
+                name,
+                ahead,
+                behind,
+            }) => writeln!(f, "Upstream {name}: {ahead} ahead, {behind} behind"),
+            None => writeln!(f, "Upstream: none configured"),
+        }
+    }
+}
@@ -0,0 +1,393 @@
+//! Version control of journals
+//!
+//! Journals are normally kept in git, but anything implementing [`Vcs`]
+//! will do.  Which backend is used is chosen by the `vcs` setting in the
+//! journal configuration, so a journal without version control says so
+//! explicitly rather than having every operation quietly do nothing.
+
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use eyre::{bail, Result};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::git::Git;
+
+/// The state of a changed file, as in `git status --porcelain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStatus {
+    /// The state of the file in the index
+    pub index: char,
+    /// The state of the file in the working tree
+    pub worktree: char,
+    pub path: PathBuf,
+}
+
+/// A commit in the history of the journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    pub id: String,
+    pub time: OffsetDateTime,
+    pub summary: String,
+}
+
+/// How a journal compares with the branch it tracks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Upstream {
+    pub name: String,
+    /// Commits here which aren't upstream
+    pub ahead: usize,
+    /// Commits upstream which aren't here
+    pub behind: usize,
+}
+
+/// Operations on the version control holding a journal
+///
+/// Paths are relative to the base of the journal.
+pub trait Vcs {
+    fn init(&self) -> Result<()>;
+    fn add(&self, path: &Path) -> Result<()>;
+    fn status(&self) -> Result<Vec<FileStatus>>;
+    fn commit(&self, message: &str) -> Result<()>;
+    /// Revisions affecting `path`, or the whole journal, newest first
+    fn log(&self, path: Option<&Path>) -> Result<Vec<Revision>>;
+    /// The content of a file at a revision, if it existed then
+    fn show(&self, revision: &str, path: &Path) -> Result<Option<String>>;
+    /// The content of a file as staged for the next commit, if it is there
+    fn staged(&self, path: &Path) -> Result<Option<String>>;
+    /// The upstream being tracked, if there is one
+    fn upstream(&self) -> Result<Option<Upstream>>;
+    /// Have files matching `pattern` merged by running `command`
+    fn set_merge_driver(&self, pattern: &str, command: &str) -> Result<()>;
+    /// Where hooks are run from, if there are such things
+    fn hooks_dir(&self) -> Result<Option<PathBuf>>;
+    /// Merge the changes from `base` to `theirs` into `ours` line by line,
+    /// as `git merge-file` does, leaving conflict markers where they clash
+    ///
+    /// These are paths to files anywhere, not just in the journal.  Returns
+    /// whether there were any conflicts.
+    fn merge_file(&self, ours: &Path, base: &Path, theirs: &Path) -> Result<bool>;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum VcsKind {
+    #[default]
+    Git,
+    /// No version control at all
+    None,
+}
+
+/// The backend of the given kind for the journal at `base`
+pub fn open(kind: VcsKind, base: &Path) -> Box<dyn Vcs> {
+    match kind {
+        VcsKind::Git => Box::new(Git::new(base)),
+        VcsKind::None => Box::new(NoVcs),
+    }
+}
+
+/// For journals which aren't kept in version control
+///
+/// There is never anything to report, and adding and committing do nothing.
+pub struct NoVcs;
+
+impl Vcs for NoVcs {
+    fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn add(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn status(&self) -> Result<Vec<FileStatus>> {
+        Ok(Vec::new())
+    }
+
+    fn commit(&self, _message: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn log(&self, _path: Option<&Path>) -> Result<Vec<Revision>> {
+        Ok(Vec::new())
+    }
+
+    fn show(&self, _revision: &str, _path: &Path) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn staged(&self, _path: &Path) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn upstream(&self) -> Result<Option<Upstream>> {
+        Ok(None)
+    }
+
+    fn set_merge_driver(&self, _pattern: &str, _command: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn hooks_dir(&self) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
+
+    fn merge_file(&self, _ours: &Path, _base: &Path, _theirs: &Path) -> Result<bool> {
+        bail!("Unable to merge files in a journal without version control")
+    }
+}
+
+#[cfg(test)]
+pub mod fake {
+    //! An in-memory [`Vcs`] for tests
+    //!
+    //! Files are read from disk when they're added, and everything else
+    //! happens in memory.  Revision ids are simply `r1`, `r2` and so on.
+
+    use std::{
+        cell::RefCell,
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    };
+
+    use eyre::{bail, Context, Result};
+    use time::{Duration, OffsetDateTime};
+
+    use super::{FileStatus, Revision, Upstream, Vcs};
+
+    struct Commit {
+        revision: Revision,
+        files: BTreeMap<PathBuf, String>,
+    }
+
+    #[derive(Default)]
+    struct State {
+        initialised: bool,
+        staged: BTreeMap<PathBuf, String>,
+        /// The pattern and command given to `set_merge_driver`
+        merge_driver: Option<(String, String)>,
+        /// Oldest first
+        commits: Vec<Commit>,
+    }
+
+    pub struct FakeVcs {
+        base: PathBuf,
+        state: RefCell<State>,
+    }
+
+    impl FakeVcs {
+        pub fn new(base: impl AsRef<Path>) -> Self {
+            Self {
+                base: base.as_ref().to_path_buf(),
+                state: RefCell::default(),
+            }
+        }
+
+        pub fn initialised(&self) -> bool {
+            self.state.borrow().initialised
+        }
+
+        /// The files in the most recent commit
+        pub fn committed(&self) -> Vec<PathBuf> {
+            self.state
+                .borrow()
+                .commits
+                .last()
+                .map(|c| c.files.keys().cloned().collect())
+                .unwrap_or_default()
+        }
+
+        pub fn merge_driver(&self) -> Option<(String, String)> {
+            self.state.borrow().merge_driver.clone()
+        }
+
+        fn check(&self) -> Result<()> {
+            if !self.initialised() {
+                bail!("Not a repository: {}", self.base.display());
+            }
+            Ok(())
+        }
+
+        fn head(&self) -> BTreeMap<PathBuf, String> {
+            self.state
+                .borrow()
+                .commits
+                .last()
+                .map(|c| c.files.clone())
+                .unwrap_or_default()
+        }
+    }
+
+    impl Vcs for FakeVcs {
+        fn init(&self) -> Result<()> {
+            self.state.borrow_mut().initialised = true;
+            Ok(())
+        }
+
+        fn add(&self, path: &Path) -> Result<()> {
+            self.check()?;
+            let full = self.base.join(path);
+            let content = std::fs::read_to_string(&full)
+                .with_context(|| format!("Reading {} to add it", full.display()))?;
+            self.state
+                .borrow_mut()
+                .staged
+                .insert(path.to_path_buf(), content);
+            Ok(())
+        }
+
+        fn status(&self) -> Result<Vec<FileStatus>> {
+            self.check()?;
+            let head = self.head();
+            Ok(self
+                .state
+                .borrow()
+                .staged
+                .keys()
+                .map(|path| FileStatus {
+                    index: if head.contains_key(path) { 'M' } else { 'A' },
+                    worktree: ' ',
+                    path: path.clone(),
+                })
+                .collect())
+        }
+
+        fn commit(&self, message: &str) -> Result<()> {
+            self.check()?;
+            let mut files = self.head();
+            let mut state = self.state.borrow_mut();
+            if state.staged.is_empty() {
+                bail!("Nothing to commit");
+            }
+            files.append(&mut state.staged);
+            let n = state.commits.len() + 1;
+            // Commits are a minute apart, so that ordering by time is stable
+            let time = OffsetDateTime::UNIX_EPOCH + Duration::minutes(n as i64);
+            state.commits.push(Commit {
+                revision: Revision {
+                    id: format!("r{n}"),
+                    time,
+                    summary: message.lines().next().unwrap_or_default().to_string(),
+                },
+                files,
+            });
+            Ok(())
+        }
+
+        fn log(&self, path: Option<&Path>) -> Result<Vec<Revision>> {
+            self.check()?;
+            let state = self.state.borrow();
+            let mut previous: Option<&BTreeMap<PathBuf, String>> = None;
+            let mut ret = Vec::new();
+            for commit in &state.commits {
+                let touched = match path {
+                    None => true,
+                    Some(path) => {
+                        commit.files.get(path) != previous.and_then(|files| files.get(path))
+                    }
+                };
+                if touched {
+                    ret.push(commit.revision.clone());
+                }
+                previous = Some(&commit.files);
+            }
+            ret.reverse();
+            Ok(ret)
+        }
+
+        fn show(&self, revision: &str, path: &Path) -> Result<Option<String>> {
+            self.check()?;
+            let state = self.state.borrow();
+            let Some(commit) = state.commits.iter().find(|c| c.revision.id == revision) else {
+                bail!("Unknown revision {revision}");
+            };
+            Ok(commit.files.get(path).cloned())
+        }
+
+        fn staged(&self, path: &Path) -> Result<Option<String>> {
+            self.check()?;
+            match self.state.borrow().staged.get(path) {
+                Some(content) => Ok(Some(content.clone())),
+                None => Ok(self.head().get(path).cloned()),
+            }
+        }
+
+        fn upstream(&self) -> Result<Option<Upstream>> {
+            self.check()?;
+            Ok(None)
+        }
+
+        fn set_merge_driver(&self, pattern: &str, command: &str) -> Result<()> {
+            self.check()?;
+            self.state.borrow_mut().merge_driver = Some((pattern.into(), command.into()));
+            Ok(())
+        }
+
+        fn hooks_dir(&self) -> Result<Option<PathBuf>> {
+            self.check()?;
+            Ok(Some(self.base.join(".fake").join("hooks")))
+        }
+
+        /// Whole files only: either side's if the other is unchanged,
+        /// otherwise both between conflict markers
+        fn merge_file(&self, ours: &Path, base: &Path, theirs: &Path) -> Result<bool> {
+            let read = |path: &Path| {
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Reading {} to merge it", path.display()))
+            };
+            let (mine, old, other) = (read(ours)?, read(base)?, read(theirs)?);
+            let (merged, conflicted) = if mine == other || other == old {
+                return Ok(false);
+            } else if mine == old {
+                (other, false)
+            } else {
+                (
+                    format!("<<<<<<< ours\n{mine}=======\n{other}>>>>>>> theirs\n"),
+                    true,
+                )
+            };
+            std::fs::write(ours, merged)
+                .with_context(|| format!("Writing the merge to {}", ours.display()))?;
+            Ok(conflicted)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fake::FakeVcs, *};
+
+    #[test]
+    fn fake_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let vcs = FakeVcs::new(dir.path());
+        let (a, b) = (Path::new("a.md"), Path::new("b.md"));
+        assert!(vcs.add(a).is_err());
+        vcs.init().unwrap();
+
+        std::fs::write(dir.path().join(a), "one").unwrap();
+        vcs.add(a).unwrap();
+        assert_eq!(vcs.status().unwrap()[0].index, 'A');
+        vcs.commit("First").unwrap();
+        std::fs::write(dir.path().join(b), "other").unwrap();
+        vcs.add(b).unwrap();
+        vcs.commit("Second").unwrap();
+        std::fs::write(dir.path().join(a), "two").unwrap();
+        vcs.add(a).unwrap();
+        assert_eq!(vcs.status().unwrap()[0].index, 'M');
+        vcs.commit("Third\n\nWith more detail").unwrap();
+        assert!(vcs.status().unwrap().is_empty());
+
+        let summaries =
+            |revisions: Vec<Revision>| revisions.into_iter().map(|r| r.summary).collect::<Vec<_>>();
+        assert_eq!(
+            summaries(vcs.log(None).unwrap()),
+            ["Third", "Second", "First"]
+        );
+        assert_eq!(summaries(vcs.log(Some(a)).unwrap()), ["Third", "First"]);
+        assert_eq!(vcs.show("r1", a).unwrap().as_deref(), Some("one"));
+        assert_eq!(vcs.show("r3", a).unwrap().as_deref(), Some("two"));
+        assert_eq!(vcs.show("r1", b).unwrap(), None);
+    }
+}